@@ -8,6 +8,7 @@ mod url_encoded;
 pub use url_encoded::UrlEncode;
 
 use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
 
 #[repr(u8)]
 #[derive(Serialize, Deserialize, Copy, Clone, Hash, Eq, PartialEq, Debug)]
@@ -24,8 +25,8 @@ pub enum Status {
     Archived = 8,
 }
 
-impl ToString for Status {
-    fn to_string(&self) -> String {
-        (*self as u8).to_string()
+impl Display for Status {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", *self as u8)
     }
 }
@@ -5,8 +5,9 @@ use url::UrlQuery;
 
 pub mod measurements;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Default)]
 pub enum DateFormat {
+    #[default]
     Unix,
     Json,
     Iso8601,
@@ -26,17 +27,15 @@ impl UrlEncode for DateFormat {
     }
 }
 
-impl Default for DateFormat {
-    fn default() -> Self {
-        DateFormat::Unix
-    }
-}
-
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub enum ResponseFormat {
     API,
+    // TODO: Maybe switch to TXT?
+    #[default]
     JSON,
-    JSONP { callback: String },
+    JSONP {
+        callback: String,
+    },
     TXT,
 }
 
@@ -60,13 +59,6 @@ impl UrlEncode for ResponseFormat {
     }
 }
 
-impl Default for ResponseFormat {
-    fn default() -> Self {
-        // TODO: Maybe switch to TXT?
-        ResponseFormat::JSON
-    }
-}
-
 #[derive(Debug, Clone)]
 pub enum SearchCondition {
     Exact(String),
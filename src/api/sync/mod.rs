@@ -3,6 +3,7 @@ use crate::api::response::measurements::Measurement;
 use crate::api::response::PagedResults;
 use crate::api::UrlEncode;
 
+#[allow(clippy::result_large_err)]
 pub fn get_measurements(
     page: u64,
     options: Option<MeasurementQueryOptions>,
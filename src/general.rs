@@ -58,3 +58,49 @@ pub type UnixTimestamp = i64;
 
 #[cfg(feature = "chrono")]
 pub type UnixTimestamp = chrono::DateTime<chrono::Utc>;
+
+/// Get the number of seconds since the unix epoch from a timestamp regardless of if the `chrono`
+/// feature is enabled.
+#[cfg(not(feature = "chrono"))]
+pub fn unix_seconds(timestamp: UnixTimestamp) -> i64 {
+    timestamp
+}
+
+#[cfg(feature = "chrono")]
+pub fn unix_seconds(timestamp: UnixTimestamp) -> i64 {
+    timestamp.timestamp()
+}
+
+/// Create a timestamp from a number of seconds since the unix epoch. This is the inverse of
+/// [`unix_seconds`]. Returns `None` if the `chrono` feature is enabled and the time is outside of
+/// the range supported by `DateTime<Utc>`.
+#[cfg(not(feature = "chrono"))]
+pub fn from_unix_seconds(seconds: i64) -> Option<UnixTimestamp> {
+    Some(seconds)
+}
+
+#[cfg(feature = "chrono")]
+pub fn from_unix_seconds(seconds: i64) -> Option<UnixTimestamp> {
+    use chrono::TimeZone;
+
+    chrono::Utc.timestamp_opt(seconds, 0).single()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_unix_seconds() {
+        for seconds in [0, 1669852800, -86400] {
+            assert_eq!(from_unix_seconds(seconds).map(unix_seconds), Some(seconds));
+        }
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn rejects_unsupported_times() {
+        assert_eq!(from_unix_seconds(i64::MAX), None);
+        assert_eq!(from_unix_seconds(i64::MIN), None);
+    }
+}
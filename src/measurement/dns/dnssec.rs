@@ -0,0 +1,1706 @@
+//! DNSSEC validation (RFC 4033, RFC 4034 and RFC 4035) of decoded DNS messages.
+//!
+//! Probes only report the answer to the query they sent, so the DNSKEY and DS records needed to
+//! build a chain of trust usually have to be supplied from other measurements using
+//! [`Validator::add_message`]. Signature validity is checked against the time of the measurement
+//! instead of the current time so old results can still be validated.
+//!
+//! Records without signatures are only insecure when no trust anchor covers them, or when a signed
+//! NSEC record proves the delegation to their zone has no DS records (RFC 4035 5.2). Otherwise they
+//! are bogus. Negative answers must include NSEC records proving the name or type does not exist;
+//! denial of existence using NSEC3 is not verified, so those answers are reported as insecure.
+//! Answers expanded from a wildcard likewise need an NSEC record proving that no closer match
+//! exists, and are bogus without one.
+//!
+//! Signatures using algorithms which are not supported are ignored. A zone is only insecure
+//! because of its algorithms when none of the algorithms in its authenticated DS records or trust
+//! anchors are supported (RFC 4035 5.2 and RFC 6840 5.11).
+use crate::general::{unix_seconds, UnixTimestamp};
+use crate::measurement::dns::message::{
+    Dnskey, Ds, Message, Name, Record, RecordData, RecordType, ResponseCode, Rrsig,
+};
+use ring::digest;
+use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+use std::cmp::Ordering;
+use std::fmt::{self, Display, Formatter};
+
+/// A key which is trusted without needing to be validated through its parent zone. This will
+/// usually be the DS record of the root zone as published by IANA.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TrustAnchor {
+    Ds { owner: Name, ds: Ds },
+    Dnskey { owner: Name, key: Dnskey },
+}
+
+impl TrustAnchor {
+    fn owner(&self) -> &Name {
+        match self {
+            TrustAnchor::Ds { owner, .. } => owner,
+            TrustAnchor::Dnskey { owner, .. } => owner,
+        }
+    }
+}
+
+/// Outcome of validation as described in RFC 4033 5
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Security {
+    /// Every record has an unbroken chain of signatures back to a trust anchor
+    Secure,
+    /// The records could not be linked to a trust anchor, but there was no evidence of tampering
+    Insecure(InsecureReason),
+    /// A chain of trust should have been present, but could not be verified
+    Bogus(BogusReason),
+}
+
+impl Security {
+    pub fn is_secure(&self) -> bool {
+        matches!(self, Security::Secure)
+    }
+
+    pub fn is_bogus(&self) -> bool {
+        matches!(self, Security::Bogus(_))
+    }
+}
+
+impl Display for Security {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Security::Secure => write!(f, "secure"),
+            Security::Insecure(reason) => write!(f, "insecure: {}", reason),
+            Security::Bogus(reason) => write!(f, "bogus: {}", reason),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InsecureReason {
+    /// There were no signatures covering the RRset, and it belongs to an unsigned zone
+    Unsigned { name: Name, rtype: RecordType },
+    /// There was no trust anchor or DS RRset for the zone
+    NoTrustAnchor { zone: Name },
+    /// None of the algorithms used by the DS records or trust anchors of the zone are supported
+    /// by this library
+    UnsupportedAlgorithm { zone: Name, algorithm: u8 },
+    /// All of the DS records for the zone used digest types which are not supported
+    UnsupportedDigest { zone: Name, digest_type: u8 },
+    /// The negative answer only used NSEC3 records to deny the existence of the name or type, which
+    /// are not verified
+    UnsupportedDenial { name: Name, rtype: RecordType },
+}
+
+impl Display for InsecureReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            InsecureReason::Unsigned { name, rtype } => {
+                write!(f, "no signatures for {} {}", name, rtype)
+            }
+            InsecureReason::NoTrustAnchor { zone } => {
+                write!(f, "no trust anchor or DS records for zone {}", zone)
+            }
+            InsecureReason::UnsupportedAlgorithm { zone, algorithm } => {
+                write!(
+                    f,
+                    "unsupported algorithm {} used by zone {}",
+                    algorithm, zone
+                )
+            }
+            InsecureReason::UnsupportedDigest { zone, digest_type } => {
+                write!(
+                    f,
+                    "unsupported DS digest type {} for zone {}",
+                    digest_type, zone
+                )
+            }
+            InsecureReason::UnsupportedDenial { name, rtype } => {
+                write!(f, "unverified NSEC3 denial of {} {}", name, rtype)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BogusReason {
+    /// The DNSKEY RRset of the zone was not available or did not contain the signing key
+    MissingDnskey { zone: Name, key_tag: u16 },
+    /// None of the keys in the DNSKEY RRset of the zone matched its DS records or trust anchors
+    NoMatchingDs { zone: Name },
+    /// The zone is below a trust anchor, but there were no DS records for it and no proof that its
+    /// delegation is unsigned
+    MissingDs { zone: Name },
+    /// The RRset is below a trust anchor, but had no signatures and no proof that its zone is
+    /// unsigned
+    MissingSignature { name: Name, rtype: RecordType },
+    /// The RRset was expanded from a wildcard, but there was no NSEC record proving that no closer
+    /// match for the name exists
+    MissingWildcardProof { name: Name, rtype: RecordType },
+    /// The answer was empty, but there were no NSEC records proving the name or type does not
+    /// exist
+    MissingDenial { name: Name, rtype: RecordType },
+    /// The signer of an RRSIG was not the zone containing the records it covers
+    InvalidSigner { name: Name, signer: Name },
+    /// The measurement took place after the signature expired
+    SignatureExpired {
+        name: Name,
+        rtype: RecordType,
+        expiration: u32,
+    },
+    /// The measurement took place before the signature inception time
+    SignatureNotYetValid {
+        name: Name,
+        rtype: RecordType,
+        inception: u32,
+    },
+    /// The signature did not match the records it covers
+    InvalidSignature {
+        name: Name,
+        rtype: RecordType,
+        key_tag: u16,
+    },
+}
+
+impl Display for BogusReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BogusReason::MissingDnskey { zone, key_tag } => {
+                write!(f, "missing DNSKEY {} for zone {}", key_tag, zone)
+            }
+            BogusReason::NoMatchingDs { zone } => {
+                write!(f, "no DNSKEY for zone {} matches a DS record", zone)
+            }
+            BogusReason::MissingDs { zone } => {
+                write!(f, "missing DS records for zone {}", zone)
+            }
+            BogusReason::MissingSignature { name, rtype } => {
+                write!(f, "no signatures for {} {}", name, rtype)
+            }
+            BogusReason::MissingWildcardProof { name, rtype } => {
+                write!(
+                    f,
+                    "no proof that {} {} has no closer match than a wildcard",
+                    name, rtype
+                )
+            }
+            BogusReason::MissingDenial { name, rtype } => {
+                write!(f, "no proof that {} {} does not exist", name, rtype)
+            }
+            BogusReason::InvalidSigner { name, signer } => {
+                write!(f, "{} can not be signed by {}", name, signer)
+            }
+            BogusReason::SignatureExpired {
+                name,
+                rtype,
+                expiration,
+            } => write!(
+                f,
+                "signature for {} {} expired at {}",
+                name, rtype, expiration
+            ),
+            BogusReason::SignatureNotYetValid {
+                name,
+                rtype,
+                inception,
+            } => write!(
+                f,
+                "signature for {} {} is not valid until {}",
+                name, rtype, inception
+            ),
+            BogusReason::InvalidSignature {
+                name,
+                rtype,
+                key_tag,
+            } => write!(
+                f,
+                "signature for {} {} by key {} does not match",
+                name, rtype, key_tag
+            ),
+        }
+    }
+}
+
+/// Validates messages against a set of trust anchors and any supplemental DNSKEY, DS, NSEC and
+/// RRSIG records which were provided.
+#[derive(Clone, Debug, Default)]
+pub struct Validator {
+    anchors: Vec<TrustAnchor>,
+    records: Vec<Record>,
+}
+
+impl Validator {
+    pub fn new(anchors: Vec<TrustAnchor>) -> Self {
+        Validator {
+            anchors,
+            records: Vec::new(),
+        }
+    }
+
+    pub fn add_anchor(&mut self, anchor: TrustAnchor) {
+        self.anchors.push(anchor);
+    }
+
+    /// Make the DNSKEY, DS, NSEC and RRSIG records of another message available when building
+    /// chains of trust. This is usually the answer to a DNSKEY or DS query for one of the zones
+    /// leading up to the name being validated. A negative answer to a DS query proves that the
+    /// delegation to a zone is unsigned.
+    pub fn add_message(&mut self, message: &Message) {
+        self.records.extend(
+            message
+                .records()
+                .filter(|record| {
+                    matches!(
+                        record.rtype,
+                        RecordType::DNSKEY | RecordType::DS | RecordType::NSEC | RecordType::RRSIG
+                    )
+                })
+                .cloned(),
+        );
+    }
+
+    /// Validate the answer section of a message. When multiple RRsets are present, the least
+    /// secure outcome is returned.
+    ///
+    /// Negative answers are validated using the authority section, and are only secure when the
+    /// NSEC records in it prove that the name or type in the question does not exist (RFC 4035
+    /// 5.4). A message without any records is never secure.
+    pub fn validate(&self, message: &Message, time: UnixTimestamp) -> Security {
+        let pool: Vec<&Record> = message.records().chain(&self.records).collect();
+        let context = Context {
+            validator: self,
+            pool: &pool,
+            time: unix_seconds(time) as u32,
+        };
+
+        let (name, rtype) = match message.questions.first() {
+            Some(question) => (question.name.clone(), question.qtype),
+            None => (Name::root(), RecordType::ANY),
+        };
+
+        if !message.answers.is_empty() {
+            return context.verify_section(&message.answers, &name, rtype);
+        }
+
+        match context.verify_section(&message.authorities, &name, rtype) {
+            Security::Secure => {}
+            status => return status,
+        }
+
+        let nxdomain = message.response_code() == ResponseCode::NXDomain;
+        if proves_denial(&message.authorities, &name, rtype, nxdomain) {
+            return Security::Secure;
+        }
+
+        match message
+            .authorities
+            .iter()
+            .any(|record| record.rtype == RecordType::NSEC3)
+        {
+            true => Security::Insecure(InsecureReason::UnsupportedDenial { name, rtype }),
+            false => Security::Bogus(BogusReason::MissingDenial { name, rtype }),
+        }
+    }
+}
+
+/// State used while validating a single message
+struct Context<'a> {
+    validator: &'a Validator,
+    pool: &'a [&'a Record],
+    /// time of the measurement in seconds since the epoch, modulo 2^32
+    time: u32,
+}
+
+impl<'a> Context<'a> {
+    /// Verify every RRset in a section. A section without any RRsets is treated like an unsigned
+    /// RRset for the name and type in the question.
+    fn verify_section(&self, records: &[Record], name: &Name, rtype: RecordType) -> Security {
+        let mut outcome = None;
+        for rrset in group_rrsets(records) {
+            match self.verify_rrset(&rrset) {
+                Security::Secure => {
+                    outcome.get_or_insert(Security::Secure);
+                }
+                bogus @ Security::Bogus(_) => return bogus,
+                insecure => outcome = Some(insecure),
+            }
+        }
+
+        outcome.unwrap_or_else(|| match self.is_insecure(name) {
+            true => Security::Insecure(InsecureReason::Unsigned {
+                name: name.clone(),
+                rtype,
+            }),
+            false => Security::Bogus(BogusReason::MissingDenial {
+                name: name.clone(),
+                rtype,
+            }),
+        })
+    }
+
+    fn rrset(&self, name: &Name, rtype: RecordType) -> Vec<&'a Record> {
+        self.pool
+            .iter()
+            .copied()
+            .filter(|record| record.rtype == rtype && record.name == *name)
+            .collect()
+    }
+
+    fn signatures(&self, name: &Name, rtype: RecordType) -> impl Iterator<Item = &'a Rrsig> {
+        let name = name.clone();
+        self.pool
+            .iter()
+            .filter(move |record| record.name == name)
+            .filter_map(move |record| match &record.data {
+                RecordData::Rrsig(sig) if sig.type_covered == rtype => Some(sig),
+                _ => None,
+            })
+    }
+
+    /// Verify an RRset using any of the RRSIGs covering it (RFC 4035 5.3)
+    fn verify_rrset(&self, rrset: &[&Record]) -> Security {
+        let name = &rrset[0].name;
+        let rtype = rrset[0].rtype;
+
+        let mut bogus = None;
+        let mut insecure = None;
+
+        for sig in self.signatures(name, rtype) {
+            // The DNSKEY RRset is signed by the zone itself, but a DS RRset must be signed by the
+            // parent. Requiring this also ensures the chain always moves up towards the root.
+            let valid_signer = name.is_subdomain_of(&sig.signer_name)
+                && !(rtype == RecordType::DS && sig.signer_name == *name);
+            if !valid_signer || usize::from(sig.labels) > wildcard_labels(name) {
+                bogus = Some(BogusReason::InvalidSigner {
+                    name: name.clone(),
+                    signer: sig.signer_name.clone(),
+                });
+                continue;
+            }
+
+            // Whether the zone may use an unsupported algorithm is decided by its DS records, since
+            // anyone can add a signature using an algorithm which is not supported
+            if !is_supported_algorithm(sig.algorithm) {
+                match self.entry_keys(&sig.signer_name) {
+                    Err(Security::Insecure(reason)) => insecure = Some(reason),
+                    Err(Security::Bogus(reason)) => bogus = Some(reason),
+                    _ => {}
+                }
+                continue;
+            }
+
+            if serial_lt(self.time, sig.inception) {
+                bogus = Some(BogusReason::SignatureNotYetValid {
+                    name: name.clone(),
+                    rtype,
+                    inception: sig.inception,
+                });
+                continue;
+            }
+
+            if serial_lt(sig.expiration, self.time) {
+                bogus = Some(BogusReason::SignatureExpired {
+                    name: name.clone(),
+                    rtype,
+                    expiration: sig.expiration,
+                });
+                continue;
+            }
+
+            let keys = match rtype == RecordType::DNSKEY && sig.signer_name == *name {
+                true => self.entry_keys(name),
+                false => self.zone_keys(&sig.signer_name),
+            };
+
+            let keys = match keys {
+                Ok(keys) => keys,
+                Err(Security::Bogus(reason)) => {
+                    bogus = Some(reason);
+                    continue;
+                }
+                Err(Security::Insecure(reason)) => {
+                    insecure = Some(reason);
+                    continue;
+                }
+                Err(Security::Secure) => unreachable!(),
+            };
+
+            let signed_data = signed_data(sig, rrset);
+            let mut candidates = keys
+                .iter()
+                .filter(|key| key.algorithm == sig.algorithm && key.key_tag() == sig.key_tag)
+                .peekable();
+
+            if candidates.peek().is_none() {
+                bogus = Some(BogusReason::MissingDnskey {
+                    zone: sig.signer_name.clone(),
+                    key_tag: sig.key_tag,
+                });
+                continue;
+            }
+
+            if candidates.any(|key| verify_signature(key, &signed_data, &sig.signature)) {
+                if usize::from(sig.labels) < wildcard_labels(name)
+                    && !self.proves_no_closer_match(name, sig)
+                {
+                    bogus = Some(BogusReason::MissingWildcardProof {
+                        name: name.clone(),
+                        rtype,
+                    });
+                    continue;
+                }
+
+                return Security::Secure;
+            }
+
+            bogus = Some(BogusReason::InvalidSignature {
+                name: name.clone(),
+                rtype,
+                key_tag: sig.key_tag,
+            });
+        }
+
+        match (bogus, insecure) {
+            (Some(reason), _) => Security::Bogus(reason),
+            (None, Some(reason)) => Security::Insecure(reason),
+            (None, None) if self.is_insecure(name) => {
+                Security::Insecure(InsecureReason::Unsigned {
+                    name: name.clone(),
+                    rtype,
+                })
+            }
+            (None, None) => Security::Bogus(BogusReason::MissingSignature {
+                name: name.clone(),
+                rtype,
+            }),
+        }
+    }
+
+    /// Check for a validated NSEC record from the signer of a wildcard expansion which proves that
+    /// the name one label below the wildcard does not exist, so there was no closer match for the
+    /// name than the wildcard (RFC 4035 5.3.4)
+    fn proves_no_closer_match(&self, name: &Name, sig: &Rrsig) -> bool {
+        let skip = name.labels().len() - usize::from(sig.labels) - 1;
+        let next_closer = Name::from_labels(name.labels()[skip..].to_vec());
+
+        self.pool.iter().any(|record| match &record.data {
+            RecordData::Nsec {
+                next_domain_name,
+                types,
+            } => {
+                let nsec = Nsec {
+                    owner: &record.name,
+                    next: next_domain_name,
+                    types,
+                };
+
+                let mut signatures = self.signatures(&record.name, RecordType::NSEC).peekable();
+                nsec.covers(&next_closer)
+                    && signatures.peek().is_some()
+                    && signatures.all(|nsec_sig| nsec_sig.signer_name == sig.signer_name)
+                    && self
+                        .verify_rrset(&self.rrset(&record.name, RecordType::NSEC))
+                        .is_secure()
+            }
+            _ => false,
+        })
+    }
+
+    /// Get the validated zone keys of a zone which may be used to sign its records.
+    fn zone_keys(&self, zone: &Name) -> Result<Vec<Dnskey>, Security> {
+        let rrset = self.rrset(zone, RecordType::DNSKEY);
+
+        if rrset.is_empty() {
+            // Keys configured as trust anchors can still be used directly
+            let anchors = self.anchor_keys(zone);
+            if !anchors.is_empty() {
+                return Ok(anchors);
+            }
+
+            // Leave it to the caller to report the key which was missing
+            if self.has_chain(zone) || !self.is_insecure(zone) {
+                return Ok(Vec::new());
+            }
+
+            return Err(Security::Insecure(InsecureReason::NoTrustAnchor {
+                zone: zone.clone(),
+            }));
+        }
+
+        match self.verify_rrset(&rrset) {
+            Security::Secure => Ok(rrset
+                .into_iter()
+                .filter_map(|record| match &record.data {
+                    RecordData::Dnskey(key) if key.is_zone_key() => Some(key.clone()),
+                    _ => None,
+                })
+                .collect()),
+            status => Err(status),
+        }
+    }
+
+    /// Get the keys which are allowed to sign the DNSKEY RRset of a zone. These are either trust
+    /// anchors or keys which match an authenticated DS record.
+    fn entry_keys(&self, zone: &Name) -> Result<Vec<Dnskey>, Security> {
+        let anchors = self.anchor_keys(zone);
+        if !anchors.is_empty() {
+            return Ok(anchors);
+        }
+
+        let mut ds_records: Vec<Ds> = self
+            .validator
+            .anchors
+            .iter()
+            .filter_map(|anchor| match anchor {
+                TrustAnchor::Ds { owner, ds } if owner == zone => Some(ds.clone()),
+                _ => None,
+            })
+            .collect();
+
+        if ds_records.is_empty() {
+            let rrset = self.rrset(zone, RecordType::DS);
+            if rrset.is_empty() {
+                return Err(match self.is_insecure(zone) {
+                    true => {
+                        Security::Insecure(InsecureReason::NoTrustAnchor { zone: zone.clone() })
+                    }
+                    false => Security::Bogus(BogusReason::MissingDs { zone: zone.clone() }),
+                });
+            }
+
+            match self.verify_rrset(&rrset) {
+                Security::Secure => {}
+                status => return Err(status),
+            }
+
+            ds_records.extend(rrset.into_iter().filter_map(|record| match &record.data {
+                RecordData::Ds(ds) => Some(ds.clone()),
+                _ => None,
+            }));
+        }
+
+        let supported: Vec<&Ds> = ds_records
+            .iter()
+            .filter(|ds| ds_digest_algorithm(ds.digest_type).is_some())
+            .filter(|ds| is_supported_algorithm(ds.algorithm))
+            .collect();
+
+        if supported.is_empty() {
+            let ds = &ds_records[0];
+            return Err(Security::Insecure(
+                match ds_digest_algorithm(ds.digest_type) {
+                    None => InsecureReason::UnsupportedDigest {
+                        zone: zone.clone(),
+                        digest_type: ds.digest_type,
+                    },
+                    Some(_) => InsecureReason::UnsupportedAlgorithm {
+                        zone: zone.clone(),
+                        algorithm: ds.algorithm,
+                    },
+                },
+            ));
+        }
+
+        let keys: Vec<Dnskey> = self
+            .rrset(zone, RecordType::DNSKEY)
+            .into_iter()
+            .filter_map(|record| match &record.data {
+                RecordData::Dnskey(key) => Some(key),
+                _ => None,
+            })
+            .filter(|key| supported.iter().any(|ds| ds_matches(zone, key, ds)))
+            .cloned()
+            .collect();
+
+        if keys.is_empty() {
+            return Err(Security::Bogus(BogusReason::NoMatchingDs {
+                zone: zone.clone(),
+            }));
+        }
+
+        Ok(keys)
+    }
+
+    fn anchor_keys(&self, zone: &Name) -> Vec<Dnskey> {
+        self.validator
+            .anchors
+            .iter()
+            .filter_map(|anchor| match anchor {
+                TrustAnchor::Dnskey { owner, key } if owner == zone => Some(key.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Check if there is a trust anchor or DS record which would allow the zone to be secure.
+    fn has_chain(&self, zone: &Name) -> bool {
+        self.validator
+            .anchors
+            .iter()
+            .any(|anchor| anchor.owner() == zone)
+            || !self.rrset(zone, RecordType::DS).is_empty()
+    }
+
+    /// Check if records at a name may be unsigned. This is the case when neither the name nor any
+    /// zone above it has a trust anchor or DS records, or when a zone cut between the name and the
+    /// closest of these is proven to have no DS records (RFC 4035 5.2).
+    fn is_insecure(&self, name: &Name) -> bool {
+        let mut zone = name.clone();
+        loop {
+            if self.is_unsigned_delegation(&zone) {
+                return true;
+            }
+
+            if self.has_chain(&zone) {
+                return false;
+            }
+
+            match parent(&zone) {
+                Some(parent) => zone = parent,
+                None => return true,
+            }
+        }
+    }
+
+    /// Check for a validated NSEC record from the parent zone showing that a delegation exists,
+    /// but has no DS records
+    fn is_unsigned_delegation(&self, zone: &Name) -> bool {
+        let rrset = self.rrset(zone, RecordType::NSEC);
+        let delegation = rrset.iter().any(|record| match &record.data {
+            RecordData::Nsec { types, .. } => {
+                types.contains(&RecordType::NS)
+                    && !types.contains(&RecordType::DS)
+                    && !types.contains(&RecordType::SOA)
+            }
+            _ => false,
+        });
+
+        // Only signatures from a zone above the delegation count. This also stops the validation of
+        // the NSEC record from coming back to the same zone.
+        let mut signatures = self.signatures(zone, RecordType::NSEC).peekable();
+        delegation
+            && signatures.peek().is_some()
+            && signatures.all(|sig| sig.signer_name != *zone)
+            && self.verify_rrset(&rrset).is_secure()
+    }
+}
+
+/// Group records into RRsets while preserving the order in which they first appeared. RRSIG and
+/// OPT records are skipped since they are not signed themselves.
+fn group_rrsets(records: &[Record]) -> Vec<Vec<&Record>> {
+    let mut rrsets: Vec<Vec<&Record>> = Vec::new();
+
+    for record in records {
+        if matches!(record.rtype, RecordType::RRSIG | RecordType::OPT) {
+            continue;
+        }
+
+        let existing = rrsets.iter_mut().find(|rrset| {
+            rrset[0].rtype == record.rtype
+                && rrset[0].class == record.class
+                && rrset[0].name == record.name
+        });
+
+        match existing {
+            Some(rrset) => rrset.push(record),
+            None => rrsets.push(vec![record]),
+        }
+    }
+
+    rrsets
+}
+
+fn parent(name: &Name) -> Option<Name> {
+    match name.labels() {
+        [] => None,
+        [_, rest @ ..] => Some(Name::from_labels(rest.to_vec())),
+    }
+}
+
+/// Compare names in the canonical order used by NSEC records (RFC 4034 6.1)
+fn canonical_cmp(a: &Name, b: &Name) -> Ordering {
+    let labels = |name: &'_ Name| -> Vec<Vec<u8>> {
+        name.labels()
+            .iter()
+            .rev()
+            .map(|label| label.to_ascii_lowercase())
+            .collect()
+    };
+
+    labels(a).cmp(&labels(b))
+}
+
+/// An NSEC record proving that no names exist between its owner and the next name
+struct Nsec<'a> {
+    owner: &'a Name,
+    next: &'a Name,
+    types: &'a [RecordType],
+}
+
+impl<'a> Nsec<'a> {
+    /// The name falls strictly between the owner and the next name. The last NSEC record of a zone
+    /// wraps around to the zone apex.
+    fn covers(&self, name: &Name) -> bool {
+        let after_owner = canonical_cmp(self.owner, name) == Ordering::Less;
+        match canonical_cmp(self.owner, self.next) {
+            Ordering::Less => after_owner && canonical_cmp(name, self.next) == Ordering::Less,
+            _ => after_owner,
+        }
+    }
+
+    /// The name exists, but has no records of the type or a CNAME which could replace them
+    fn denies_type(&self, name: &Name, rtype: RecordType) -> bool {
+        self.owner == name
+            && !self.types.contains(&rtype)
+            && !self.types.contains(&RecordType::CNAME)
+    }
+
+    /// Closest name above the covered name which exists in the zone (RFC 4035 5.4). Both the owner
+    /// and the next name exist, so this is the longest ancestor shared with either of them.
+    fn closest_encloser(&self, name: &Name) -> Name {
+        let shared = |other: &Name| {
+            name.labels()
+                .iter()
+                .rev()
+                .zip(other.labels().iter().rev())
+                .take_while(|(a, b)| a.eq_ignore_ascii_case(b))
+                .count()
+        };
+
+        let length = shared(self.owner).max(shared(self.next));
+        Name::from_labels(name.labels()[name.labels().len() - length..].to_vec())
+    }
+}
+
+/// Check if the NSEC records in a section prove that a name does not exist, or that it has no
+/// records of a type (RFC 4035 5.4). Both also require proof that no wildcard could have been used
+/// to answer the query instead.
+fn proves_denial(records: &[Record], name: &Name, rtype: RecordType, nxdomain: bool) -> bool {
+    let nsecs: Vec<Nsec> = records
+        .iter()
+        .filter_map(|record| match &record.data {
+            RecordData::Nsec {
+                next_domain_name,
+                types,
+            } => Some(Nsec {
+                owner: &record.name,
+                next: next_domain_name,
+                types,
+            }),
+            _ => None,
+        })
+        .collect();
+
+    if !nxdomain && nsecs.iter().any(|nsec| nsec.denies_type(name, rtype)) {
+        return true;
+    }
+
+    let closest_encloser = match nsecs.iter().find(|nsec| nsec.covers(name)) {
+        Some(nsec) => nsec.closest_encloser(name),
+        None => return false,
+    };
+
+    let mut labels = vec![b"*".to_vec()];
+    labels.extend_from_slice(closest_encloser.labels());
+    let wildcard = Name::from_labels(labels);
+
+    match nxdomain {
+        true => nsecs.iter().any(|nsec| nsec.covers(&wildcard)),
+        false => nsecs.iter().any(|nsec| nsec.denies_type(&wildcard, rtype)),
+    }
+}
+
+/// Number of labels in a name as counted by the RRSIG labels field (RFC 4034 3.1.3)
+fn wildcard_labels(name: &Name) -> usize {
+    match name.labels().first() {
+        Some(label) if label.as_slice() == b"*" => name.labels().len() - 1,
+        _ => name.labels().len(),
+    }
+}
+
+/// Serial number comparison from RFC 1982 which is used for signature times (RFC 4034 3.1.5)
+fn serial_lt(a: u32, b: u32) -> bool {
+    a != b && (b.wrapping_sub(a) as i32) > 0
+}
+
+fn is_supported_algorithm(algorithm: u8) -> bool {
+    matches!(algorithm, 5 | 7 | 8 | 10 | 13 | 14 | 15)
+}
+
+fn ds_digest_algorithm(digest_type: u8) -> Option<&'static digest::Algorithm> {
+    match digest_type {
+        1 => Some(&digest::SHA1_FOR_LEGACY_USE_ONLY),
+        2 => Some(&digest::SHA256),
+        4 => Some(&digest::SHA384),
+        _ => None,
+    }
+}
+
+/// Check if a key matches a DS record (RFC 4034 5.1.4)
+fn ds_matches(owner: &Name, key: &Dnskey, ds: &Ds) -> bool {
+    let algorithm = match ds_digest_algorithm(ds.digest_type) {
+        Some(algorithm) => algorithm,
+        None => return false,
+    };
+
+    if key.algorithm != ds.algorithm || key.key_tag() != ds.key_tag {
+        return false;
+    }
+
+    let mut data = Vec::new();
+    owner.to_lowercase().write_wire(&mut data);
+    write_canonical_rdata(&RecordData::Dnskey(key.clone()), &mut data);
+
+    digest::digest(algorithm, &data).as_ref() == ds.digest.as_slice()
+}
+
+/// Build the data covered by a signature (RFC 4034 3.1.8.1)
+fn signed_data(sig: &Rrsig, rrset: &[&Record]) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(&u16::from(sig.type_covered).to_be_bytes());
+    data.push(sig.algorithm);
+    data.push(sig.labels);
+    data.extend_from_slice(&sig.original_ttl.to_be_bytes());
+    data.extend_from_slice(&sig.expiration.to_be_bytes());
+    data.extend_from_slice(&sig.inception.to_be_bytes());
+    data.extend_from_slice(&sig.key_tag.to_be_bytes());
+    sig.signer_name.to_lowercase().write_wire(&mut data);
+
+    // Wildcard expansions are signed using the wildcard owner name (RFC 4035 5.3.2)
+    let name = rrset[0].name.to_lowercase();
+    let mut owner = Vec::new();
+    if usize::from(sig.labels) < wildcard_labels(&name) {
+        let skip = name.labels().len() - usize::from(sig.labels);
+        let mut labels = vec![b"*".to_vec()];
+        labels.extend_from_slice(&name.labels()[skip..]);
+        Name::from_labels(labels).write_wire(&mut owner);
+    } else {
+        name.write_wire(&mut owner);
+    }
+
+    let mut rdatas: Vec<Vec<u8>> = rrset
+        .iter()
+        .map(|record| {
+            let mut rdata = Vec::new();
+            write_canonical_rdata(&record.data, &mut rdata);
+            rdata
+        })
+        .collect();
+    rdatas.sort_unstable();
+    rdatas.dedup();
+
+    let class = u16::from(rrset[0].class);
+    for rdata in rdatas {
+        data.extend_from_slice(&owner);
+        data.extend_from_slice(&u16::from(rrset[0].rtype).to_be_bytes());
+        data.extend_from_slice(&class.to_be_bytes());
+        data.extend_from_slice(&sig.original_ttl.to_be_bytes());
+        data.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        data.extend_from_slice(&rdata);
+    }
+
+    data
+}
+
+/// Write record data in the canonical form from RFC 4034 6.2 with the update from RFC 6840 5.1
+/// which stops names in NSEC records from being converted to lowercase.
+fn write_canonical_rdata(data: &RecordData, buffer: &mut Vec<u8>) {
+    let write_name = |name: &Name, buffer: &mut Vec<u8>| name.to_lowercase().write_wire(buffer);
+    let write_string = |string: &[u8], buffer: &mut Vec<u8>| {
+        buffer.push(string.len() as u8);
+        buffer.extend_from_slice(string);
+    };
+
+    match data {
+        RecordData::A(addr) => buffer.extend_from_slice(&addr.octets()),
+        RecordData::Aaaa(addr) => buffer.extend_from_slice(&addr.octets()),
+        RecordData::Ns(name) | RecordData::Cname(name) | RecordData::Ptr(name) => {
+            write_name(name, buffer)
+        }
+        RecordData::Mx {
+            preference,
+            exchange,
+        } => {
+            buffer.extend_from_slice(&preference.to_be_bytes());
+            write_name(exchange, buffer);
+        }
+        RecordData::Soa {
+            mname,
+            rname,
+            serial,
+            refresh,
+            retry,
+            expire,
+            minimum,
+        } => {
+            write_name(mname, buffer);
+            write_name(rname, buffer);
+            for value in [serial, refresh, retry, expire, minimum] {
+                buffer.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+        RecordData::Txt(strings) => {
+            for string in strings {
+                write_string(string, buffer);
+            }
+        }
+        RecordData::Srv {
+            priority,
+            weight,
+            port,
+            target,
+        } => {
+            for value in [priority, weight, port] {
+                buffer.extend_from_slice(&value.to_be_bytes());
+            }
+            write_name(target, buffer);
+        }
+        RecordData::Naptr {
+            order,
+            preference,
+            flags,
+            services,
+            regexp,
+            replacement,
+        } => {
+            buffer.extend_from_slice(&order.to_be_bytes());
+            buffer.extend_from_slice(&preference.to_be_bytes());
+            write_string(flags, buffer);
+            write_string(services, buffer);
+            write_string(regexp, buffer);
+            write_name(replacement, buffer);
+        }
+        RecordData::Ds(ds) => {
+            buffer.extend_from_slice(&ds.key_tag.to_be_bytes());
+            buffer.push(ds.algorithm);
+            buffer.push(ds.digest_type);
+            buffer.extend_from_slice(&ds.digest);
+        }
+        RecordData::Dnskey(key) => {
+            buffer.extend_from_slice(&key.flags.to_be_bytes());
+            buffer.push(key.protocol);
+            buffer.push(key.algorithm);
+            buffer.extend_from_slice(&key.public_key);
+        }
+        RecordData::Rrsig(sig) => {
+            buffer.extend_from_slice(&u16::from(sig.type_covered).to_be_bytes());
+            buffer.push(sig.algorithm);
+            buffer.push(sig.labels);
+            buffer.extend_from_slice(&sig.original_ttl.to_be_bytes());
+            buffer.extend_from_slice(&sig.expiration.to_be_bytes());
+            buffer.extend_from_slice(&sig.inception.to_be_bytes());
+            buffer.extend_from_slice(&sig.key_tag.to_be_bytes());
+            write_name(&sig.signer_name, buffer);
+            buffer.extend_from_slice(&sig.signature);
+        }
+        RecordData::Nsec {
+            next_domain_name,
+            types,
+        } => {
+            next_domain_name.write_wire(buffer);
+            write_type_bitmap(types, buffer);
+        }
+        RecordData::Tlsa {
+            usage,
+            selector,
+            matching_type,
+            data,
+        } => {
+            buffer.extend_from_slice(&[*usage, *selector, *matching_type]);
+            buffer.extend_from_slice(data);
+        }
+        RecordData::Opt(opt) => {
+            for option in &opt.options {
+                buffer.extend_from_slice(&option.code.to_be_bytes());
+                buffer.extend_from_slice(&(option.data.len() as u16).to_be_bytes());
+                buffer.extend_from_slice(&option.data);
+            }
+        }
+        RecordData::Unknown(data) => buffer.extend_from_slice(data),
+    }
+}
+
+/// Write the type bitmap used by NSEC records (RFC 4034 4.1.2)
+fn write_type_bitmap(types: &[RecordType], buffer: &mut Vec<u8>) {
+    let mut types: Vec<u16> = types.iter().map(|&rtype| u16::from(rtype)).collect();
+    types.sort_unstable();
+    types.dedup();
+
+    for window in types.chunk_by(|a, b| a >> 8 == b >> 8) {
+        let length = usize::from(window[window.len() - 1] & 0xFF) / 8 + 1;
+        let mut bitmap = vec![0u8; length];
+        for rtype in window {
+            let low = usize::from(rtype & 0xFF);
+            bitmap[low / 8] |= 0x80 >> (low % 8);
+        }
+
+        buffer.push((window[0] >> 8) as u8);
+        buffer.push(length as u8);
+        buffer.extend_from_slice(&bitmap);
+    }
+}
+
+fn verify_signature(key: &Dnskey, message: &[u8], signature: &[u8]) -> bool {
+    match key.algorithm {
+        5 | 7 => verify_rsa(
+            &signature::RSA_PKCS1_1024_8192_SHA1_FOR_LEGACY_USE_ONLY,
+            key,
+            message,
+            signature,
+        ),
+        8 => verify_rsa(
+            &signature::RSA_PKCS1_1024_8192_SHA256_FOR_LEGACY_USE_ONLY,
+            key,
+            message,
+            signature,
+        ),
+        10 => verify_rsa(
+            &signature::RSA_PKCS1_1024_8192_SHA512_FOR_LEGACY_USE_ONLY,
+            key,
+            message,
+            signature,
+        ),
+        13 => verify_ecdsa(&signature::ECDSA_P256_SHA256_FIXED, key, message, signature),
+        14 => verify_ecdsa(&signature::ECDSA_P384_SHA384_FIXED, key, message, signature),
+        15 => UnparsedPublicKey::new(&signature::ED25519, &key.public_key)
+            .verify(message, signature)
+            .is_ok(),
+        _ => false,
+    }
+}
+
+/// RSA public keys are stored as the exponent length, exponent and modulus (RFC 3110 2)
+fn verify_rsa(
+    parameters: &'static signature::RsaParameters,
+    key: &Dnskey,
+    message: &[u8],
+    signature: &[u8],
+) -> bool {
+    let public_key = key.public_key.as_slice();
+    let (exponent_length, offset) = match public_key {
+        [0, high, low, ..] => (usize::from(*high) << 8 | usize::from(*low), 3),
+        [length, ..] => (usize::from(*length), 1),
+        [] => return false,
+    };
+
+    if public_key.len() <= offset + exponent_length {
+        return false;
+    }
+
+    let (exponent, modulus) = public_key[offset..].split_at(exponent_length);
+    RsaPublicKeyComponents {
+        n: modulus,
+        e: exponent,
+    }
+    .verify(parameters, message, signature)
+    .is_ok()
+}
+
+/// ECDSA public keys are stored as the concatenated x and y coordinates (RFC 6605 4)
+fn verify_ecdsa(
+    algorithm: &'static signature::EcdsaVerificationAlgorithm,
+    key: &Dnskey,
+    message: &[u8],
+    signature: &[u8],
+) -> bool {
+    let mut point = Vec::with_capacity(key.public_key.len() + 1);
+    point.push(0x04);
+    point.extend_from_slice(&key.public_key);
+
+    UnparsedPublicKey::new(algorithm, point)
+        .verify(message, signature)
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::general::from_unix_seconds;
+    use crate::measurement::dns::message::{Class, Header, Question};
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+
+    /// Signed with a 2048 bit RSA/SHA-256 key (tag 29987) which is valid from 1700000000 until
+    /// 1800000000. The zone holds `example.org`, an unsigned delegation to `insecure.example.org`
+    /// and an A record for `www.example.org`.
+    const DNSKEY: &str = include_str!("testdata/example.org-dnskey.abuf");
+    const ANSWER: &str = include_str!("testdata/www.example.org-a.abuf");
+    const NXDOMAIN: &str = include_str!("testdata/nope.example.org-nxdomain.abuf");
+    const NODATA: &str = include_str!("testdata/www.example.org-aaaa-nodata.abuf");
+    const NSEC3: &str = include_str!("testdata/nope.example.org-nsec3.abuf");
+    const UNSIGNED_DELEGATION: &str = include_str!("testdata/insecure.example.org-ds-nodata.abuf");
+
+    /// The zone `example.net` signed with ECDSA P-256 key 55444 over the same period. It only holds
+    /// an A record at `*.example.net` and one at `www.example.net`.
+    const WILDCARD_DNSKEY: &str = include_str!("testdata/example.net-dnskey.abuf");
+    /// Answer for `host.example.net` expanded from the wildcard, with the NSEC record proving
+    /// `host.example.net` does not exist
+    const WILDCARD: &str = include_str!("testdata/host.example.net-a-wildcard.abuf");
+    const WILDCARD_DS_DIGEST: &str =
+        "cec7f10f7bc2162847a85c0e309263fc735241f5ca440b83a3deb5e671f1419b";
+
+    const DS_DIGEST: &str = "99908dfda0df98e723d43ac12265ddf651a8e8d741be95bcb34ee63d2d1674f3";
+    const VALID: i64 = 1750000000;
+
+    fn name(name: &str) -> Name {
+        Name::from_labels(
+            name.split('.')
+                .map(|label| label.as_bytes().to_vec())
+                .collect(),
+        )
+    }
+
+    fn at(seconds: i64) -> UnixTimestamp {
+        from_unix_seconds(seconds).unwrap()
+    }
+
+    fn hex(data: &str) -> Vec<u8> {
+        (0..data.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&data[index..index + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn parse(abuf: &str) -> Message {
+        Message::from_base64(abuf).unwrap()
+    }
+
+    fn anchor(digest: &str) -> TrustAnchor {
+        TrustAnchor::Ds {
+            owner: name("example.org"),
+            ds: Ds {
+                key_tag: 29987,
+                algorithm: 8,
+                digest_type: 2,
+                digest: hex(digest),
+            },
+        }
+    }
+
+    fn validator() -> Validator {
+        let mut validator = Validator::new(vec![anchor(DS_DIGEST)]);
+        validator.add_message(&parse(DNSKEY));
+        validator
+    }
+
+    fn record(owner: &str, data: RecordData) -> Record {
+        let rtype = match &data {
+            RecordData::A(_) => RecordType::A,
+            RecordData::Mx { .. } => RecordType::MX,
+            other => panic!("unexpected record data {:?}", other),
+        };
+
+        Record {
+            name: name(owner),
+            rtype,
+            class: Class::IN,
+            ttl: 3600,
+            data,
+        }
+    }
+
+    /// Response with a question for the first of the answers
+    fn answer(answers: Vec<Record>) -> Message {
+        Message {
+            header: Header {
+                id: 0,
+                is_response: true,
+                opcode: 0,
+                authoritative_answer: true,
+                truncated: false,
+                recursion_desired: false,
+                recursion_available: false,
+                zero: false,
+                authentic_data: false,
+                checking_disabled: false,
+                rcode: 0,
+                question_count: 1,
+                answer_count: answers.len() as u16,
+                authority_count: 0,
+                additional_count: 0,
+            },
+            questions: vec![Question {
+                name: answers[0].name.clone(),
+                qtype: answers[0].rtype,
+                qclass: Class::IN,
+            }],
+            answers,
+            authorities: Vec::new(),
+            additionals: Vec::new(),
+        }
+    }
+
+    fn rrsig(message: &mut Message) -> &mut Rrsig {
+        message
+            .answers
+            .iter_mut()
+            .find_map(|record| match &mut record.data {
+                RecordData::Rrsig(sig) => Some(sig),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    /// Known answer from RFC 6605 6.1, RFC 6605 6.2 or RFC 8080 6.1
+    struct Vector {
+        owner: &'static str,
+        key: Dnskey,
+        ds: Ds,
+        rrset: Record,
+        signature: Rrsig,
+        time: i64,
+    }
+
+    fn ecdsa_vector(
+        algorithm: u8,
+        public_key: &str,
+        ds: (u16, u8, &str),
+        (expiration, inception): (u32, u32),
+        signature: &str,
+    ) -> Vector {
+        Vector {
+            owner: "example.net",
+            key: Dnskey {
+                flags: 257,
+                protocol: 3,
+                algorithm,
+                public_key: STANDARD.decode(public_key).unwrap(),
+            },
+            ds: Ds {
+                key_tag: ds.0,
+                algorithm,
+                digest_type: ds.1,
+                digest: hex(ds.2),
+            },
+            rrset: record("www.example.net", RecordData::A([192, 0, 2, 1].into())),
+            signature: Rrsig {
+                type_covered: RecordType::A,
+                algorithm,
+                labels: 3,
+                original_ttl: 3600,
+                expiration,
+                inception,
+                key_tag: ds.0,
+                signer_name: name("example.net"),
+                signature: STANDARD.decode(signature).unwrap(),
+            },
+            time: i64::from(inception) + 86400,
+        }
+    }
+
+    fn rfc_vectors() -> Vec<Vector> {
+        vec![
+            ecdsa_vector(
+                13,
+                "GojIhhXUN/u4v54ZQqGSnyhWJwaubCvTmeexv7bR6edbkrSqQpF64cYbcB7wNcP+e+MAnLr+Wi9xMWyQLc8NAA==",
+                (55648, 2, "b4c8c1fe2e7477127b27115656ad6256f424625bf5c1e2770ce6d6e37df61d17"),
+                (1284026679, 1281607479),
+                "qx6wLYqmh+l9oCKTN6qIc+bw6ya+KJ8oMz0YP107epXAyGmt+3SNruPFKG7tZoLBLlUzGGus7ZwmwWep666VCw==",
+            ),
+            ecdsa_vector(
+                14,
+                "xKYaNhWdGOfJ+nPrL8/arkwf2EY3MDJ+SErKivBVSum1w/egsXvSADtNJhyem5RCOpgQ6K8X1DRSEkrbYQ+OB+v8/uX45NBwY8rp65F6Glur8I/mlVNgF6W/qTI37m40",
+                (10771, 4, "72d7b62976ce06438e9c0bf319013cf801f09ecc84b8d7e9495f27e305c6a9b0563a9b5f4d288405c3008a946df983d6"),
+                (1284027625, 1281608425),
+                "/L5hDKIvGDyI1fcARX3z65qrmPsVz73QD1Mr5CEqOiLP95hxQouuroGCeZOvzFaxsT8Glr74hbavRKayJNuydCuzWTSSPdz7wnqXL5bdcJzusdnI0RSMROxxwGipWcJm",
+            ),
+            Vector {
+                owner: "example.com",
+                key: Dnskey {
+                    flags: 257,
+                    protocol: 3,
+                    algorithm: 15,
+                    public_key: STANDARD
+                        .decode("l02Woi0iS8Aa25FQkUd9RMzZHJpBoRQwAQEX1SxZJA4=")
+                        .unwrap(),
+                },
+                ds: Ds {
+                    key_tag: 3613,
+                    algorithm: 15,
+                    digest_type: 2,
+                    digest: hex("3aa5ab37efce57f737fc1627013fee07bdf241bd10f3b1964ab55c78e79a304b"),
+                },
+                rrset: record(
+                    "example.com",
+                    RecordData::Mx {
+                        preference: 10,
+                        exchange: name("mail.example.com"),
+                    },
+                ),
+                signature: Rrsig {
+                    type_covered: RecordType::MX,
+                    algorithm: 15,
+                    labels: 2,
+                    original_ttl: 3600,
+                    expiration: 1440021600,
+                    inception: 1438207200,
+                    key_tag: 3613,
+                    signer_name: name("example.com"),
+                    signature: STANDARD
+                        .decode("oL9krJun7xfBOIWcGHi7mag5/hdZrKWw15jPGrHpjQeRAvTdszaPD+QLs3fx8A4M3e23mRZ9VrbpMngwcrqNAg==")
+                        .unwrap(),
+                },
+                time: 1439000000,
+            },
+        ]
+    }
+
+    impl Vector {
+        fn message(&self) -> Message {
+            let mut signature = self.rrset.clone();
+            signature.rtype = RecordType::RRSIG;
+            signature.data = RecordData::Rrsig(self.signature.clone());
+
+            answer(vec![self.rrset.clone(), signature])
+        }
+
+        fn validator(&self, key: Dnskey) -> Validator {
+            Validator::new(vec![TrustAnchor::Dnskey {
+                owner: name(self.owner),
+                key,
+            }])
+        }
+    }
+
+    #[test]
+    fn verifies_rfc_vectors() {
+        for vector in rfc_vectors() {
+            assert_eq!(vector.key.key_tag(), vector.ds.key_tag);
+            assert!(ds_matches(&name(vector.owner), &vector.key, &vector.ds));
+
+            let validator = vector.validator(vector.key.clone());
+            let security = validator.validate(&vector.message(), at(vector.time));
+            assert_eq!(
+                security,
+                Security::Secure,
+                "algorithm {}",
+                vector.key.algorithm
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_keys() {
+        for vector in rfc_vectors() {
+            // Swapping two bytes two positions apart changes the key but not its tag
+            let mut key = vector.key.clone();
+            key.public_key.swap(0, 2);
+            assert_eq!(key.key_tag(), vector.key.key_tag());
+            assert!(!ds_matches(&name(vector.owner), &key, &vector.ds));
+
+            let security = vector
+                .validator(key)
+                .validate(&vector.message(), at(vector.time));
+            assert_eq!(
+                security,
+                Security::Bogus(BogusReason::InvalidSignature {
+                    name: vector.rrset.name.clone(),
+                    rtype: vector.rrset.rtype,
+                    key_tag: vector.ds.key_tag,
+                })
+            );
+        }
+
+        // The signature of one vector does not verify with the key of another
+        let vectors = rfc_vectors();
+        let security = vectors[0]
+            .validator(vectors[1].key.clone())
+            .validate(&vectors[0].message(), at(vectors[0].time));
+        assert!(matches!(
+            security,
+            Security::Bogus(BogusReason::MissingDnskey { key_tag: 55648, .. })
+        ));
+    }
+
+    #[test]
+    fn verifies_rsa_chain_from_ds() {
+        let validator = validator();
+        let time = at(VALID);
+
+        assert_eq!(validator.validate(&parse(DNSKEY), time), Security::Secure);
+        assert_eq!(validator.validate(&parse(ANSWER), time), Security::Secure);
+
+        // The DNSKEY records may also come from the same message
+        let mut message = parse(ANSWER);
+        message.additionals.extend(parse(DNSKEY).answers);
+        let validator = Validator::new(vec![anchor(DS_DIGEST)]);
+        assert_eq!(validator.validate(&message, time), Security::Secure);
+    }
+
+    #[test]
+    fn rejects_signatures_outside_validity() {
+        let validator = validator();
+        let name = name("www.example.org");
+
+        assert_eq!(
+            validator.validate(&parse(ANSWER), at(1800000001)),
+            Security::Bogus(BogusReason::SignatureExpired {
+                name: name.clone(),
+                rtype: RecordType::A,
+                expiration: 1800000000,
+            })
+        );
+        assert_eq!(
+            validator.validate(&parse(ANSWER), at(1699999999)),
+            Security::Bogus(BogusReason::SignatureNotYetValid {
+                name,
+                rtype: RecordType::A,
+                inception: 1700000000,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_modified_answers() {
+        let validator = validator();
+        let time = at(VALID);
+
+        let mut message = parse(ANSWER);
+        rrsig(&mut message).key_tag = 29988;
+        assert_eq!(
+            validator.validate(&message, time),
+            Security::Bogus(BogusReason::MissingDnskey {
+                zone: name("example.org"),
+                key_tag: 29988,
+            })
+        );
+
+        let mut message = parse(ANSWER);
+        message.answers[0].data = RecordData::A([192, 0, 2, 81].into());
+        assert_eq!(
+            validator.validate(&message, time),
+            Security::Bogus(BogusReason::InvalidSignature {
+                name: name("www.example.org"),
+                rtype: RecordType::A,
+                key_tag: 29987,
+            })
+        );
+    }
+
+    #[test]
+    fn ignores_unsupported_algorithms() {
+        let validator = validator();
+        let time = at(VALID);
+
+        // Replacing the signature with one using an unknown algorithm must not make it insecure
+        let mut message = parse(ANSWER);
+        rrsig(&mut message).algorithm = 200;
+        assert_eq!(
+            validator.validate(&message, time),
+            Security::Bogus(BogusReason::MissingSignature {
+                name: name("www.example.org"),
+                rtype: RecordType::A,
+            })
+        );
+
+        let mut message = parse(ANSWER);
+        let mut injected = message.answers[1].clone();
+        rrsig(&mut message).signature[0] ^= 0xff;
+        match &mut injected.data {
+            RecordData::Rrsig(sig) => sig.algorithm = 200,
+            other => panic!("unexpected record data {:?}", other),
+        }
+        message.answers.push(injected);
+        assert_eq!(
+            validator.validate(&message, time),
+            Security::Bogus(BogusReason::InvalidSignature {
+                name: name("www.example.org"),
+                rtype: RecordType::A,
+                key_tag: 29987,
+            })
+        );
+
+        // Only a zone whose DS records all use unsupported algorithms is insecure
+        let mut unsupported = Validator::new(vec![TrustAnchor::Ds {
+            owner: name("example.org"),
+            ds: Ds {
+                key_tag: 29987,
+                algorithm: 200,
+                digest_type: 2,
+                digest: hex(DS_DIGEST),
+            },
+        }]);
+        unsupported.add_message(&parse(DNSKEY));
+        assert_eq!(
+            unsupported.validate(&message, time),
+            Security::Insecure(InsecureReason::UnsupportedAlgorithm {
+                zone: name("example.org"),
+                algorithm: 200,
+            })
+        );
+    }
+
+    #[test]
+    fn requires_proof_for_wildcard_answers() {
+        let mut validator = Validator::new(vec![TrustAnchor::Ds {
+            owner: name("example.net"),
+            ds: Ds {
+                key_tag: 55444,
+                algorithm: 13,
+                digest_type: 2,
+                digest: hex(WILDCARD_DS_DIGEST),
+            },
+        }]);
+        validator.add_message(&parse(WILDCARD_DNSKEY));
+        let time = at(VALID);
+
+        let message = parse(WILDCARD);
+        assert_eq!(message.answers.len(), 2);
+        assert_eq!(validator.validate(&message, time), Security::Secure);
+
+        let missing = Security::Bogus(BogusReason::MissingWildcardProof {
+            name: name("host.example.net"),
+            rtype: RecordType::A,
+        });
+        let mut unproven = message.clone();
+        unproven.authorities.clear();
+        assert_eq!(validator.validate(&unproven, time), missing);
+
+        // The NSEC record must be signed by the zone
+        let mut forged = message.clone();
+        for record in &mut forged.authorities {
+            if let RecordData::Rrsig(sig) = &mut record.data {
+                sig.signature[0] ^= 0xff;
+            }
+        }
+        assert!(validator.validate(&forged, time).is_bogus());
+
+        // An NSEC record which does not cover the name is no proof
+        let mut uncovered = message;
+        for record in &mut uncovered.authorities {
+            if let RecordData::Nsec {
+                next_domain_name, ..
+            } = &mut record.data
+            {
+                *next_domain_name = name("a.example.net");
+            }
+        }
+        assert!(validator.validate(&uncovered, time).is_bogus());
+    }
+
+    #[test]
+    fn rejects_ds_digest_mismatch() {
+        let mut digest = DS_DIGEST.to_string();
+        digest.replace_range(..2, "00");
+
+        let mut validator = Validator::new(vec![anchor(&digest)]);
+        validator.add_message(&parse(DNSKEY));
+        assert_eq!(
+            validator.validate(&parse(ANSWER), at(VALID)),
+            Security::Bogus(BogusReason::NoMatchingDs {
+                zone: name("example.org"),
+            })
+        );
+    }
+
+    #[test]
+    fn verifies_denial_of_existence() {
+        let validator = validator();
+        let time = at(VALID);
+
+        assert_eq!(validator.validate(&parse(NXDOMAIN), time), Security::Secure);
+        assert_eq!(validator.validate(&parse(NODATA), time), Security::Secure);
+
+        // The NSEC record of www.example.org shows it has an A record
+        let mut message = parse(NODATA);
+        message.questions[0].qtype = RecordType::A;
+        assert_eq!(
+            validator.validate(&message, time),
+            Security::Bogus(BogusReason::MissingDenial {
+                name: name("www.example.org"),
+                rtype: RecordType::A,
+            })
+        );
+
+        // Without the NSEC record of the apex, a wildcard could still have matched
+        let mut message = parse(NXDOMAIN);
+        message
+            .authorities
+            .retain(|record| record.name != name("example.org") || record.rtype == RecordType::SOA
+                || matches!(&record.data, RecordData::Rrsig(sig) if sig.type_covered == RecordType::SOA));
+        assert_eq!(message.authorities.len(), 4);
+        assert!(validator.validate(&message, time).is_bogus());
+    }
+
+    #[test]
+    fn never_trusts_empty_answers() {
+        let validator = validator();
+        let time = at(VALID);
+        let missing = Security::Bogus(BogusReason::MissingDenial {
+            name: name("nope.example.org"),
+            rtype: RecordType::A,
+        });
+
+        let mut message = parse(NXDOMAIN);
+        message.authorities.retain(|record| {
+            !matches!(record.rtype, RecordType::NSEC)
+                && !matches!(&record.data, RecordData::Rrsig(sig) if sig.type_covered == RecordType::NSEC)
+        });
+        assert_eq!(validator.validate(&message, time), missing);
+
+        message.authorities.clear();
+        assert_eq!(validator.validate(&message, time), missing);
+
+        // A message with only a header has nothing to validate
+        let header = Message::parse(&[0, 0, 0x81, 0x80, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+        assert!(!validator.validate(&header, time).is_secure());
+        let root = Validator::new(vec![TrustAnchor::Ds {
+            owner: Name::root(),
+            ds: Ds {
+                key_tag: 20326,
+                algorithm: 8,
+                digest_type: 2,
+                digest: hex("e06d44b80b8f1d39a95c0b0d7c65d08458e880409bbc683457104237c7f8ec8d"),
+            },
+        }]);
+        assert!(root.validate(&header, time).is_bogus());
+    }
+
+    #[test]
+    fn does_not_verify_nsec3() {
+        assert_eq!(
+            validator().validate(&parse(NSEC3), at(VALID)),
+            Security::Insecure(InsecureReason::UnsupportedDenial {
+                name: name("nope.example.org"),
+                rtype: RecordType::A,
+            })
+        );
+    }
+
+    #[test]
+    fn requires_proof_of_unsigned_zones() {
+        let time = at(VALID);
+        let unsigned =
+            |owner: &str| answer(vec![record(owner, RecordData::A([192, 0, 2, 1].into()))]);
+
+        let mut validator = validator();
+        assert_eq!(
+            validator.validate(&unsigned("www.example.org"), time),
+            Security::Bogus(BogusReason::MissingSignature {
+                name: name("www.example.org"),
+                rtype: RecordType::A,
+            })
+        );
+        assert!(validator
+            .validate(&unsigned("host.insecure.example.org"), time)
+            .is_bogus());
+
+        // Names outside of any trust anchor
+        assert_eq!(
+            validator.validate(&unsigned("www.example.com"), time),
+            Security::Insecure(InsecureReason::Unsigned {
+                name: name("www.example.com"),
+                rtype: RecordType::A,
+            })
+        );
+
+        // The negative answer to a DS query proves the delegation is unsigned
+        validator.add_message(&parse(UNSIGNED_DELEGATION));
+        assert_eq!(
+            validator.validate(&unsigned("host.insecure.example.org"), time),
+            Security::Insecure(InsecureReason::Unsigned {
+                name: name("host.insecure.example.org"),
+                rtype: RecordType::A,
+            })
+        );
+        assert!(validator
+            .validate(&unsigned("www.example.org"), time)
+            .is_bogus());
+    }
+}
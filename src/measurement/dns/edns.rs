@@ -0,0 +1,348 @@
+//! Typed access to the EDNS0 options (RFC 6891) carried by the OPT record of a DNS message.
+use crate::measurement::dns::message::{Opt, RawOption};
+use std::borrow::Cow;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+pub const OPTION_NSID: u16 = 3;
+pub const OPTION_CLIENT_SUBNET: u16 = 8;
+pub const OPTION_COOKIE: u16 = 10;
+pub const OPTION_PADDING: u16 = 12;
+pub const OPTION_EXTENDED_ERROR: u16 = 15;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EdnsOption<'a> {
+    /// Name Server Identifier (RFC 5001)
+    Nsid(Nsid<'a>),
+    /// EDNS Client Subnet (RFC 7871)
+    ClientSubnet(ClientSubnet<'a>),
+    /// DNS Cookie (RFC 7873)
+    Cookie(Cookie<'a>),
+    /// Padding (RFC 7830). Only the number of padding octets is kept.
+    Padding(usize),
+    /// Extended DNS Error (RFC 8914)
+    ExtendedError(ExtendedError<'a>),
+    /// An option with a known code whose contents did not match the format of that option
+    Malformed(&'a RawOption),
+    /// An option this library does not decode
+    Unknown(&'a RawOption),
+}
+
+impl<'a> From<&'a RawOption> for EdnsOption<'a> {
+    fn from(option: &'a RawOption) -> Self {
+        let parsed = match option.code {
+            OPTION_NSID => Some(EdnsOption::Nsid(Nsid(&option.data))),
+            OPTION_CLIENT_SUBNET => ClientSubnet::parse(&option.data).map(EdnsOption::ClientSubnet),
+            OPTION_COOKIE => Cookie::parse(&option.data).map(EdnsOption::Cookie),
+            OPTION_PADDING => Some(EdnsOption::Padding(option.data.len())),
+            OPTION_EXTENDED_ERROR => {
+                ExtendedError::parse(&option.data).map(EdnsOption::ExtendedError)
+            }
+            _ => return EdnsOption::Unknown(option),
+        };
+
+        parsed.unwrap_or(EdnsOption::Malformed(option))
+    }
+}
+
+impl Opt {
+    /// Iterate over the decoded form of each option in the order they appeared in the message.
+    pub fn iter_options(&self) -> impl Iterator<Item = EdnsOption<'_>> {
+        self.options.iter().map(EdnsOption::from)
+    }
+
+    pub fn nsid(&self) -> Option<Nsid<'_>> {
+        self.iter_options().find_map(|option| match option {
+            EdnsOption::Nsid(nsid) => Some(nsid),
+            _ => None,
+        })
+    }
+
+    pub fn client_subnet(&self) -> Option<ClientSubnet<'_>> {
+        self.iter_options().find_map(|option| match option {
+            EdnsOption::ClientSubnet(subnet) => Some(subnet),
+            _ => None,
+        })
+    }
+
+    pub fn cookie(&self) -> Option<Cookie<'_>> {
+        self.iter_options().find_map(|option| match option {
+            EdnsOption::Cookie(cookie) => Some(cookie),
+            _ => None,
+        })
+    }
+
+    /// A response may carry more than one extended error (RFC 8914 3)
+    pub fn extended_errors(&self) -> impl Iterator<Item = ExtendedError<'_>> {
+        self.iter_options().filter_map(|option| match option {
+            EdnsOption::ExtendedError(error) => Some(error),
+            _ => None,
+        })
+    }
+}
+
+/// Name Server Identifier. The contents are not required to be text, but most operators use either
+/// a printable hostname or a hex string.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Nsid<'a>(pub &'a [u8]);
+
+impl<'a> Nsid<'a> {
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+
+    /// The identifier as text if it is valid UTF-8
+    pub fn as_str(&self) -> Option<&'a str> {
+        std::str::from_utf8(self.0).ok()
+    }
+
+    pub fn to_string_lossy(&self) -> Cow<'a, str> {
+        String::from_utf8_lossy(self.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ClientSubnet<'a> {
+    /// address family as assigned by IANA (1: IPv4, 2: IPv6)
+    pub family: u16,
+    pub source_prefix_length: u8,
+    pub scope_prefix_length: u8,
+    /// address truncated to the source prefix length
+    pub address: &'a [u8],
+}
+
+impl<'a> ClientSubnet<'a> {
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        if data.len() < 4 {
+            return None;
+        }
+
+        Some(ClientSubnet {
+            family: u16::from_be_bytes([data[0], data[1]]),
+            source_prefix_length: data[2],
+            scope_prefix_length: data[3],
+            address: &data[4..],
+        })
+    }
+
+    /// The subnet address padded back out to a full IP address. Returns `None` for families other
+    /// than IPv4 and IPv6.
+    pub fn ip(&self) -> Option<IpAddr> {
+        match self.family {
+            1 if self.address.len() <= 4 => {
+                let mut octets = [0; 4];
+                octets[..self.address.len()].copy_from_slice(self.address);
+                Some(IpAddr::V4(Ipv4Addr::from(octets)))
+            }
+            2 if self.address.len() <= 16 => {
+                let mut octets = [0; 16];
+                octets[..self.address.len()].copy_from_slice(self.address);
+                Some(IpAddr::V6(Ipv6Addr::from(octets)))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Cookie<'a> {
+    /// 8 octet client cookie
+    pub client: &'a [u8],
+    /// 8 to 32 octet server cookie. This is only present in responses from servers which support
+    /// cookies.
+    pub server: Option<&'a [u8]>,
+}
+
+impl<'a> Cookie<'a> {
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        match data.len() {
+            8 => Some(Cookie {
+                client: data,
+                server: None,
+            }),
+            16..=40 => Some(Cookie {
+                client: &data[..8],
+                server: Some(&data[8..]),
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ExtendedError<'a> {
+    pub info_code: u16,
+    /// optional explanation of the error from the server; not guaranteed to be valid UTF-8
+    pub extra_text: &'a [u8],
+}
+
+impl<'a> ExtendedError<'a> {
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        if data.len() < 2 {
+            return None;
+        }
+
+        Some(ExtendedError {
+            info_code: u16::from_be_bytes([data[0], data[1]]),
+            extra_text: &data[2..],
+        })
+    }
+
+    pub fn extra_text_lossy(&self) -> Cow<'a, str> {
+        String::from_utf8_lossy(self.extra_text)
+    }
+
+    /// Name of the info code in the IANA Extended DNS Error Codes registry
+    pub fn purpose(&self) -> Option<&'static str> {
+        Some(match self.info_code {
+            0 => "Other Error",
+            1 => "Unsupported DNSKEY Algorithm",
+            2 => "Unsupported DS Digest Type",
+            3 => "Stale Answer",
+            4 => "Forged Answer",
+            5 => "DNSSEC Indeterminate",
+            6 => "DNSSEC Bogus",
+            7 => "Signature Expired",
+            8 => "Signature Not Yet Valid",
+            9 => "DNSKEY Missing",
+            10 => "RRSIGs Missing",
+            11 => "No Zone Key Bit Set",
+            12 => "NSEC Missing",
+            13 => "Cached Error",
+            14 => "Not Ready",
+            15 => "Blocked",
+            16 => "Censored",
+            17 => "Filtered",
+            18 => "Prohibited",
+            19 => "Stale NXDomain Answer",
+            20 => "Not Authoritative",
+            21 => "Not Supported",
+            22 => "No Reachable Authority",
+            23 => "Network Error",
+            24 => "Invalid Data",
+            25 => "Signature Expired before Valid",
+            26 => "Too Early",
+            27 => "Unsupported NSEC3 Iterations Value",
+            28 => "Unable to conform to policy",
+            29 => "Synthesized",
+            30 => "Invalid Query Type",
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::measurement::dns::message::{Message, ResponseCode};
+
+    /// SERVFAIL response to `example.com A` carrying an NSID, client subnet, cookie, padding, two
+    /// extended errors and an unassigned option
+    const ABUF: &str = "EjSBggABAAAAAAABB2V4YW1wbGUDY29tAAABAAEAACkE0AAAAAAAWgADAA5hbXMtMDEuZXhhbXBsZQAIAAcAARgAwAACAAoAGAABAgMEBQYHCAkKCwwNDg8QERITFBUWFwAMAAYAAAAAAAAADwAIAAZmYWlsZWQADwACABb96QABAQ==";
+
+    fn option(code: u16, data: &[u8]) -> RawOption {
+        RawOption {
+            code,
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn decodes_options_from_abuf() {
+        let message = Message::from_base64(ABUF).unwrap();
+        assert_eq!(message.response_code(), ResponseCode::ServFail);
+
+        let opt = message.opt().unwrap();
+        assert_eq!(opt.udp_payload_size, 1232);
+        assert_eq!(opt.iter_options().count(), 7);
+
+        let nsid = opt.nsid().unwrap();
+        assert_eq!(nsid.as_str(), Some("ams-01.example"));
+        assert_eq!(nsid.to_string_lossy(), "ams-01.example");
+
+        let subnet = opt.client_subnet().unwrap();
+        assert_eq!(subnet.family, 1);
+        assert_eq!(subnet.source_prefix_length, 24);
+        assert_eq!(subnet.scope_prefix_length, 0);
+        assert_eq!(subnet.ip(), Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 0))));
+
+        let cookie = opt.cookie().unwrap();
+        assert_eq!(cookie.client, &[0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(cookie.server.map(<[u8]>::len), Some(16));
+
+        let errors: Vec<ExtendedError> = opt.extended_errors().collect();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].info_code, 6);
+        assert_eq!(errors[0].purpose(), Some("DNSSEC Bogus"));
+        assert_eq!(errors[0].extra_text_lossy(), "failed");
+        assert_eq!(errors[1].purpose(), Some("No Reachable Authority"));
+        assert!(errors[1].extra_text.is_empty());
+
+        let options: Vec<EdnsOption> = opt.iter_options().collect();
+        assert_eq!(options[3], EdnsOption::Padding(6));
+        assert!(matches!(options[6], EdnsOption::Unknown(raw) if raw.code == 65001));
+    }
+
+    #[test]
+    fn rejects_malformed_options() {
+        for raw in [
+            option(OPTION_CLIENT_SUBNET, &[0, 1, 24]),
+            option(OPTION_COOKIE, &[0; 7]),
+            option(OPTION_COOKIE, &[0; 12]),
+            option(OPTION_COOKIE, &[0; 41]),
+            option(OPTION_EXTENDED_ERROR, &[0]),
+        ] {
+            assert_eq!(EdnsOption::from(&raw), EdnsOption::Malformed(&raw));
+        }
+
+        // A client cookie on its own is sent in queries
+        let raw = option(OPTION_COOKIE, &[7; 8]);
+        assert!(matches!(
+            EdnsOption::from(&raw),
+            EdnsOption::Cookie(Cookie { server: None, .. })
+        ));
+    }
+
+    #[test]
+    fn pads_client_subnet_addresses() {
+        let raw = option(
+            OPTION_CLIENT_SUBNET,
+            &[0, 2, 48, 0, 0x20, 0x01, 0x0D, 0xB8, 0, 1],
+        );
+        let subnet = match EdnsOption::from(&raw) {
+            EdnsOption::ClientSubnet(subnet) => subnet,
+            other => panic!("expected a client subnet, got {:?}", other),
+        };
+        assert_eq!(subnet.ip(), Some("2001:db8:1::".parse().unwrap()));
+
+        // Addresses longer than their family allows and unknown families have no address
+        let raw = option(OPTION_CLIENT_SUBNET, &[0, 1, 32, 0, 1, 2, 3, 4, 5]);
+        assert!(
+            matches!(EdnsOption::from(&raw), EdnsOption::ClientSubnet(subnet) if subnet.ip().is_none())
+        );
+        let raw = option(OPTION_CLIENT_SUBNET, &[0, 3, 0, 0]);
+        assert!(
+            matches!(EdnsOption::from(&raw), EdnsOption::ClientSubnet(subnet) if subnet.ip().is_none())
+        );
+    }
+
+    #[test]
+    fn handles_binary_nsid() {
+        let raw = option(OPTION_NSID, &[0xFF, 0x00]);
+        let nsid = match EdnsOption::from(&raw) {
+            EdnsOption::Nsid(nsid) => nsid,
+            other => panic!("expected an NSID, got {:?}", other),
+        };
+        assert_eq!(nsid.as_bytes(), &[0xFF, 0x00]);
+        assert_eq!(nsid.as_str(), None);
+        assert_eq!(nsid.to_string_lossy(), "\u{FFFD}\u{0}");
+
+        assert_eq!(
+            ExtendedError {
+                info_code: 49152,
+                extra_text: &[]
+            }
+            .purpose(),
+            None
+        );
+    }
+}
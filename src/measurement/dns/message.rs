@@ -0,0 +1,1350 @@
+//! Decoding of raw DNS messages in the wire format described by RFC 1035. Probes include these as
+//! base64 strings in the `abuf` and `qbuf` fields of DNS measurement results.
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::Engine;
+use std::fmt::{self, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// Probes have been seen to both include and omit the trailing padding, so accept either.
+const BUFFER_ENGINE: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Names may not be longer than 255 octets in wire format (RFC 1035 2.3.4)
+const MAX_NAME_LENGTH: usize = 255;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The buffer was not valid base64
+    Base64(base64::DecodeError),
+    /// The message ended while reading the field starting at this offset
+    UnexpectedEnd { offset: usize },
+    /// A compression pointer at this offset did not point to an earlier position in the message
+    InvalidPointer { offset: usize },
+    /// The label at this offset used one of the reserved label types
+    InvalidLabel { offset: usize },
+    /// A name was longer than 255 octets
+    NameTooLong { offset: usize },
+    /// The contents of the record at this offset did not match its rdata length
+    RecordLength { offset: usize },
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Base64(err) => write!(f, "invalid base64 in DNS buffer: {}", err),
+            DecodeError::UnexpectedEnd { offset } => {
+                write!(f, "DNS message ended unexpectedly at offset {}", offset)
+            }
+            DecodeError::InvalidPointer { offset } => {
+                write!(f, "invalid compression pointer at offset {}", offset)
+            }
+            DecodeError::InvalidLabel { offset } => {
+                write!(f, "unsupported label type at offset {}", offset)
+            }
+            DecodeError::NameTooLong { offset } => {
+                write!(f, "name at offset {} exceeds 255 octets", offset)
+            }
+            DecodeError::RecordLength { offset } => {
+                write!(f, "rdata length mismatch for record at offset {}", offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DecodeError::Base64(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<base64::DecodeError> for DecodeError {
+    fn from(err: base64::DecodeError) -> Self {
+        DecodeError::Base64(err)
+    }
+}
+
+/// A fully decoded DNS message (RFC 1035 4.1)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    pub header: Header,
+    pub questions: Vec<Question>,
+    pub answers: Vec<Record>,
+    pub authorities: Vec<Record>,
+    pub additionals: Vec<Record>,
+}
+
+impl Message {
+    /// Decode a message from a base64 encoded buffer such as `abuf` or `qbuf`.
+    pub fn from_base64(buffer: &str) -> Result<Self, DecodeError> {
+        Message::parse(&BUFFER_ENGINE.decode(buffer.trim())?)
+    }
+
+    /// Decode a message from its raw wire format.
+    pub fn parse(buffer: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(buffer);
+        let header = Header::read(&mut reader)?;
+
+        let mut questions = Vec::with_capacity(header.question_count.into());
+        for _ in 0..header.question_count {
+            questions.push(Question::read(&mut reader)?);
+        }
+
+        Ok(Message {
+            answers: Record::read_section(&mut reader, header.answer_count)?,
+            authorities: Record::read_section(&mut reader, header.authority_count)?,
+            additionals: Record::read_section(&mut reader, header.additional_count)?,
+            header,
+            questions,
+        })
+    }
+
+    /// Iterate over the records of the answer, authority and additional sections in order.
+    pub fn records(&self) -> impl Iterator<Item = &Record> {
+        self.answers
+            .iter()
+            .chain(&self.authorities)
+            .chain(&self.additionals)
+    }
+
+    /// The EDNS0 pseudo-record (RFC 6891) from the additional section if one was included.
+    pub fn opt(&self) -> Option<&Opt> {
+        self.additionals
+            .iter()
+            .find_map(|record| match &record.data {
+                RecordData::Opt(opt) => Some(opt),
+                _ => None,
+            })
+    }
+
+    /// The full response code of this message. When an OPT record is present, its upper 8 bits are
+    /// combined with the 4 bits from the header (RFC 6891 6.1.3).
+    pub fn response_code(&self) -> ResponseCode {
+        let upper = self.opt().map_or(0, |opt| u16::from(opt.extended_rcode));
+        ResponseCode::from(upper << 4 | u16::from(self.header.rcode))
+    }
+}
+
+/// DNS message header (RFC 1035 4.1.1)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+    /// query ID
+    pub id: u16,
+    /// QR bit; set for responses and cleared for queries
+    pub is_response: bool,
+    /// kind of query (0: QUERY, 1: IQUERY, 2: STATUS, 4: NOTIFY, 5: UPDATE)
+    pub opcode: u8,
+    /// AA bit
+    pub authoritative_answer: bool,
+    /// TC bit
+    pub truncated: bool,
+    /// RD bit
+    pub recursion_desired: bool,
+    /// RA bit
+    pub recursion_available: bool,
+    /// Z bit, reserved for future use
+    pub zero: bool,
+    /// AD bit (RFC 4035 3.2.3)
+    pub authentic_data: bool,
+    /// CD bit (RFC 4035 3.2.2)
+    pub checking_disabled: bool,
+    /// lower 4 bits of the response code. See [`Message::response_code`] for the full value.
+    pub rcode: u8,
+    pub question_count: u16,
+    pub answer_count: u16,
+    pub authority_count: u16,
+    pub additional_count: u16,
+}
+
+impl Header {
+    fn read(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let id = reader.u16()?;
+        let flags = reader.u16()?;
+
+        Ok(Header {
+            id,
+            is_response: flags & 0x8000 != 0,
+            opcode: ((flags >> 11) & 0xF) as u8,
+            authoritative_answer: flags & 0x0400 != 0,
+            truncated: flags & 0x0200 != 0,
+            recursion_desired: flags & 0x0100 != 0,
+            recursion_available: flags & 0x0080 != 0,
+            zero: flags & 0x0040 != 0,
+            authentic_data: flags & 0x0020 != 0,
+            checking_disabled: flags & 0x0010 != 0,
+            rcode: (flags & 0xF) as u8,
+            question_count: reader.u16()?,
+            answer_count: reader.u16()?,
+            authority_count: reader.u16()?,
+            additional_count: reader.u16()?,
+        })
+    }
+}
+
+/// Entry in the question section (RFC 1035 4.1.2)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Question {
+    pub name: Name,
+    pub qtype: RecordType,
+    pub qclass: Class,
+}
+
+impl Question {
+    fn read(reader: &mut Reader) -> Result<Self, DecodeError> {
+        Ok(Question {
+            name: reader.name()?,
+            qtype: RecordType::from(reader.u16()?),
+            qclass: Class::from(reader.u16()?),
+        })
+    }
+}
+
+/// Resource record from the answer, authority or additional sections (RFC 1035 4.1.3)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    pub name: Name,
+    pub rtype: RecordType,
+    /// record class. For OPT records this holds the UDP payload size instead, which is also
+    /// available in decoded form through [`Opt::udp_payload_size`].
+    pub class: Class,
+    pub ttl: u32,
+    pub data: RecordData,
+}
+
+impl Record {
+    fn read_section(reader: &mut Reader, count: u16) -> Result<Vec<Self>, DecodeError> {
+        let mut records = Vec::with_capacity(count.into());
+        for _ in 0..count {
+            records.push(Record::read(reader)?);
+        }
+
+        Ok(records)
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let name = reader.name()?;
+        let rtype = RecordType::from(reader.u16()?);
+        let class = reader.u16()?;
+        let ttl = reader.u32()?;
+        let length = usize::from(reader.u16()?);
+
+        let start = reader.pos;
+        let end = start + length;
+        if end > reader.buffer.len() {
+            return Err(DecodeError::UnexpectedEnd { offset: start });
+        }
+
+        let data = RecordData::read(reader, rtype, class, ttl, end)?;
+        if reader.pos != end {
+            return Err(DecodeError::RecordLength { offset: start });
+        }
+
+        Ok(Record {
+            name,
+            rtype,
+            class: Class::from(class),
+            ttl,
+            data,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RecordData {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    Ns(Name),
+    Cname(Name),
+    Ptr(Name),
+    Mx {
+        preference: u16,
+        exchange: Name,
+    },
+    Soa {
+        mname: Name,
+        rname: Name,
+        serial: u32,
+        refresh: u32,
+        retry: u32,
+        expire: u32,
+        minimum: u32,
+    },
+    /// Each character string is kept as raw bytes since TXT records are not required to be UTF-8
+    Txt(Vec<Vec<u8>>),
+    Srv {
+        priority: u16,
+        weight: u16,
+        port: u16,
+        target: Name,
+    },
+    Naptr {
+        order: u16,
+        preference: u16,
+        flags: Vec<u8>,
+        services: Vec<u8>,
+        regexp: Vec<u8>,
+        replacement: Name,
+    },
+    Ds(Ds),
+    Dnskey(Dnskey),
+    Rrsig(Rrsig),
+    Nsec {
+        next_domain_name: Name,
+        types: Vec<RecordType>,
+    },
+    Tlsa {
+        usage: u8,
+        selector: u8,
+        matching_type: u8,
+        data: Vec<u8>,
+    },
+    Opt(Opt),
+    /// Raw rdata of a record type this library does not decode
+    Unknown(Vec<u8>),
+}
+
+impl RecordData {
+    fn read(
+        reader: &mut Reader,
+        rtype: RecordType,
+        class: u16,
+        ttl: u32,
+        end: usize,
+    ) -> Result<Self, DecodeError> {
+        Ok(match rtype {
+            RecordType::A => {
+                let octets: [u8; 4] = reader.bytes(4)?.try_into().expect("exact length");
+                RecordData::A(Ipv4Addr::from(octets))
+            }
+            RecordType::AAAA => {
+                let octets: [u8; 16] = reader.bytes(16)?.try_into().expect("exact length");
+                RecordData::Aaaa(Ipv6Addr::from(octets))
+            }
+            RecordType::NS => RecordData::Ns(reader.name()?),
+            RecordType::CNAME => RecordData::Cname(reader.name()?),
+            RecordType::PTR => RecordData::Ptr(reader.name()?),
+            RecordType::MX => RecordData::Mx {
+                preference: reader.u16()?,
+                exchange: reader.name()?,
+            },
+            RecordType::SOA => RecordData::Soa {
+                mname: reader.name()?,
+                rname: reader.name()?,
+                serial: reader.u32()?,
+                refresh: reader.u32()?,
+                retry: reader.u32()?,
+                expire: reader.u32()?,
+                minimum: reader.u32()?,
+            },
+            RecordType::TXT => {
+                let mut strings = Vec::new();
+                while reader.pos < end {
+                    strings.push(reader.character_string()?);
+                }
+                RecordData::Txt(strings)
+            }
+            RecordType::SRV => RecordData::Srv {
+                priority: reader.u16()?,
+                weight: reader.u16()?,
+                port: reader.u16()?,
+                target: reader.name()?,
+            },
+            RecordType::NAPTR => RecordData::Naptr {
+                order: reader.u16()?,
+                preference: reader.u16()?,
+                flags: reader.character_string()?,
+                services: reader.character_string()?,
+                regexp: reader.character_string()?,
+                replacement: reader.name()?,
+            },
+            RecordType::DS => RecordData::Ds(Ds {
+                key_tag: reader.u16()?,
+                algorithm: reader.u8()?,
+                digest_type: reader.u8()?,
+                digest: reader.remaining(end)?,
+            }),
+            RecordType::DNSKEY => RecordData::Dnskey(Dnskey {
+                flags: reader.u16()?,
+                protocol: reader.u8()?,
+                algorithm: reader.u8()?,
+                public_key: reader.remaining(end)?,
+            }),
+            RecordType::RRSIG => RecordData::Rrsig(Rrsig {
+                type_covered: RecordType::from(reader.u16()?),
+                algorithm: reader.u8()?,
+                labels: reader.u8()?,
+                original_ttl: reader.u32()?,
+                expiration: reader.u32()?,
+                inception: reader.u32()?,
+                key_tag: reader.u16()?,
+                signer_name: reader.name()?,
+                signature: reader.remaining(end)?,
+            }),
+            RecordType::NSEC => RecordData::Nsec {
+                next_domain_name: reader.name()?,
+                types: reader.type_bitmap(end)?,
+            },
+            RecordType::TLSA => RecordData::Tlsa {
+                usage: reader.u8()?,
+                selector: reader.u8()?,
+                matching_type: reader.u8()?,
+                data: reader.remaining(end)?,
+            },
+            RecordType::OPT => {
+                let mut options = Vec::new();
+                while reader.pos < end {
+                    let code = reader.u16()?;
+                    let length = usize::from(reader.u16()?);
+                    options.push(RawOption {
+                        code,
+                        data: reader.bytes(length)?.to_vec(),
+                    });
+                }
+
+                RecordData::Opt(Opt {
+                    udp_payload_size: class,
+                    extended_rcode: (ttl >> 24) as u8,
+                    version: (ttl >> 16) as u8,
+                    dnssec_ok: ttl & 0x8000 != 0,
+                    options,
+                })
+            }
+            _ => RecordData::Unknown(reader.remaining(end)?),
+        })
+    }
+}
+
+/// Delegation signer (RFC 4034 5.1)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ds {
+    pub key_tag: u16,
+    pub algorithm: u8,
+    pub digest_type: u8,
+    pub digest: Vec<u8>,
+}
+
+/// DNS public key (RFC 4034 2.1)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dnskey {
+    pub flags: u16,
+    /// Always 3
+    pub protocol: u8,
+    pub algorithm: u8,
+    pub public_key: Vec<u8>,
+}
+
+impl Dnskey {
+    /// Zone Key flag (bit 7)
+    pub fn is_zone_key(&self) -> bool {
+        self.flags & 0x0100 != 0
+    }
+
+    /// Secure Entry Point flag (bit 15), usually set on key signing keys
+    pub fn is_secure_entry_point(&self) -> bool {
+        self.flags & 0x0001 != 0
+    }
+
+    /// Key tag as calculated in RFC 4034 Appendix B
+    pub fn key_tag(&self) -> u16 {
+        let mut rdata = Vec::with_capacity(4 + self.public_key.len());
+        rdata.extend_from_slice(&self.flags.to_be_bytes());
+        rdata.push(self.protocol);
+        rdata.push(self.algorithm);
+        rdata.extend_from_slice(&self.public_key);
+
+        let mut accumulator: u32 = 0;
+        for (index, byte) in rdata.iter().enumerate() {
+            if index & 1 == 0 {
+                accumulator += u32::from(*byte) << 8;
+            } else {
+                accumulator += u32::from(*byte);
+            }
+        }
+
+        accumulator += (accumulator >> 16) & 0xFFFF;
+        (accumulator & 0xFFFF) as u16
+    }
+}
+
+/// Resource record signature (RFC 4034 3.1)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rrsig {
+    pub type_covered: RecordType,
+    pub algorithm: u8,
+    pub labels: u8,
+    pub original_ttl: u32,
+    /// signature expiration as seconds since the unix epoch, modulo 2^32
+    pub expiration: u32,
+    /// signature inception as seconds since the unix epoch, modulo 2^32
+    pub inception: u32,
+    pub key_tag: u16,
+    pub signer_name: Name,
+    pub signature: Vec<u8>,
+}
+
+/// EDNS0 pseudo-record (RFC 6891 6.1.2)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Opt {
+    /// requestor's UDP payload size
+    pub udp_payload_size: u16,
+    /// upper 8 bits of the extended response code
+    pub extended_rcode: u8,
+    pub version: u8,
+    /// DNSSEC OK bit (RFC 3225)
+    pub dnssec_ok: bool,
+    pub options: Vec<RawOption>,
+}
+
+/// Undecoded EDNS0 option. See [`EdnsOption`](super::edns::EdnsOption) for the decoded form.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawOption {
+    pub code: u16,
+    pub data: Vec<u8>,
+}
+
+/// Domain name made up of a sequence of labels, not including the empty root label. Comparisons
+/// between names are case-insensitive as required by RFC 4343.
+#[derive(Clone, Default)]
+pub struct Name {
+    labels: Vec<Vec<u8>>,
+}
+
+impl Name {
+    pub fn root() -> Self {
+        Name::default()
+    }
+
+    pub fn from_labels(labels: Vec<Vec<u8>>) -> Self {
+        Name { labels }
+    }
+
+    pub fn labels(&self) -> &[Vec<u8>] {
+        &self.labels
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// Check if this name is equal to or below `parent` in the DNS hierarchy.
+    pub fn is_subdomain_of(&self, parent: &Name) -> bool {
+        self.labels.len() >= parent.labels.len()
+            && self
+                .labels
+                .iter()
+                .rev()
+                .zip(parent.labels.iter().rev())
+                .all(|(a, b)| a.eq_ignore_ascii_case(b))
+    }
+
+    /// Copy of this name with all ASCII letters converted to lowercase
+    pub fn to_lowercase(&self) -> Name {
+        Name {
+            labels: self
+                .labels
+                .iter()
+                .map(|label| label.to_ascii_lowercase())
+                .collect(),
+        }
+    }
+
+    /// Append the uncompressed wire format of this name to a buffer.
+    pub fn write_wire(&self, buffer: &mut Vec<u8>) {
+        for label in &self.labels {
+            buffer.push(label.len() as u8);
+            buffer.extend_from_slice(label);
+        }
+        buffer.push(0);
+    }
+}
+
+impl PartialEq for Name {
+    fn eq(&self, other: &Self) -> bool {
+        self.labels.len() == other.labels.len() && self.is_subdomain_of(other)
+    }
+}
+
+impl Eq for Name {}
+
+impl Hash for Name {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for label in &self.labels {
+            state.write_usize(label.len());
+            for byte in label {
+                state.write_u8(byte.to_ascii_lowercase());
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Name {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Name(\"{}\")", self)
+    }
+}
+
+/// Formats the name in presentation format with a trailing dot. Special and non-printable
+/// characters are escaped as described in RFC 4343 2.1.
+impl Display for Name {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.labels.is_empty() {
+            return write!(f, ".");
+        }
+
+        for label in &self.labels {
+            for &byte in label {
+                match byte {
+                    b'.' | b'\\' => write!(f, "\\{}", byte as char)?,
+                    0x21..=0x7E => write!(f, "{}", byte as char)?,
+                    _ => write!(f, "\\{:03}", byte)?,
+                }
+            }
+            write!(f, ".")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RecordType {
+    A,
+    NS,
+    CNAME,
+    SOA,
+    PTR,
+    MX,
+    TXT,
+    AAAA,
+    SRV,
+    NAPTR,
+    OPT,
+    DS,
+    RRSIG,
+    NSEC,
+    DNSKEY,
+    NSEC3,
+    NSEC3PARAM,
+    TLSA,
+    CDS,
+    CDNSKEY,
+    ANY,
+    Other(u16),
+}
+
+impl From<u16> for RecordType {
+    fn from(value: u16) -> Self {
+        match value {
+            1 => RecordType::A,
+            2 => RecordType::NS,
+            5 => RecordType::CNAME,
+            6 => RecordType::SOA,
+            12 => RecordType::PTR,
+            15 => RecordType::MX,
+            16 => RecordType::TXT,
+            28 => RecordType::AAAA,
+            33 => RecordType::SRV,
+            35 => RecordType::NAPTR,
+            41 => RecordType::OPT,
+            43 => RecordType::DS,
+            46 => RecordType::RRSIG,
+            47 => RecordType::NSEC,
+            48 => RecordType::DNSKEY,
+            50 => RecordType::NSEC3,
+            51 => RecordType::NSEC3PARAM,
+            52 => RecordType::TLSA,
+            59 => RecordType::CDS,
+            60 => RecordType::CDNSKEY,
+            255 => RecordType::ANY,
+            x => RecordType::Other(x),
+        }
+    }
+}
+
+impl From<RecordType> for u16 {
+    fn from(value: RecordType) -> Self {
+        match value {
+            RecordType::A => 1,
+            RecordType::NS => 2,
+            RecordType::CNAME => 5,
+            RecordType::SOA => 6,
+            RecordType::PTR => 12,
+            RecordType::MX => 15,
+            RecordType::TXT => 16,
+            RecordType::AAAA => 28,
+            RecordType::SRV => 33,
+            RecordType::NAPTR => 35,
+            RecordType::OPT => 41,
+            RecordType::DS => 43,
+            RecordType::RRSIG => 46,
+            RecordType::NSEC => 47,
+            RecordType::DNSKEY => 48,
+            RecordType::NSEC3 => 50,
+            RecordType::NSEC3PARAM => 51,
+            RecordType::TLSA => 52,
+            RecordType::CDS => 59,
+            RecordType::CDNSKEY => 60,
+            RecordType::ANY => 255,
+            RecordType::Other(x) => x,
+        }
+    }
+}
+
+impl Display for RecordType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordType::Other(x) => write!(f, "TYPE{}", x),
+            x => write!(f, "{:?}", x),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Class {
+    IN,
+    CH,
+    HS,
+    NONE,
+    ANY,
+    Other(u16),
+}
+
+impl From<u16> for Class {
+    fn from(value: u16) -> Self {
+        match value {
+            1 => Class::IN,
+            3 => Class::CH,
+            4 => Class::HS,
+            254 => Class::NONE,
+            255 => Class::ANY,
+            x => Class::Other(x),
+        }
+    }
+}
+
+impl From<Class> for u16 {
+    fn from(value: Class) -> Self {
+        match value {
+            Class::IN => 1,
+            Class::CH => 3,
+            Class::HS => 4,
+            Class::NONE => 254,
+            Class::ANY => 255,
+            Class::Other(x) => x,
+        }
+    }
+}
+
+/// Response codes from RFC 1035, RFC 2136 and RFC 6891
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ResponseCode {
+    NoError,
+    FormErr,
+    ServFail,
+    NXDomain,
+    NotImp,
+    Refused,
+    YXDomain,
+    YXRRSet,
+    NXRRSet,
+    NotAuth,
+    NotZone,
+    BadVers,
+    Other(u16),
+}
+
+impl From<u16> for ResponseCode {
+    fn from(value: u16) -> Self {
+        match value {
+            0 => ResponseCode::NoError,
+            1 => ResponseCode::FormErr,
+            2 => ResponseCode::ServFail,
+            3 => ResponseCode::NXDomain,
+            4 => ResponseCode::NotImp,
+            5 => ResponseCode::Refused,
+            6 => ResponseCode::YXDomain,
+            7 => ResponseCode::YXRRSet,
+            8 => ResponseCode::NXRRSet,
+            9 => ResponseCode::NotAuth,
+            10 => ResponseCode::NotZone,
+            16 => ResponseCode::BadVers,
+            x => ResponseCode::Other(x),
+        }
+    }
+}
+
+/// Cursor over the raw message. Names can refer back to anywhere in the message, so the entire
+/// buffer is kept around instead of slicing off the parts that were already read.
+struct Reader<'a> {
+    buffer: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buffer: &'a [u8]) -> Self {
+        Reader { buffer, pos: 0 }
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], DecodeError> {
+        let bytes = self
+            .buffer
+            .get(self.pos..self.pos + length)
+            .ok_or(DecodeError::UnexpectedEnd { offset: self.pos })?;
+
+        self.pos += length;
+        Ok(bytes)
+    }
+
+    fn remaining(&mut self, end: usize) -> Result<Vec<u8>, DecodeError> {
+        if end < self.pos {
+            return Err(DecodeError::RecordLength { offset: self.pos });
+        }
+
+        Ok(self.bytes(end - self.pos)?.to_vec())
+    }
+
+    fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, DecodeError> {
+        Ok(u16::from_be_bytes(
+            self.bytes(2)?.try_into().expect("exact length"),
+        ))
+    }
+
+    fn u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_be_bytes(
+            self.bytes(4)?.try_into().expect("exact length"),
+        ))
+    }
+
+    fn character_string(&mut self) -> Result<Vec<u8>, DecodeError> {
+        let length = usize::from(self.u8()?);
+        Ok(self.bytes(length)?.to_vec())
+    }
+
+    /// Read a possibly compressed name (RFC 1035 4.1.4). To guarantee termination, every
+    /// compression pointer must point strictly before the previous one.
+    fn name(&mut self) -> Result<Name, DecodeError> {
+        let start = self.pos;
+        let mut labels = Vec::new();
+        let mut wire_length = 1;
+
+        let mut cursor = self.pos;
+        let mut jump_limit = cursor;
+        let mut jumped = false;
+
+        loop {
+            let length = *self
+                .buffer
+                .get(cursor)
+                .ok_or(DecodeError::UnexpectedEnd { offset: cursor })?;
+
+            match length & 0xC0 {
+                0x00 if length == 0 => {
+                    if !jumped {
+                        self.pos = cursor + 1;
+                    }
+                    return Ok(Name { labels });
+                }
+                0x00 => {
+                    let label_end = cursor + 1 + usize::from(length);
+                    let label = self
+                        .buffer
+                        .get(cursor + 1..label_end)
+                        .ok_or(DecodeError::UnexpectedEnd { offset: cursor })?;
+
+                    wire_length += 1 + label.len();
+                    if wire_length > MAX_NAME_LENGTH {
+                        return Err(DecodeError::NameTooLong { offset: start });
+                    }
+
+                    labels.push(label.to_vec());
+                    cursor = label_end;
+                }
+                0xC0 => {
+                    let low = *self
+                        .buffer
+                        .get(cursor + 1)
+                        .ok_or(DecodeError::UnexpectedEnd { offset: cursor })?;
+                    let target = usize::from(length & 0x3F) << 8 | usize::from(low);
+
+                    if target >= jump_limit {
+                        return Err(DecodeError::InvalidPointer { offset: cursor });
+                    }
+
+                    if !jumped {
+                        self.pos = cursor + 2;
+                        jumped = true;
+                    }
+
+                    jump_limit = target;
+                    cursor = target;
+                }
+                _ => return Err(DecodeError::InvalidLabel { offset: cursor }),
+            }
+        }
+    }
+
+    /// Read the type bitmap used by NSEC and NSEC3 records (RFC 4034 4.1.2)
+    fn type_bitmap(&mut self, end: usize) -> Result<Vec<RecordType>, DecodeError> {
+        let mut types = Vec::new();
+
+        while self.pos < end {
+            let window = u16::from(self.u8()?);
+            let length = usize::from(self.u8()?);
+
+            for (index, byte) in self.bytes(length)?.iter().enumerate() {
+                for bit in 0..8 {
+                    if byte & (0x80 >> bit) != 0 {
+                        types.push(RecordType::from(window << 8 | (index * 8 + bit) as u16));
+                    }
+                }
+            }
+        }
+
+        Ok(types)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Response to `www.ripe.net A` with a CNAME answer compressed against the question and an
+    /// OPT record, encoded without padding as some probes do
+    const ABUF: &str =
+        "vu+BgAABAAIAAAABA3d3dwRyaXBlA25ldAAAAQABwAwABQABAAABLAACwBDAEAABAAEAAAEsAATBAAsZAAApAgAAAIAAAAA";
+
+    fn name(name: &str) -> Name {
+        Name::from_labels(
+            name.split('.')
+                .map(|label| label.as_bytes().to_vec())
+                .collect(),
+        )
+    }
+
+    fn wire(name: &str) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        self::name(name).write_wire(&mut buffer);
+        buffer
+    }
+
+    /// Header of a response followed by a question for `example.com A`, which starts at offset 12
+    fn message(answers: u16, authorities: u16, additionals: u16) -> Vec<u8> {
+        let mut buffer = vec![0x12, 0x34, 0x81, 0x80, 0, 1];
+        for count in [answers, authorities, additionals] {
+            buffer.extend_from_slice(&count.to_be_bytes());
+        }
+        buffer.extend(wire("example.com"));
+        buffer.extend_from_slice(&[0, 1, 0, 1]);
+        buffer
+    }
+
+    /// Append a record of class IN owned by `owner`, given in wire format
+    fn record(buffer: &mut Vec<u8>, owner: &[u8], rtype: u16, rdata: &[u8]) {
+        buffer.extend_from_slice(owner);
+        buffer.extend_from_slice(&rtype.to_be_bytes());
+        buffer.extend_from_slice(&[0, 1, 0, 0, 0x0E, 0x10]);
+        buffer.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        buffer.extend_from_slice(rdata);
+    }
+
+    #[test]
+    fn decodes_abuf() {
+        let message = Message::from_base64(ABUF).unwrap();
+
+        assert_eq!(message.header.id, 0xBEEF);
+        assert!(message.header.is_response);
+        assert!(message.header.recursion_desired);
+        assert!(message.header.recursion_available);
+        assert!(!message.header.authentic_data);
+        assert_eq!(message.response_code(), ResponseCode::NoError);
+
+        assert_eq!(message.questions.len(), 1);
+        assert_eq!(message.questions[0].name, name("www.ripe.net"));
+        assert_eq!(message.questions[0].qtype, RecordType::A);
+        assert_eq!(message.questions[0].qclass, Class::IN);
+
+        assert_eq!(message.answers.len(), 2);
+        assert_eq!(message.answers[0].name, name("www.ripe.net"));
+        assert_eq!(message.answers[0].ttl, 300);
+        assert_eq!(message.answers[0].data, RecordData::Cname(name("ripe.net")));
+        assert_eq!(message.answers[1].name, name("ripe.net"));
+        assert_eq!(
+            message.answers[1].data,
+            RecordData::A([193, 0, 11, 25].into())
+        );
+
+        let opt = message.opt().unwrap();
+        assert_eq!(opt.udp_payload_size, 512);
+        assert!(opt.dnssec_ok);
+        assert!(opt.options.is_empty());
+        assert_eq!(message.records().count(), 3);
+    }
+
+    #[test]
+    fn decodes_each_record_type() {
+        let owner = [0xC0, 12];
+        let mut buffer = message(16, 0, 1);
+
+        record(&mut buffer, &owner, 1, &[192, 0, 2, 1]);
+        let mut aaaa = [0u8; 16];
+        aaaa[..4].copy_from_slice(&[0x20, 0x01, 0x0D, 0xB8]);
+        aaaa[15] = 1;
+        record(&mut buffer, &owner, 28, &aaaa);
+        record(&mut buffer, &owner, 2, &wire("ns.example.com"));
+        // Compressed against the question
+        record(&mut buffer, &owner, 5, &[3, b'w', b'w', b'w', 0xC0, 12]);
+        record(&mut buffer, &owner, 12, &wire("host.example.com"));
+        record(
+            &mut buffer,
+            &owner,
+            15,
+            &[[0, 10].as_slice(), &wire("mx.example.com")].concat(),
+        );
+
+        let mut soa = [wire("ns.example.com"), wire("admin.example.com")].concat();
+        for value in [2023010101u32, 7200, 3600, 1209600, 300] {
+            soa.extend_from_slice(&value.to_be_bytes());
+        }
+        record(&mut buffer, &owner, 6, &soa);
+        record(&mut buffer, &owner, 16, b"\x05hello\x00\x02\xFF\xFE");
+        record(
+            &mut buffer,
+            &owner,
+            33,
+            &[
+                [0, 1, 0, 2, 0x01, 0xBB].as_slice(),
+                &wire("sip.example.com"),
+            ]
+            .concat(),
+        );
+        record(
+            &mut buffer,
+            &owner,
+            35,
+            &[
+                [0, 100, 0, 10, 1, b'S', 0, 0].as_slice(),
+                &wire("_sip._udp.example.com"),
+            ]
+            .concat(),
+        );
+        record(&mut buffer, &owner, 43, &[0x4F, 0x66, 13, 2, 0xAA, 0xBB]);
+        record(&mut buffer, &owner, 48, &[1, 1, 3, 15, 0x11, 0x22]);
+
+        let mut rrsig = vec![0, 1, 13, 2, 0, 0, 0x0E, 0x10];
+        rrsig.extend_from_slice(&1700000000u32.to_be_bytes());
+        rrsig.extend_from_slice(&1690000000u32.to_be_bytes());
+        rrsig.extend_from_slice(&[0x4F, 0x66]);
+        rrsig.extend_from_slice(&[0xC0, 20]);
+        rrsig.extend_from_slice(&[0xDE, 0xAD]);
+        record(&mut buffer, &owner, 46, &rrsig);
+
+        // Types 1, 2, 46 and 47 in window 0 followed by type 257 in window 1
+        let nsec = [
+            wire("a.example.com"),
+            vec![0, 6, 0x60, 0, 0, 0, 0, 0x03, 1, 1, 0x40],
+        ]
+        .concat();
+        record(&mut buffer, &owner, 47, &nsec);
+        record(&mut buffer, &owner, 52, &[3, 1, 1, 0x01, 0x02]);
+        record(&mut buffer, &owner, 99, &[0xCA, 0xFE]);
+
+        // OPT with an NSID request
+        buffer.extend_from_slice(&[
+            0, 0, 41, 0x04, 0xD0, 0x01, 0x00, 0x80, 0x00, 0, 4, 0, 3, 0, 0,
+        ]);
+
+        let message = Message::parse(&buffer).unwrap();
+        let data: Vec<&RecordData> = message.answers.iter().map(|record| &record.data).collect();
+        assert!(message
+            .answers
+            .iter()
+            .all(|record| record.name == name("example.com")));
+
+        assert_eq!(data[0], &RecordData::A([192, 0, 2, 1].into()));
+        assert_eq!(data[1], &RecordData::Aaaa("2001:db8::1".parse().unwrap()));
+        assert_eq!(data[2], &RecordData::Ns(name("ns.example.com")));
+        assert_eq!(data[3], &RecordData::Cname(name("www.example.com")));
+        assert_eq!(data[4], &RecordData::Ptr(name("host.example.com")));
+        assert_eq!(
+            data[5],
+            &RecordData::Mx {
+                preference: 10,
+                exchange: name("mx.example.com"),
+            }
+        );
+        assert_eq!(
+            data[6],
+            &RecordData::Soa {
+                mname: name("ns.example.com"),
+                rname: name("admin.example.com"),
+                serial: 2023010101,
+                refresh: 7200,
+                retry: 3600,
+                expire: 1209600,
+                minimum: 300,
+            }
+        );
+        assert_eq!(
+            data[7],
+            &RecordData::Txt(vec![b"hello".to_vec(), Vec::new(), vec![0xFF, 0xFE]])
+        );
+        assert_eq!(
+            data[8],
+            &RecordData::Srv {
+                priority: 1,
+                weight: 2,
+                port: 443,
+                target: name("sip.example.com"),
+            }
+        );
+        assert_eq!(
+            data[9],
+            &RecordData::Naptr {
+                order: 100,
+                preference: 10,
+                flags: b"S".to_vec(),
+                services: Vec::new(),
+                regexp: Vec::new(),
+                replacement: name("_sip._udp.example.com"),
+            }
+        );
+        assert_eq!(
+            data[10],
+            &RecordData::Ds(Ds {
+                key_tag: 0x4F66,
+                algorithm: 13,
+                digest_type: 2,
+                digest: vec![0xAA, 0xBB],
+            })
+        );
+        assert_eq!(
+            data[11],
+            &RecordData::Dnskey(Dnskey {
+                flags: 257,
+                protocol: 3,
+                algorithm: 15,
+                public_key: vec![0x11, 0x22],
+            })
+        );
+        assert_eq!(
+            data[12],
+            &RecordData::Rrsig(Rrsig {
+                type_covered: RecordType::A,
+                algorithm: 13,
+                labels: 2,
+                original_ttl: 3600,
+                expiration: 1700000000,
+                inception: 1690000000,
+                key_tag: 0x4F66,
+                signer_name: name("com"),
+                signature: vec![0xDE, 0xAD],
+            })
+        );
+        assert_eq!(
+            data[13],
+            &RecordData::Nsec {
+                next_domain_name: name("a.example.com"),
+                types: vec![
+                    RecordType::A,
+                    RecordType::NS,
+                    RecordType::RRSIG,
+                    RecordType::NSEC,
+                    RecordType::Other(257),
+                ],
+            }
+        );
+        assert_eq!(
+            data[14],
+            &RecordData::Tlsa {
+                usage: 3,
+                selector: 1,
+                matching_type: 1,
+                data: vec![0x01, 0x02],
+            }
+        );
+        assert_eq!(data[15], &RecordData::Unknown(vec![0xCA, 0xFE]));
+        assert_eq!(message.answers[15].rtype, RecordType::Other(99));
+
+        let opt = message.opt().unwrap();
+        assert_eq!(opt.udp_payload_size, 1232);
+        assert_eq!(opt.extended_rcode, 1);
+        assert_eq!(opt.version, 0);
+        assert!(opt.dnssec_ok);
+        assert_eq!(
+            opt.options,
+            vec![RawOption {
+                code: 3,
+                data: Vec::new(),
+            }]
+        );
+        // BADVERS is split between the OPT record and the header
+        assert_eq!(message.response_code(), ResponseCode::BadVers);
+    }
+
+    #[test]
+    fn follows_backward_pointers() {
+        let mut buffer = message(3, 0, 0);
+        // "mail" followed by a pointer to the question name
+        record(
+            &mut buffer,
+            &[4, b'm', b'a', b'i', b'l', 0xC0, 12],
+            1,
+            &[192, 0, 2, 1],
+        );
+        // A pointer to the name above, which itself ends in a pointer
+        record(&mut buffer, &[0xC0, 29], 1, &[192, 0, 2, 2]);
+        // A pointer into the middle of the question name
+        record(&mut buffer, &[0xC0, 20], 2, &[0xC0, 29]);
+
+        let message = Message::parse(&buffer).unwrap();
+        assert_eq!(message.answers[0].name, name("mail.example.com"));
+        assert_eq!(message.answers[1].name, name("mail.example.com"));
+        assert_eq!(
+            message.answers[1].data,
+            RecordData::A([192, 0, 2, 2].into())
+        );
+        assert_eq!(message.answers[2].name, name("com"));
+        assert_eq!(
+            message.answers[2].data,
+            RecordData::Ns(name("mail.example.com"))
+        );
+    }
+
+    #[test]
+    fn rejects_forward_pointers() {
+        let mut buffer = message(1, 0, 0);
+        // The answer name points to the rdata of its own record
+        record(&mut buffer, &[0xC0, 41], 2, &wire("ns.example.com"));
+
+        assert_eq!(
+            Message::parse(&buffer),
+            Err(DecodeError::InvalidPointer { offset: 29 })
+        );
+    }
+
+    #[test]
+    fn rejects_pointer_loops() {
+        // A pointer to itself
+        let mut buffer = message(0, 0, 0);
+        buffer.truncate(12);
+        buffer.extend_from_slice(&[0xC0, 12, 0, 1, 0, 1]);
+        assert_eq!(
+            Message::parse(&buffer),
+            Err(DecodeError::InvalidPointer { offset: 12 })
+        );
+
+        // "a" followed by a pointer to the answer name, which points back to "a"
+        let mut buffer = message(1, 0, 0);
+        buffer[4..6].copy_from_slice(&[0, 0]);
+        buffer.truncate(12);
+        buffer.extend_from_slice(&[1, b'a', 0xC0, 16]);
+        record(&mut buffer, &[0xC0, 12], 1, &[192, 0, 2, 1]);
+        assert_eq!(
+            Message::parse(&buffer),
+            Err(DecodeError::InvalidPointer { offset: 14 })
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        let mut buffer = message(0, 0, 0);
+        buffer[12] = 0x47;
+        assert_eq!(
+            Message::parse(&buffer),
+            Err(DecodeError::InvalidLabel { offset: 12 })
+        );
+
+        let mut buffer = message(0, 0, 0);
+        buffer.truncate(12);
+        for _ in 0..5 {
+            buffer.push(63);
+            buffer.extend_from_slice(&[b'a'; 63]);
+        }
+        buffer.extend_from_slice(&[0, 0, 1, 0, 1]);
+        assert_eq!(
+            Message::parse(&buffer),
+            Err(DecodeError::NameTooLong { offset: 12 })
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_rdata() {
+        // The rdata length runs past the end of the message
+        let mut buffer = message(1, 0, 0);
+        record(&mut buffer, &[0xC0, 12], 1, &[192, 0, 2, 1]);
+        buffer.truncate(buffer.len() - 2);
+        assert_eq!(
+            Message::parse(&buffer),
+            Err(DecodeError::UnexpectedEnd { offset: 41 })
+        );
+
+        // The rdata length is shorter than the address it should hold
+        let mut buffer = message(1, 0, 0);
+        record(&mut buffer, &[0xC0, 12], 1, &[192, 0, 2]);
+        buffer.push(1);
+        assert_eq!(
+            Message::parse(&buffer),
+            Err(DecodeError::RecordLength { offset: 41 })
+        );
+
+        // A name in the rdata continues past the rdata length
+        let mut buffer = message(1, 0, 0);
+        record(&mut buffer, &[0xC0, 12], 2, &[2, b'n', b's']);
+        buffer.extend_from_slice(&[0xC0, 12]);
+        assert_eq!(
+            Message::parse(&buffer),
+            Err(DecodeError::RecordLength { offset: 41 })
+        );
+    }
+
+    #[test]
+    fn rejects_counts_past_the_end() {
+        let mut buffer = message(u16::MAX, 0, 0);
+        record(&mut buffer, &[0xC0, 12], 1, &[192, 0, 2, 1]);
+        assert_eq!(
+            Message::parse(&buffer),
+            Err(DecodeError::UnexpectedEnd { offset: 45 })
+        );
+
+        let mut buffer = message(0, 0, 0);
+        buffer[5] = 2;
+        assert_eq!(
+            Message::parse(&buffer),
+            Err(DecodeError::UnexpectedEnd { offset: 29 })
+        );
+
+        assert_eq!(
+            Message::parse(&[0x12, 0x34, 0x81, 0x80, 0]),
+            Err(DecodeError::UnexpectedEnd { offset: 4 })
+        );
+        assert!(matches!(
+            Message::from_base64("not base64!"),
+            Err(DecodeError::Base64(_))
+        ));
+    }
+
+    #[test]
+    fn compares_names_without_case() {
+        let upper = name("WWW.Example.COM");
+        assert_eq!(upper, name("www.example.com"));
+        assert!(upper.is_subdomain_of(&name("example.com")));
+        assert!(!name("example.com").is_subdomain_of(&upper));
+        assert!(upper.is_subdomain_of(&Name::root()));
+        assert_eq!(upper.to_string(), "WWW.Example.COM.");
+        assert_eq!(Name::root().to_string(), ".");
+        assert_eq!(
+            Name::from_labels(vec![b"a.b".to_vec(), vec![b' ']]).to_string(),
+            "a\\.b.\\032."
+        );
+    }
+}
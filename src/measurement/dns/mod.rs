@@ -0,0 +1,468 @@
+use crate::general::{AddressFamily, Protocol, UnixTimestamp};
+use crate::measurement::Measurement;
+use crate::serde_utils::one_or_many;
+use message::{DecodeError, Message};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+#[cfg(feature = "dnssec")]
+pub mod dnssec;
+pub mod edns;
+pub mod message;
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
+pub struct Dns<'a> {
+    /// [optional] IP version: "4" or "6" (int)
+    pub af: Option<AddressFamily>,
+    // /// [optional] instance ID for a collection of related measurement results (int)
+    // bundle: Option<i64>,
+    /// [optional] IP address of the destination (string)
+    pub dst_addr: Option<Cow<'a, str>>,
+    /// [optional] hostname of the destination (string)
+    pub dst_name: Option<Cow<'a, str>>,
+    /// [optional] port of the destination (string)
+    pub dst_port: Option<Cow<'a, str>>,
+    /// [optional] error message (object)
+    pub error: Option<DNSLookupError<'a>>,
+    /// "TCP" or "UDP" (string)
+    pub proto: Option<Protocol>,
+    /// [optional] query payload buffer which was sent to the server, base64 encoded (string)
+    pub qbuf: Option<Cow<'a, str>>,
+    /// [optional] response from the DNS server (object)
+    pub result: Option<DNSResponse<'a>>,
+    /// [optional] an array of objects containing all the fields of a DNS result object, except for
+    /// the fields: fw, from, group_id, msm_id, prb_id, and type. Available for queries sent to each
+    /// local resolver. (array of objects)
+    pub resultset: Option<Vec<DNSResult<'a>>>,
+    /// [optional] retry count (int)
+    pub retry: Option<u32>,
+    /// [optional] sequence number of this result within a group of results, available if the
+    /// resolution is done by the probe's local resolver (int)
+    pub subid: Option<i64>,
+    /// [optional] total number of results within a group (int)
+    pub submax: Option<u32>,
+
+    pub name: Option<Cow<'a, str>>,
+}
+
+impl<'a> Dns<'a> {
+    /// Decode the query sent by the probe. This is only available for measurements which set the
+    /// `include_qbuf` option.
+    pub fn decode_qbuf(&self) -> Option<Result<Message, DecodeError>> {
+        self.qbuf.as_deref().map(Message::from_base64)
+    }
+
+    /// Decode the answer received by the probe if there was a single result.
+    pub fn decode_abuf(&self) -> Option<Result<Message, DecodeError>> {
+        self.result.as_ref().map(DNSResponse::decode_abuf)
+    }
+}
+
+impl<'a> Measurement<'a, Dns<'a>> {
+    /// Iterate over every result in this measurement. Queries sent to each of the probe's local
+    /// resolvers report one `resultset` entry per resolver, while all other measurements report a
+    /// single result at the top level. Both are flattened into the same form here.
+    pub fn iter_results(&self) -> impl Iterator<Item = DNSResultRef<'_>> {
+        let top_level = match &self.resultset {
+            Some(_) => None,
+            None => Some(DNSResultRef {
+                time: self.timestamp,
+                lts: self.lts,
+                af: self.af,
+                dst_addr: self.dst_addr.as_deref(),
+                proto: self.proto,
+                qbuf: self.qbuf.as_deref(),
+                result: self.result.as_ref(),
+                error: self.error.as_ref(),
+                retry: self.retry,
+                subid: self.subid,
+                submax: self.submax,
+            }),
+        };
+
+        top_level
+            .into_iter()
+            .chain(self.resultset.iter().flatten().map(DNSResultRef::from))
+    }
+}
+
+/// A single entry of [`Dns::resultset`] holding the outcome of the query sent to one resolver.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
+pub struct DNSResult<'a> {
+    /// unix timestamp of the start time of the measurement (int)
+    #[cfg_attr(feature = "chrono", serde(with = "chrono::serde::ts_seconds"))]
+    pub time: UnixTimestamp,
+    /// [optional] last time synchronised. How long ago (in seconds) the clock of the probe was
+    /// found to be in sync with that of a controller. The value -1 is used to indicate that the
+    /// probe does not know whether it is in sync (int)
+    pub lts: Option<i64>,
+    /// [optional] IP version: "4" or "6" (int)
+    pub af: Option<AddressFamily>,
+    /// [optional] IP address of the resolver (string)
+    pub dst_addr: Option<Cow<'a, str>>,
+    /// [optional] hostname of the destination (string)
+    pub dst_name: Option<Cow<'a, str>>,
+    /// [optional] port of the destination (string)
+    pub dst_port: Option<Cow<'a, str>>,
+    /// [optional] source address used by the probe (string)
+    pub src_addr: Option<Cow<'a, str>>,
+    /// "TCP" or "UDP" (string)
+    pub proto: Option<Protocol>,
+    /// [optional] query payload buffer which was sent to the server, base64 encoded (string)
+    pub qbuf: Option<Cow<'a, str>>,
+    /// [optional] response from the DNS server (object)
+    pub result: Option<DNSResponse<'a>>,
+    /// [optional] error message (object)
+    pub error: Option<DNSLookupError<'a>>,
+    /// [optional] retry count (int)
+    pub retry: Option<u32>,
+    /// [optional] sequence number of this result within a group of results (int)
+    pub subid: Option<i64>,
+    /// [optional] total number of results within a group (int)
+    pub submax: Option<u32>,
+}
+
+impl<'a> DNSResult<'a> {
+    /// Decode the query sent by the probe to this resolver.
+    pub fn decode_qbuf(&self) -> Option<Result<Message, DecodeError>> {
+        self.qbuf.as_deref().map(Message::from_base64)
+    }
+}
+
+/// Borrowed view of a single DNS result produced by [`Measurement::iter_results`]. This has the
+/// same shape regardless of whether the result came from a `resultset` entry or the top level of
+/// the measurement.
+#[derive(Copy, Clone, Debug)]
+pub struct DNSResultRef<'a> {
+    /// start time of the query. For top level results this is the measurement timestamp.
+    pub time: UnixTimestamp,
+    pub lts: Option<i64>,
+    pub af: Option<AddressFamily>,
+    pub dst_addr: Option<&'a str>,
+    pub proto: Option<Protocol>,
+    pub qbuf: Option<&'a str>,
+    pub result: Option<&'a DNSResponse<'a>>,
+    pub error: Option<&'a DNSLookupError<'a>>,
+    pub retry: Option<u32>,
+    pub subid: Option<i64>,
+    pub submax: Option<u32>,
+}
+
+impl<'a> DNSResultRef<'a> {
+    /// Decode the query sent by the probe.
+    pub fn decode_qbuf(&self) -> Option<Result<Message, DecodeError>> {
+        self.qbuf.map(Message::from_base64)
+    }
+
+    /// Decode the answer received by the probe.
+    pub fn decode_abuf(&self) -> Option<Result<Message, DecodeError>> {
+        self.result.map(DNSResponse::decode_abuf)
+    }
+}
+
+impl<'a> From<&'a DNSResult<'a>> for DNSResultRef<'a> {
+    fn from(result: &'a DNSResult<'a>) -> Self {
+        DNSResultRef {
+            time: result.time,
+            lts: result.lts,
+            af: result.af,
+            dst_addr: result.dst_addr.as_deref(),
+            proto: result.proto,
+            qbuf: result.qbuf.as_deref(),
+            result: result.result.as_ref(),
+            error: result.error.as_ref(),
+            retry: result.retry,
+            subid: result.subid,
+            submax: result.submax,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
+pub struct DNSResponse<'a> {
+    /// answer count, RFC 1035 4.1.1 (int)
+    #[serde(rename = "ANCOUNT")]
+    answer_count: u32,
+    /// additional record count, RFC 1035, 4.1.1 (int)
+    #[serde(rename = "ARCOUNT")]
+    additional_record_count: u32,
+    /// query ID, RFC 1035 4.1.1 (int)
+    #[serde(rename = "ID")]
+    id: i64,
+    /// name server count (int)
+    #[serde(rename = "NSCOUNT")]
+    name_server_count: u32,
+    /// number of queries (int)
+    #[serde(rename = "QDCOUNT")]
+    number_of_queries: u32,
+    /// answer payload buffer from the server, base64 encoded (string) See example code for decoding
+    /// the value
+    abuf: Cow<'a, str>,
+    /// first two records from the response decoded by the probe, if they are TXT or SOA; other RR
+    /// can be decoded from "abuf" (array of objects)
+    answers: Option<Vec<DNSRecord<'a>>>,
+    /// [optional] response time in milli seconds (float)
+    rt: Option<f32>,
+    /// [optional] response size (int)
+    size: Option<u64>,
+    /// [optional] TTL (hop limit for IPv6) field from UDP reply packet (from 5010) (int)
+    ttl: Option<u32>,
+    qt: Option<f32>,
+}
+
+impl<'a> DNSResponse<'a> {
+    /// Decode the full answer received by the probe. Unlike `answers`, this includes every section
+    /// of the response and all record types.
+    pub fn decode_abuf(&self) -> Result<Message, DecodeError> {
+        Message::from_base64(&self.abuf)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(tag = "TYPE", rename_all = "UPPERCASE")]
+#[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
+pub enum DNSRecord<'a> {
+    #[serde(rename_all = "UPPERCASE")]
+    Txt {
+        // mname: Option<Cow<'a, str>>,
+        name: Cow<'a, str>,
+        #[serde(deserialize_with = "one_or_many")]
+        rdata: Vec<Cow<'a, str>>,
+    },
+    #[serde(rename_all = "UPPERCASE")]
+    Soa {
+        mname: Cow<'a, str>,
+        name: Cow<'a, str>,
+        rname: Cow<'a, str>,
+        serial: i64,
+        ttl: i64,
+    },
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+#[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
+pub enum DNSLookupError<'a> {
+    /// query timeout in milliseconds (int)
+    Timeout { timeout: u64 },
+    /// error message from resolving the name of the target (string)
+    AddressResolution { getaddrinfo: Cow<'a, str> },
+    /// error message from creating or using the socket (string)
+    Socket { socket: Cow<'a, str> },
+    /// error message from connecting to the target over TCP (string)
+    Connect { connect: Cow<'a, str> },
+    /// error message from the TLS handshake with the target (string)
+    Tls { tls: Cow<'a, str> },
+    /// Any other error reported by the probe
+    Other(HashMap<Cow<'a, str>, Cow<'a, str>>),
+}
+
+impl<'a> DNSLookupError<'a> {
+    pub fn kind(&self) -> DNSErrorKind {
+        match self {
+            DNSLookupError::Timeout { .. } => DNSErrorKind::Timeout,
+            DNSLookupError::AddressResolution { .. } => DNSErrorKind::AddressResolution,
+            DNSLookupError::Socket { .. } => DNSErrorKind::Socket,
+            DNSLookupError::Connect { .. } => DNSErrorKind::Connect,
+            DNSLookupError::Tls { .. } => DNSErrorKind::Tls,
+            DNSLookupError::Other(_) => DNSErrorKind::Other,
+        }
+    }
+
+    /// The error message provided by the probe. Timeouts do not include a message.
+    pub fn message(&self) -> Option<&str> {
+        match self {
+            DNSLookupError::Timeout { .. } | DNSLookupError::Other(_) => None,
+            DNSLookupError::AddressResolution {
+                getaddrinfo: message,
+            }
+            | DNSLookupError::Socket { socket: message }
+            | DNSLookupError::Connect { connect: message }
+            | DNSLookupError::Tls { tls: message } => Some(message),
+        }
+    }
+}
+
+impl<'a> Display for DNSLookupError<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DNSLookupError::Timeout { timeout } => write!(f, "timeout after {}ms", timeout),
+            DNSLookupError::Other(fields) => {
+                let mut fields: Vec<_> = fields.iter().collect();
+                fields.sort_unstable();
+
+                for (index, (key, value)) in fields.into_iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                Ok(())
+            }
+            error => write!(
+                f,
+                "{}: {}",
+                error.kind(),
+                error.message().unwrap_or_default()
+            ),
+        }
+    }
+}
+
+/// Category of a [`DNSLookupError`] without any of the details
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum DNSErrorKind {
+    Timeout,
+    AddressResolution,
+    Socket,
+    Connect,
+    Tls,
+    Other,
+}
+
+impl Display for DNSErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DNSErrorKind::Timeout => write!(f, "timeout"),
+            DNSErrorKind::AddressResolution => write!(f, "getaddrinfo"),
+            DNSErrorKind::Socket => write!(f, "socket"),
+            DNSErrorKind::Connect => write!(f, "connect"),
+            DNSErrorKind::Tls => write!(f, "tls"),
+            DNSErrorKind::Other => write!(f, "other"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::general::unix_seconds;
+    use crate::measurement::DnsMeasurement;
+
+    /// Query for `www.ripe.net A` sent to each of the probe's local resolvers, where the second
+    /// resolver timed out
+    const RESULTSET: &str = r#"{"fw":5080,"lts":27,"msm_id":30001,"msm_name":"Tdig","prb_id":6001,
+        "resultset":[{"time":1669852800,"lts":27,"subid":1,"submax":2,"dst_addr":"192.168.1.1",
+        "af":4,"src_addr":"192.168.1.10","proto":"UDP","result":{"rt":12.345,"size":74,
+        "abuf":"vu+BgAABAAIAAAABA3d3dwRyaXBlA25ldAAAAQABwAwABQABAAABLAACwBDAEAABAAEAAAEsAATBAAsZAAApAgAAAIAAAAA=",
+        "ID":48879,"ANCOUNT":2,"QDCOUNT":1,"NSCOUNT":0,"ARCOUNT":1}},{"time":1669852801,"lts":28,
+        "subid":2,"submax":2,"dst_addr":"fd00::1","af":6,"proto":"UDP","error":{"timeout":5000}}],
+        "timestamp":1669852800,"from":"203.0.113.5","type":"dns","group_id":30001}"#;
+
+    /// The same query sent directly to a single server
+    const SINGLE: &str = r#"{"fw":5080,"lts":12,"af":4,"dst_addr":"193.0.14.129","dst_port":"53",
+        "from":"203.0.113.5","msm_id":30002,"msm_name":"Tdig","prb_id":6001,"proto":"UDP",
+        "result":{"ANCOUNT":2,"ARCOUNT":1,"ID":48879,"NSCOUNT":0,"QDCOUNT":1,"rt":3.5,"size":74,
+        "abuf":"vu+BgAABAAIAAAABA3d3dwRyaXBlA25ldAAAAQABwAwABQABAAABLAACwBDAEAABAAEAAAEsAATBAAsZAAApAgAAAIAAAAA="},
+        "src_addr":"192.168.1.10","timestamp":1669852900,"type":"dns","group_id":30002}"#;
+
+    #[test]
+    fn flattens_resultset() {
+        let measurement: DnsMeasurement = serde_json::from_str(RESULTSET).unwrap();
+        let results: Vec<DNSResultRef> = measurement.iter_results().collect();
+        assert_eq!(results.len(), 2);
+
+        let answered = &results[0];
+        assert_eq!(unix_seconds(answered.time), 1669852800);
+        assert_eq!(answered.lts, Some(27));
+        assert_eq!(answered.dst_addr, Some("192.168.1.1"));
+        assert_eq!((answered.subid, answered.submax), (Some(1), Some(2)));
+        assert!(answered.error.is_none());
+        let message = answered.decode_abuf().unwrap().unwrap();
+        assert_eq!(message.answers.len(), 2);
+        assert!(answered.decode_qbuf().is_none());
+
+        let timed_out = &results[1];
+        assert_eq!(unix_seconds(timed_out.time), 1669852801);
+        assert_eq!(timed_out.dst_addr, Some("fd00::1"));
+        assert!(timed_out.result.is_none());
+        assert!(timed_out.decode_abuf().is_none());
+        assert!(matches!(
+            timed_out.error,
+            Some(DNSLookupError::Timeout { timeout: 5000 })
+        ));
+    }
+
+    #[test]
+    fn wraps_single_result() {
+        let measurement: DnsMeasurement = serde_json::from_str(SINGLE).unwrap();
+        assert!(measurement.resultset.is_none());
+
+        let results: Vec<DNSResultRef> = measurement.iter_results().collect();
+        assert_eq!(results.len(), 1);
+        assert_eq!(unix_seconds(results[0].time), 1669852900);
+        assert_eq!(results[0].lts, Some(12));
+        assert_eq!(results[0].dst_addr, Some("193.0.14.129"));
+        assert_eq!(results[0].subid, None);
+
+        let from_result = results[0].decode_abuf().unwrap().unwrap();
+        assert_eq!(Some(Ok(from_result)), measurement.decode_abuf());
+    }
+
+    #[test]
+    fn keeps_top_level_errors() {
+        let json = r#"{"fw":5080,"af":4,"dst_addr":"193.0.14.129","from":"203.0.113.5",
+            "msm_id":30002,"msm_name":"Tdig","prb_id":6001,"proto":"UDP",
+            "error":{"timeout":5000},"timestamp":1669852900,"type":"dns"}"#;
+        let measurement: DnsMeasurement = serde_json::from_str(json).unwrap();
+
+        let results: Vec<DNSResultRef> = measurement.iter_results().collect();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].result.is_none());
+        assert!(results[0].error.is_some());
+    }
+
+    #[test]
+    fn types_lookup_errors() {
+        let errors = [
+            (
+                r#"{"timeout":5000}"#,
+                DNSErrorKind::Timeout,
+                None,
+                "timeout after 5000ms",
+            ),
+            (
+                r#"{"getaddrinfo":"Name or service not known"}"#,
+                DNSErrorKind::AddressResolution,
+                Some("Name or service not known"),
+                "getaddrinfo: Name or service not known",
+            ),
+            (
+                r#"{"socket":"connect failed Network is unreachable"}"#,
+                DNSErrorKind::Socket,
+                Some("connect failed Network is unreachable"),
+                "socket: connect failed Network is unreachable",
+            ),
+            (
+                r#"{"connect":"Connection refused"}"#,
+                DNSErrorKind::Connect,
+                Some("Connection refused"),
+                "connect: Connection refused",
+            ),
+            (
+                r#"{"tls":"handshake failed"}"#,
+                DNSErrorKind::Tls,
+                Some("handshake failed"),
+                "tls: handshake failed",
+            ),
+            (
+                r#"{"senderror":"sendto failed","extra":"1"}"#,
+                DNSErrorKind::Other,
+                None,
+                "extra: 1, senderror: sendto failed",
+            ),
+        ];
+
+        for (json, kind, message, display) in errors {
+            let error: DNSLookupError = serde_json::from_str(json).unwrap();
+            assert_eq!(error.kind(), kind, "{}", json);
+            assert_eq!(error.message(), message, "{}", json);
+            assert_eq!(error.to_string(), display);
+        }
+    }
+}
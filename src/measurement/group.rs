@@ -0,0 +1,399 @@
+//! Reassembly of results which were split into several parts by the 'all' option. Each part is
+//! sent to a different address of the target and carries a `subid` and `submax`.
+use crate::general::unix_seconds;
+use crate::measurement::dns::DNSResultRef;
+use crate::measurement::http::HttpReply;
+use crate::measurement::{DnsMeasurement, HttpMeasurement, Measurement, Response};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// A result which may be one part of a group of results
+pub trait SubResult {
+    /// sequence number of this result within the group
+    fn subid(&self) -> Option<i64>;
+    /// total number of results within the group
+    fn submax(&self) -> Option<i64>;
+    /// address of the target this part of the group was sent to
+    fn dst_addr(&self) -> Option<&str>;
+}
+
+impl<'a> SubResult for HttpReply<'a> {
+    fn subid(&self) -> Option<i64> {
+        self.subid
+    }
+
+    fn submax(&self) -> Option<i64> {
+        self.submax
+    }
+
+    fn dst_addr(&self) -> Option<&str> {
+        Some(&self.dst_addr)
+    }
+}
+
+impl<'a> SubResult for DNSResultRef<'a> {
+    fn subid(&self) -> Option<i64> {
+        self.subid
+    }
+
+    fn submax(&self) -> Option<i64> {
+        self.submax.map(i64::from)
+    }
+
+    fn dst_addr(&self) -> Option<&str> {
+        self.dst_addr
+    }
+}
+
+impl<T: SubResult> SubResult for &T {
+    fn subid(&self) -> Option<i64> {
+        (**self).subid()
+    }
+
+    fn submax(&self) -> Option<i64> {
+        (**self).submax()
+    }
+
+    fn dst_addr(&self) -> Option<&str> {
+        (**self).dst_addr()
+    }
+}
+
+/// One logical result made up of the parts reported by a probe for a single run of a measurement
+#[derive(Clone, Debug)]
+pub struct SubResultGroup<R> {
+    pub msm_id: i64,
+    pub prb_id: i64,
+    /// instance ID shared by the results of the group, if reported by the probe
+    pub bundle: Option<i64>,
+    /// parts of the group ordered by `subid`
+    pub parts: Vec<R>,
+}
+
+impl<R> SubResultGroup<R> {
+    fn new(key: GroupKey, part: R) -> Self {
+        SubResultGroup {
+            msm_id: key.msm_id,
+            prb_id: key.prb_id,
+            bundle: key.bundle,
+            parts: vec![part],
+        }
+    }
+}
+
+impl<R: SubResult> SubResultGroup<R> {
+    /// Number of parts the probe reported for this group. This is `None` for results which were
+    /// not split into parts.
+    pub fn submax(&self) -> Option<i64> {
+        self.parts.iter().filter_map(SubResult::submax).max()
+    }
+
+    /// Number of parts which did not arrive
+    pub fn missing(&self) -> usize {
+        match self.submax() {
+            Some(submax) => (submax.max(0) as usize).saturating_sub(self.parts.len()),
+            None => 0,
+        }
+    }
+
+    /// All `submax` parts of the group arrived
+    pub fn is_complete(&self) -> bool {
+        self.missing() == 0
+    }
+
+    /// The part sent to a specific address of the target
+    pub fn get(&self, dst_addr: &str) -> Option<&R> {
+        self.parts
+            .iter()
+            .find(|part| part.dst_addr() == Some(dst_addr))
+    }
+
+    /// Iterate over the target addresses of the parts of this group
+    pub fn addresses(&self) -> impl Iterator<Item = &str> {
+        self.parts.iter().filter_map(SubResult::dst_addr)
+    }
+}
+
+/// Group the replies of http measurements by probe and bundle. Replies without a `subid` form a
+/// group of their own. Results without a bundle ID are assigned to runs using the `interval` of
+/// the measurement; see [`group_parts`] for details.
+pub fn group_http<'a: 'b, 'b, I>(
+    measurements: I,
+    interval: Duration,
+) -> Vec<SubResultGroup<&'b HttpReply<'a>>>
+where
+    I: IntoIterator<Item = &'b HttpMeasurement<'a>>,
+{
+    let parts = measurements.into_iter().flat_map(|measurement| {
+        measurement
+            .result
+            .iter()
+            .filter_map(move |response| match response {
+                Response::Reply(reply) => Some((
+                    GroupKey::from(measurement),
+                    unix_seconds(measurement.timestamp),
+                    reply,
+                )),
+                _ => None,
+            })
+    });
+    group_parts(parts, interval)
+}
+
+/// Group the results of dns measurements by probe and bundle. Results without a `subid` form a
+/// group of their own. Results without a bundle ID are assigned to runs using the `interval` of
+/// the measurement; see [`group_parts`] for details.
+pub fn group_dns<'a: 'b, 'b, I>(
+    measurements: I,
+    interval: Duration,
+) -> Vec<SubResultGroup<DNSResultRef<'b>>>
+where
+    I: IntoIterator<Item = &'b DnsMeasurement<'a>>,
+{
+    let parts = measurements.into_iter().flat_map(|measurement| {
+        let time = unix_seconds(measurement.timestamp);
+        measurement
+            .iter_results()
+            .map(move |result| (GroupKey::from(measurement), time, result))
+    });
+    group_parts(parts, interval)
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+struct GroupKey {
+    msm_id: i64,
+    prb_id: i64,
+    bundle: Option<i64>,
+}
+
+impl<'a, T> From<&Measurement<'a, T>> for GroupKey {
+    fn from(measurement: &Measurement<'a, T>) -> Self {
+        GroupKey {
+            msm_id: measurement.msm_id,
+            prb_id: measurement.prb_id,
+            bundle: measurement.bundle,
+        }
+    }
+}
+
+/// Parts are processed in order of their timestamp (in Unix seconds). Without a bundle ID a part
+/// starts a new group if its `subid` was already seen in the open group of the probe, or if it was
+/// sent at least one `interval` after the first part of the open group. The latter separates runs
+/// which lost their last parts from the next run. Intervals shorter than a second are treated as
+/// one second.
+fn group_parts<R, I>(parts: I, interval: Duration) -> Vec<SubResultGroup<R>>
+where
+    R: SubResult,
+    I: IntoIterator<Item = (GroupKey, i64, R)>,
+{
+    let interval = i64::try_from(interval.as_secs()).unwrap_or(i64::MAX).max(1);
+    let mut parts: Vec<_> = parts.into_iter().collect();
+    parts.sort_by_key(|(_, time, _)| *time);
+
+    let mut groups: Vec<SubResultGroup<R>> = Vec::new();
+    // Index of the open group of each key and the time of its first part
+    let mut open: HashMap<GroupKey, (usize, i64)> = HashMap::new();
+
+    for (key, time, part) in parts {
+        let subid = match part.subid() {
+            Some(subid) => subid,
+            None => {
+                groups.push(SubResultGroup::new(key, part));
+                continue;
+            }
+        };
+
+        match open.entry(key) {
+            Entry::Occupied(entry)
+                if (key.bundle.is_some() || time.saturating_sub(entry.get().1) < interval)
+                    && !groups[entry.get().0]
+                        .parts
+                        .iter()
+                        .any(|other| other.subid() == Some(subid)) =>
+            {
+                groups[entry.get().0].parts.push(part);
+            }
+            Entry::Occupied(mut entry) => {
+                entry.insert((groups.len(), time));
+                groups.push(SubResultGroup::new(key, part));
+            }
+            Entry::Vacant(entry) => {
+                entry.insert((groups.len(), time));
+                groups.push(SubResultGroup::new(key, part));
+            }
+        }
+    }
+
+    for group in &mut groups {
+        group.parts.sort_by_key(SubResult::subid);
+    }
+
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::general::from_unix_seconds;
+    use serde_json::{json, Value};
+
+    /// Response for www.ripe.net A
+    const ABUF: &str =
+        "vu+BgAABAAIAAAABA3d3dwRyaXBlA25ldAAAAQABwAwABQABAAABLAACwBDAEAABAAEAAAEsAATBAAsZAAApAgAAAIAAAAA=";
+
+    /// A result of an http measurement with the 'all' option, holding one part of a group
+    fn http(prb_id: i64, bundle: Option<i64>, result: Value) -> HttpMeasurement<'static> {
+        serde_json::from_value(json!({
+            "fw": 5080, "lts": 12, "msm_id": 50001, "msm_name": "HTTPGet", "prb_id": prb_id,
+            "bundle": bundle, "result": result, "timestamp": 1669852800, "type": "http",
+            "uri": "http://www.example.com/", "from": "203.0.113.5"
+        }))
+        .unwrap()
+    }
+
+    fn part(subid: Option<i64>, submax: Option<i64>, dst_addr: &str) -> Value {
+        json!({
+            "af": 4, "bsize": 1270, "dst_addr": dst_addr, "hsize": 420, "method": "GET",
+            "res": 200, "rt": 85.2, "src_addr": "192.168.1.10", "subid": subid,
+            "submax": submax, "ver": "1.1"
+        })
+    }
+
+    #[test]
+    fn groups_http_parts() {
+        let measurements = [
+            // The parts of the first run arrive out of order and the second run lost a part
+            http(1, None, json!([part(Some(2), Some(2), "2001:db8::80")])),
+            http(1, None, json!([part(Some(1), Some(2), "192.0.2.80")])),
+            http(1, None, json!([part(Some(1), Some(2), "192.0.2.80")])),
+            http(
+                2,
+                Some(7),
+                json!([{"x": "*"}, part(Some(1), Some(2), "192.0.2.80")]),
+            ),
+            http(2, Some(8), json!([part(Some(2), Some(2), "2001:db8::80")])),
+            http(3, None, json!([part(None, None, "192.0.2.80")])),
+        ];
+        let groups = group_http(&measurements, Duration::from_secs(900));
+
+        let summary: Vec<(i64, Option<i64>, usize, usize)> = groups
+            .iter()
+            .map(|group| {
+                (
+                    group.prb_id,
+                    group.bundle,
+                    group.parts.len(),
+                    group.missing(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, None, 2, 0),
+                (1, None, 1, 1),
+                (2, Some(7), 1, 1),
+                (2, Some(8), 1, 1),
+                (3, None, 1, 0),
+            ]
+        );
+
+        let complete = &groups[0];
+        assert_eq!(complete.msm_id, 50001);
+        assert!(complete.is_complete());
+        assert_eq!(complete.submax(), Some(2));
+        assert_eq!(
+            complete.addresses().collect::<Vec<_>>(),
+            vec!["192.0.2.80", "2001:db8::80"]
+        );
+        assert_eq!(
+            complete.get("2001:db8::80").and_then(|part| part.subid),
+            Some(2)
+        );
+        assert!(complete.get("198.51.100.80").is_none());
+
+        assert!(!groups[1].is_complete());
+        assert_eq!(groups[4].submax(), None);
+        assert!(groups[4].is_complete());
+
+        assert!(group_http(
+            &[http(1, None, json!([{"x": "*"}]))],
+            Duration::from_secs(900)
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn splits_runs_by_interval() {
+        let at = |time: i64, subid: i64| {
+            let mut measurement = http(1, None, json!([part(Some(subid), Some(3), "192.0.2.80")]));
+            measurement.timestamp = from_unix_seconds(time).unwrap();
+            measurement
+        };
+        // The first run lost its last part, the next run lost its first two parts. The input is
+        // not ordered by time.
+        let measurements = [at(1669853700, 3), at(1669852800, 1), at(1669852801, 2)];
+        let groups = group_http(&measurements, Duration::from_secs(900));
+
+        let subids: Vec<Vec<Option<i64>>> = groups
+            .iter()
+            .map(|group| group.parts.iter().map(|part| part.subid).collect())
+            .collect();
+        assert_eq!(subids, vec![vec![Some(1), Some(2)], vec![Some(3)]]);
+        assert_eq!(groups[0].missing(), 1);
+        assert_eq!(groups[1].missing(), 2);
+
+        // Parts of the same run are kept together regardless of the interval with a bundle ID
+        let mut bundled = measurements.clone();
+        for measurement in &mut bundled {
+            measurement.bundle = Some(7);
+        }
+        let groups = group_http(&bundled, Duration::from_secs(900));
+        assert_eq!(groups.len(), 1);
+        assert!(groups[0].is_complete());
+    }
+
+    #[test]
+    fn groups_dns_parts() {
+        // One answered part and one part which timed out
+        let resultset: DnsMeasurement = serde_json::from_value(json!({
+            "fw": 5080, "lts": 27, "msm_id": 30001, "msm_name": "Tdig", "prb_id": 6001,
+            "resultset": [
+                {"time": 1669852801, "lts": 28, "subid": 2, "submax": 2, "dst_addr": "fd00::1",
+                 "af": 6, "proto": "UDP", "error": {"timeout": 5000}},
+                {"time": 1669852800, "lts": 27, "subid": 1, "submax": 2,
+                 "dst_addr": "192.168.1.1", "af": 4, "src_addr": "192.168.1.10", "proto": "UDP",
+                 "result": {"abuf": ABUF, "rt": 12.345, "size": 74, "ID": 48879,
+                            "ANCOUNT": 2, "QDCOUNT": 1, "NSCOUNT": 0, "ARCOUNT": 1}}
+            ],
+            "timestamp": 1669852800, "from": "203.0.113.5", "type": "dns", "group_id": 30001
+        }))
+        .unwrap();
+        let single: DnsMeasurement = serde_json::from_value(json!({
+            "fw": 5080, "lts": 12, "af": 4, "dst_addr": "193.0.14.129", "dst_port": "53",
+            "from": "203.0.113.5", "msm_id": 30002, "msm_name": "Tdig", "prb_id": 6001,
+            "proto": "UDP", "result": {"abuf": ABUF, "ANCOUNT": 2, "ARCOUNT": 1, "ID": 48879,
+            "NSCOUNT": 0, "QDCOUNT": 1, "rt": 3.5, "size": 74}, "src_addr": "192.168.1.10",
+            "timestamp": 1669852900, "type": "dns", "group_id": 30002
+        }))
+        .unwrap();
+
+        let groups = group_dns([&resultset, &single], Duration::from_secs(900));
+        assert_eq!(groups.len(), 2);
+
+        let group = &groups[0];
+        assert_eq!((group.msm_id, group.prb_id), (30001, 6001));
+        assert!(group.is_complete());
+        assert_eq!(
+            group.addresses().collect::<Vec<_>>(),
+            vec!["192.168.1.1", "fd00::1"]
+        );
+        assert!(group.get("fd00::1").unwrap().error.is_some());
+
+        let group = &groups[1];
+        assert_eq!(group.msm_id, 30002);
+        assert_eq!((group.submax(), group.missing()), (None, 0));
+        assert_eq!(group.addresses().collect::<Vec<_>>(), vec!["193.0.14.129"]);
+    }
+}
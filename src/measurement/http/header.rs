@@ -0,0 +1,509 @@
+//! Parsing of the response headers included in http results.
+use crate::measurement::http::HttpReply;
+use std::borrow::Cow;
+
+impl<'a> HttpReply<'a> {
+    /// Parse the response headers. Returns `None` if the measurement did not include headers.
+    pub fn headers(&self) -> Option<Headers<'_>> {
+        self.header.as_deref().map(Headers::parse)
+    }
+}
+
+/// The response headers of a single reply. Header names are compared case-insensitively and
+/// headers which were split over multiple lines are joined back together.
+#[derive(Clone, Debug, Default)]
+pub struct Headers<'a> {
+    status_line: Option<StatusLine<'a>>,
+    fields: Vec<(&'a str, Cow<'a, str>)>,
+    truncated: bool,
+}
+
+impl<'a> Headers<'a> {
+    pub fn parse<S: AsRef<str>>(lines: &'a [S]) -> Self {
+        let mut headers = Headers::default();
+        let mut lines = lines.iter().map(AsRef::as_ref).peekable();
+
+        if let Some(status_line) = lines.peek().and_then(|line| StatusLine::parse(line)) {
+            headers.status_line = Some(status_line);
+            lines.next();
+        }
+
+        while let Some(line) = lines.next() {
+            // Measurements only keep the start of the headers. The cut off line is dropped since
+            // the value may be incomplete.
+            if line.ends_with("[...]") {
+                headers.truncated = true;
+                break;
+            }
+
+            let line = line.trim_end_matches(['\r', '\n']);
+            if line.is_empty() {
+                if lines.peek().is_none() {
+                    break;
+                }
+                continue;
+            }
+
+            // Obsolete line folding (RFC 7230 3.2.4) continues the previous value
+            if line.starts_with([' ', '\t']) {
+                if let Some((_, value)) = headers.fields.last_mut() {
+                    let value = value.to_mut();
+                    value.push(' ');
+                    value.push_str(line.trim());
+                }
+                continue;
+            }
+
+            if let Some((name, value)) = line.split_once(':') {
+                headers
+                    .fields
+                    .push((name.trim(), Cow::Borrowed(value.trim())));
+            }
+        }
+
+        headers
+    }
+
+    /// The first line of the response, if it was included
+    pub fn status_line(&self) -> Option<&StatusLine<'a>> {
+        self.status_line.as_ref()
+    }
+
+    /// The headers were cut short by the probe, so headers missing from this map may still have
+    /// been sent by the server.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// The value of the first header with this name
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.get_all(name).next()
+    }
+
+    /// The values of every header with this name in the order they were received
+    pub fn get_all<'b: 'n, 'n>(&'b self, name: &'n str) -> impl Iterator<Item = &'b str> + 'n {
+        self.fields
+            .iter()
+            .filter(move |(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_ref())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Iterate over each header name and value in the order they were received
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.fields
+            .iter()
+            .map(|(name, value)| (*name, value.as_ref()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn server(&self) -> Option<&str> {
+        self.get("Server")
+    }
+
+    /// Age of the cached response in seconds
+    pub fn age(&self) -> Option<u64> {
+        self.get("Age")?.parse().ok()
+    }
+
+    /// Proxies the response passed through, from the one closest to the server to the one closest
+    /// to the probe.
+    pub fn via(&self) -> Vec<Via<'_>> {
+        self.get_all("Via")
+            .flat_map(split_list)
+            .filter_map(Via::parse)
+            .collect()
+    }
+
+    /// All Cache-Control directives. Multiple Cache-Control headers are combined.
+    pub fn cache_control(&self) -> Option<CacheControl<'_>> {
+        let mut directives = self
+            .get_all("Cache-Control")
+            .flat_map(split_list)
+            .peekable();
+        directives.peek()?;
+
+        Some(CacheControl {
+            directives: directives
+                .map(|directive| match directive.split_once('=') {
+                    Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                    None => (directive, None),
+                })
+                .collect(),
+        })
+    }
+
+    /// The status of each cache layer listed in X-Cache. Some CDNs list one entry per layer
+    /// such as "HIT, MISS".
+    pub fn x_cache(&self) -> Vec<CacheStatus<'_>> {
+        self.get_all("X-Cache")
+            .flat_map(split_list)
+            .map(CacheStatus::from)
+            .collect()
+    }
+
+    /// The ray ID added by Cloudflare. The suffix of the ID names the data center which handled
+    /// the request.
+    pub fn cf_ray(&self) -> Option<CfRay<'_>> {
+        let ray = self.get("CF-RAY")?;
+
+        Some(match ray.rsplit_once('-') {
+            Some((id, colo)) => CfRay {
+                id,
+                colo: Some(colo),
+            },
+            None => CfRay {
+                id: ray,
+                colo: None,
+            },
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct StatusLine<'a> {
+    /// such as "HTTP/1.1"
+    pub version: &'a str,
+    pub status: u16,
+    pub reason: &'a str,
+}
+
+impl<'a> StatusLine<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        if !line.starts_with("HTTP/") {
+            return None;
+        }
+
+        let mut parts = line.splitn(3, ' ');
+        Some(StatusLine {
+            version: parts.next()?,
+            status: parts.next()?.parse().ok()?,
+            reason: parts.next().unwrap_or("").trim(),
+        })
+    }
+}
+
+/// A single entry of a Via header (RFC 7230 5.7.1)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Via<'a> {
+    /// protocol version received by the proxy, such as "1.1" or "HTTP/2"
+    pub protocol: &'a str,
+    /// host name or pseudonym of the proxy
+    pub received_by: &'a str,
+    /// comment such as the proxy software, without the surrounding parentheses
+    pub comment: Option<&'a str>,
+}
+
+impl<'a> Via<'a> {
+    fn parse(entry: &'a str) -> Option<Self> {
+        let (protocol, rest) = entry.split_once(char::is_whitespace)?;
+        let rest = rest.trim();
+
+        let (received_by, comment) = match rest.split_once(char::is_whitespace) {
+            Some((received_by, comment)) => (
+                received_by,
+                Some(comment.trim().trim_start_matches('(').trim_end_matches(')')),
+            ),
+            None => (rest, None),
+        };
+
+        Some(Via {
+            protocol,
+            received_by,
+            comment,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CacheControl<'a> {
+    /// directive names with their values, if any
+    pub directives: Vec<(&'a str, Option<&'a str>)>,
+}
+
+impl<'a> CacheControl<'a> {
+    /// Check if a directive is present
+    pub fn has(&self, name: &str) -> bool {
+        self.directives
+            .iter()
+            .any(|(directive, _)| directive.eq_ignore_ascii_case(name))
+    }
+
+    /// The value of a directive. Returns `None` if the directive is missing or has no value.
+    pub fn get(&self, name: &str) -> Option<&'a str> {
+        self.directives
+            .iter()
+            .find(|(directive, _)| directive.eq_ignore_ascii_case(name))
+            .and_then(|(_, value)| *value)
+    }
+
+    pub fn max_age(&self) -> Option<u64> {
+        self.get("max-age")?.parse().ok()
+    }
+
+    pub fn s_maxage(&self) -> Option<u64> {
+        self.get("s-maxage")?.parse().ok()
+    }
+
+    pub fn is_public(&self) -> bool {
+        self.has("public")
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.has("private")
+    }
+
+    pub fn is_no_cache(&self) -> bool {
+        self.has("no-cache")
+    }
+
+    pub fn is_no_store(&self) -> bool {
+        self.has("no-store")
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CacheStatus<'a> {
+    /// The response was served from the cache, such as "HIT" or "Hit from cloudfront"
+    Hit(&'a str),
+    /// The response was fetched from the origin, such as "MISS" or "TCP_MISS"
+    Miss(&'a str),
+    /// Any other status such as "Error from cloudfront"
+    Other(&'a str),
+}
+
+impl<'a> From<&'a str> for CacheStatus<'a> {
+    fn from(status: &'a str) -> Self {
+        let upper = status.to_ascii_uppercase();
+
+        if upper.contains("HIT") {
+            CacheStatus::Hit(status)
+        } else if upper.contains("MISS") {
+            CacheStatus::Miss(status)
+        } else {
+            CacheStatus::Other(status)
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CfRay<'a> {
+    pub id: &'a str,
+    /// IATA airport code of the Cloudflare data center, such as "AMS"
+    pub colo: Option<&'a str>,
+}
+
+/// Split a comma separated header value while ignoring commas within parentheses or quotes
+fn split_list(value: &str) -> impl Iterator<Item = &str> {
+    let mut depth = 0usize;
+    let mut quoted = false;
+    let mut start = 0;
+    let mut items = Vec::new();
+
+    for (index, c) in value.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth = depth.saturating_sub(1),
+            ',' if !quoted && depth == 0 => {
+                items.push(&value[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&value[start..]);
+
+    items
+        .into_iter()
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::measurement::{HttpMeasurement, Response};
+
+    /// Headers of a response served through a CDN, as reported by a probe
+    const HEADERS: &[&str] = &[
+        "HTTP/1.1 200 OK\r\n",
+        "Server: cloudflare\r\n",
+        "Content-Type: text/html;\r\n",
+        "\tcharset=utf-8\r\n",
+        "Cache-Control: public, max-age=3600\r\n",
+        "cache-control: s-maxage=\"600\", no-cache=\"Set-Cookie, Vary\"\r\n",
+        "Age: 120\r\n",
+        "Via: 1.1 varnish (Varnish/6.0), HTTP/2 edge.example.net\r\n",
+        "via: 1.0 fred (squid/3.5, cached)\r\n",
+        "X-Cache: HIT, MISS, Error from cloudfront\r\n",
+        "CF-RAY: 7b1c2d3e4f5a6b7c-AMS\r\n",
+        "\r\n",
+    ];
+
+    fn measurement(header: Option<&[&str]>) -> HttpMeasurement<'static> {
+        serde_json::from_value(serde_json::json!({
+            "fw": 5080, "lts": 12, "msm_id": 50001, "msm_name": "HTTPGet", "prb_id": 6001,
+            "result": [{
+                "af": 4, "bsize": 1270, "dst_addr": "192.0.2.80", "header": header, "hsize": 420,
+                "method": "GET", "res": 200, "rt": 85.2, "src_addr": "192.168.1.10",
+                "ver": "1.1"
+            }],
+            "timestamp": 1669852800, "type": "http", "uri": "http://www.example.com/",
+            "from": "203.0.113.5", "group_id": 50001
+        }))
+        .unwrap()
+    }
+
+    fn reply<'m>(measurement: &'m HttpMeasurement<'static>) -> &'m HttpReply<'static> {
+        match &measurement.result[0] {
+            Response::Reply(reply) => reply,
+            other => panic!("unexpected response {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_reported_headers() {
+        let measurement = measurement(Some(HEADERS));
+        let headers = reply(&measurement).headers().unwrap();
+
+        assert_eq!(
+            headers.status_line(),
+            Some(&StatusLine {
+                version: "HTTP/1.1",
+                status: 200,
+                reason: "OK",
+            })
+        );
+        assert!(!headers.is_truncated());
+        assert_eq!(headers.len(), 9);
+        assert_eq!(headers.iter().next(), Some(("Server", "cloudflare")));
+        assert_eq!(headers.server(), Some("cloudflare"));
+        assert_eq!(
+            headers.get("content-type"),
+            Some("text/html; charset=utf-8")
+        );
+        assert_eq!(headers.get_all("CACHE-CONTROL").count(), 2);
+        assert!(headers.contains("cf-ray"));
+        assert!(!headers.contains("Set-Cookie"));
+        assert_eq!(headers.age(), Some(120));
+    }
+
+    #[test]
+    fn parses_cache_and_cdn_headers() {
+        let headers = Headers::parse(HEADERS);
+
+        assert_eq!(
+            headers.via(),
+            vec![
+                Via {
+                    protocol: "1.1",
+                    received_by: "varnish",
+                    comment: Some("Varnish/6.0"),
+                },
+                Via {
+                    protocol: "HTTP/2",
+                    received_by: "edge.example.net",
+                    comment: None,
+                },
+                Via {
+                    protocol: "1.0",
+                    received_by: "fred",
+                    comment: Some("squid/3.5, cached"),
+                },
+            ]
+        );
+
+        let cache_control = headers.cache_control().unwrap();
+        assert!(cache_control.is_public() && cache_control.is_no_cache());
+        assert!(!cache_control.is_private() && !cache_control.is_no_store());
+        assert_eq!(cache_control.max_age(), Some(3600));
+        assert_eq!(cache_control.s_maxage(), Some(600));
+        assert_eq!(cache_control.get("No-Cache"), Some("Set-Cookie, Vary"));
+        assert_eq!(cache_control.get("public"), None);
+
+        assert_eq!(
+            headers.x_cache(),
+            vec![
+                CacheStatus::Hit("HIT"),
+                CacheStatus::Miss("MISS"),
+                CacheStatus::Other("Error from cloudfront"),
+            ]
+        );
+        assert_eq!(
+            headers.cf_ray(),
+            Some(CfRay {
+                id: "7b1c2d3e4f5a6b7c",
+                colo: Some("AMS"),
+            })
+        );
+    }
+
+    #[test]
+    fn stops_at_truncated_line() {
+        let headers = Headers::parse(&[
+            "HTTP/1.1 301 Moved Permanently",
+            "Location: https://www.example.com/",
+            "Set-Cookie: session=0123456789abcdef; Path=/; Secu[...]",
+        ]);
+
+        assert!(headers.is_truncated());
+        assert_eq!(headers.status_line().map(|line| line.status), Some(301));
+        assert_eq!(headers.len(), 1);
+        assert!(!headers.contains("Set-Cookie"));
+    }
+
+    #[test]
+    fn handles_missing_and_malformed_headers() {
+        let measurement = measurement(None);
+        assert!(reply(&measurement).headers().is_none());
+
+        let headers = Headers::parse::<&str>(&[]);
+        assert!(headers.is_empty() && headers.status_line().is_none());
+        assert!(headers.cache_control().is_none());
+        assert!(headers.via().is_empty() && headers.x_cache().is_empty());
+
+        // A status line without a reason phrase, a status which is not a number, a line without
+        // a colon and an empty line within the headers
+        let headers = Headers::parse(&[
+            "HTTP/1.0 204",
+            "HTTP/1.1 abc",
+            "garbage",
+            "",
+            "Age: soon",
+            "CF-RAY: 7b1c2d3e4f5a6b7c",
+        ]);
+        assert_eq!(
+            headers.status_line(),
+            Some(&StatusLine {
+                version: "HTTP/1.0",
+                status: 204,
+                reason: "",
+            })
+        );
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers.age(), None);
+        assert_eq!(
+            headers.cf_ray(),
+            Some(CfRay {
+                id: "7b1c2d3e4f5a6b7c",
+                colo: None,
+            })
+        );
+
+        let headers = Headers::parse(&["Server: nginx"]);
+        assert!(headers.status_line().is_none());
+        assert_eq!(headers.server(), Some("nginx"));
+    }
+}
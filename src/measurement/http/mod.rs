@@ -3,6 +3,9 @@ use crate::measurement::Response;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 
+pub mod header;
+pub mod timing;
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 #[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
 pub struct Http<'a> {
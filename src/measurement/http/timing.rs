@@ -0,0 +1,397 @@
+//! Breakdown of the time spent on each phase of an HTTP request and the throughput achieved while
+//! reading the reply.
+use crate::measurement::http::{Http, HttpReply};
+use crate::measurement::stats::percentile;
+use crate::measurement::{HttpMeasurement, Measurement, Response};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Time spent in each phase of a request in milliseconds. Phases are `None` when the fields they
+/// are derived from were not included in the result.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Timing {
+    /// time to resolve the target name
+    pub dns: Option<f64>,
+    /// time to establish the TCP connection (and TLS session for https)
+    pub connect: Option<f64>,
+    /// time between connecting and receiving the first byte of the reply
+    pub wait: Option<f64>,
+    /// time between the first byte and the end of the reply
+    pub transfer: Option<f64>,
+    /// time for the complete request including DNS resolution
+    pub total: Option<f64>,
+}
+
+impl<'a> HttpReply<'a> {
+    /// Split the reply time into phases. `ttfb` is measured from the start of connecting and `rt`
+    /// excludes DNS, so the phases do not overlap.
+    pub fn timing(&self) -> Timing {
+        let wait = match (self.ttfb, self.ttc) {
+            (Some(ttfb), Some(ttc)) => Some((ttfb - ttc).max(0.0)),
+            _ => None,
+        };
+
+        let transfer = match (self.rt, self.ttfb) {
+            (Some(rt), Some(ttfb)) => Some((rt - ttfb).max(0.0)),
+            _ => None,
+        };
+
+        Timing {
+            dns: self.ttr,
+            connect: self.ttc,
+            wait,
+            transfer,
+            total: self.rt.map(|rt| rt + self.ttr.unwrap_or(0.0)),
+        }
+    }
+
+    /// Cumulative bytes read over time, built from `readtiming`. Each point gives the stream
+    /// offset reached and the rate since the previous point.
+    pub fn throughput(&self) -> Vec<ThroughputSample> {
+        let mut samples = Vec::new();
+        let mut previous: Option<(f64, u64)> = None;
+
+        for timing in self.readtiming.iter().flatten() {
+            let rate = match previous {
+                Some((time, offset)) if timing.t > time => {
+                    timing.o.saturating_sub(offset) as f64 / (timing.t - time) * 1000.0
+                }
+                _ => 0.0,
+            };
+
+            samples.push(ThroughputSample {
+                time: timing.t,
+                offset: timing.o,
+                rate,
+            });
+            previous = Some((timing.t, timing.o));
+        }
+
+        samples
+    }
+
+    /// Average rate in bytes per second from the first to the last read. Returns `None` if fewer
+    /// than two reads were recorded.
+    pub fn average_throughput(&self) -> Option<f64> {
+        let readtiming = self.readtiming.as_ref()?;
+        let (first, last) = (readtiming.first()?, readtiming.last()?);
+
+        if last.t <= first.t {
+            return None;
+        }
+
+        Some(last.o.saturating_sub(first.o) as f64 / (last.t - first.t) * 1000.0)
+    }
+}
+
+impl<'a> Measurement<'a, Http<'a>> {
+    /// Timing of each reply. The DNS phase falls back to the `ttr` of the measurement when the
+    /// reply does not have its own.
+    pub fn timings(&self) -> impl Iterator<Item = Timing> + '_ {
+        let ttr = self.inner.ttr.or(self.ttr);
+
+        self.result
+            .iter()
+            .filter_map(move |response| match response {
+                Response::Reply(reply) => {
+                    let mut timing = reply.timing();
+                    if let (None, Some(ttr)) = (reply.ttr, ttr) {
+                        timing.dns = Some(ttr);
+                        timing.total = timing.total.map(|total| total + ttr);
+                    }
+                    Some(timing)
+                }
+                _ => None,
+            })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ThroughputSample {
+    /// milli seconds since starting to connect
+    pub time: f64,
+    /// offset in the reply stream
+    pub offset: u64,
+    /// bytes per second since the previous sample, or 0 for the first sample
+    pub rate: f64,
+}
+
+/// Distribution of a single value across many replies
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Summary {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+    /// 95th percentile
+    pub p95: f64,
+}
+
+impl Summary {
+    /// Summarize a set of values. NaN values are ignored and `None` is returned if no values
+    /// remain.
+    pub fn from_values<I: IntoIterator<Item = f64>>(values: I) -> Option<Self> {
+        let mut values: Vec<f64> = values.into_iter().filter(|x| !x.is_nan()).collect();
+        if values.is_empty() {
+            return None;
+        }
+
+        values.sort_by(f64::total_cmp);
+        let count = values.len();
+
+        Some(Summary {
+            count,
+            min: values[0],
+            max: values[count - 1],
+            mean: values.iter().sum::<f64>() / count as f64,
+            median: percentile(&values, 0.5),
+            p95: percentile(&values, 0.95),
+        })
+    }
+}
+
+/// Timing statistics for a group of replies
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TimingStats {
+    /// number of replies in the group
+    pub replies: usize,
+    pub dns: Option<Summary>,
+    pub connect: Option<Summary>,
+    pub wait: Option<Summary>,
+    pub transfer: Option<Summary>,
+    pub total: Option<Summary>,
+    /// average throughput of each reply in bytes per second
+    pub throughput: Option<Summary>,
+}
+
+#[derive(Default)]
+struct TimingValues {
+    replies: usize,
+    dns: Vec<f64>,
+    connect: Vec<f64>,
+    wait: Vec<f64>,
+    transfer: Vec<f64>,
+    total: Vec<f64>,
+    throughput: Vec<f64>,
+}
+
+impl TimingValues {
+    fn push(&mut self, timing: Timing, throughput: Option<f64>) {
+        self.replies += 1;
+        self.dns.extend(timing.dns);
+        self.connect.extend(timing.connect);
+        self.wait.extend(timing.wait);
+        self.transfer.extend(timing.transfer);
+        self.total.extend(timing.total);
+        self.throughput.extend(throughput);
+    }
+
+    fn summarize(self) -> TimingStats {
+        TimingStats {
+            replies: self.replies,
+            dns: Summary::from_values(self.dns),
+            connect: Summary::from_values(self.connect),
+            wait: Summary::from_values(self.wait),
+            transfer: Summary::from_values(self.transfer),
+            total: Summary::from_values(self.total),
+            throughput: Summary::from_values(self.throughput),
+        }
+    }
+}
+
+/// Aggregate reply timings across measurements. `group` picks the group of each measurement, such
+/// as the country or ASN of the probe found by looking up `prb_id` in probe metadata. Measurements
+/// for which it returns `None` are skipped.
+pub fn group_timings<'a: 'b, 'b, I, K, F>(measurements: I, mut group: F) -> HashMap<K, TimingStats>
+where
+    I: IntoIterator<Item = &'b HttpMeasurement<'a>>,
+    K: Eq + Hash,
+    F: FnMut(&HttpMeasurement<'a>) -> Option<K>,
+{
+    let mut groups: HashMap<K, TimingValues> = HashMap::new();
+
+    for measurement in measurements {
+        let key = match group(measurement) {
+            Some(key) => key,
+            None => continue,
+        };
+
+        let values = groups.entry(key).or_default();
+        let replies = measurement
+            .result
+            .iter()
+            .filter_map(|response| match response {
+                Response::Reply(reply) => Some(reply),
+                _ => None,
+            });
+
+        for (reply, timing) in replies.zip(measurement.timings()) {
+            values.push(timing, reply.average_throughput());
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(key, values)| (key, values.summarize()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn measurement(prb_id: i64, ttr: Option<f64>, result: Value) -> HttpMeasurement<'static> {
+        serde_json::from_value(json!({
+            "fw": 5080, "lts": 12, "msm_id": 50001, "msm_name": "HTTPGet", "prb_id": prb_id,
+            "result": result, "timestamp": 1669852800, "type": "http",
+            "uri": "http://www.example.com/", "from": "203.0.113.5", "ttr": ttr
+        }))
+        .unwrap()
+    }
+
+    /// A reply which took 12 ms to resolve, 20 ms to connect, 30 ms until the first byte and
+    /// 30 ms to read 3000 bytes
+    fn reply() -> Value {
+        json!({
+            "af": 4, "bsize": 3000, "dst_addr": "192.0.2.80", "hsize": 420, "method": "GET",
+            "res": 200, "rt": 80.0, "src_addr": "192.168.1.10", "ttc": 20.0, "ttfb": 50.0,
+            "ttr": 12.0, "ver": "1.1",
+            "readtiming": [
+                {"o": 0, "t": 50.0}, {"o": 1000, "t": 60.0}, {"o": 3000, "t": 80.0},
+                {"o": 3000, "t": 80.0}
+            ]
+        })
+    }
+
+    fn first_reply<'m>(measurement: &'m HttpMeasurement<'static>) -> &'m HttpReply<'static> {
+        measurement
+            .result
+            .iter()
+            .find_map(|response| match response {
+                Response::Reply(reply) => Some(reply),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn splits_reply_time_into_phases() {
+        let complete = measurement(1, None, json!([reply()]));
+        assert_eq!(
+            first_reply(&complete).timing(),
+            Timing {
+                dns: Some(12.0),
+                connect: Some(20.0),
+                wait: Some(30.0),
+                transfer: Some(30.0),
+                total: Some(92.0),
+            }
+        );
+
+        // Rounding in the reported times can make a phase slightly negative
+        let mut early = reply();
+        early["ttfb"] = json!(19.5);
+        early["rt"] = json!(19.0);
+        let late = measurement(1, None, json!([early]));
+        let timing = first_reply(&late).timing();
+        assert_eq!((timing.wait, timing.transfer), (Some(0.0), Some(0.0)));
+
+        let minimal = measurement(
+            1,
+            None,
+            json!([{"af": 4, "dst_addr": "192.0.2.80", "method": "GET", "rt": 80.0}]),
+        );
+        assert_eq!(
+            first_reply(&minimal).timing(),
+            Timing {
+                total: Some(80.0),
+                ..Timing::default()
+            }
+        );
+    }
+
+    #[test]
+    fn falls_back_to_measurement_dns_time() {
+        let mut without_ttr = reply();
+        without_ttr.as_object_mut().unwrap().remove("ttr");
+        let measurement = measurement(1, Some(5.0), json!([{"x": "*"}, without_ttr, reply()]));
+
+        let timings: Vec<Timing> = measurement.timings().collect();
+        assert_eq!(timings.len(), 2);
+        assert_eq!((timings[0].dns, timings[0].total), (Some(5.0), Some(85.0)));
+        assert_eq!((timings[1].dns, timings[1].total), (Some(12.0), Some(92.0)));
+    }
+
+    #[test]
+    fn computes_throughput() {
+        let complete = measurement(1, None, json!([reply()]));
+        let reply = first_reply(&complete);
+
+        let rates: Vec<(u64, f64)> = reply
+            .throughput()
+            .iter()
+            .map(|sample| (sample.offset, sample.rate))
+            .collect();
+        assert_eq!(
+            rates,
+            vec![(0, 0.0), (1000, 100000.0), (3000, 100000.0), (3000, 0.0)]
+        );
+        assert_eq!(reply.average_throughput(), Some(100000.0));
+
+        let single = measurement(
+            1,
+            None,
+            json!([{"af": 4, "dst_addr": "192.0.2.80", "method": "GET",
+                    "readtiming": [{"o": 0, "t": 50.0}]}]),
+        );
+        assert_eq!(first_reply(&single).average_throughput(), None);
+        assert_eq!(first_reply(&single).throughput().len(), 1);
+    }
+
+    #[test]
+    fn summarizes_values() {
+        let summary = Summary::from_values([3.0, f64::NAN, 1.0, 4.0, 2.0]).unwrap();
+        assert_eq!(
+            (summary.count, summary.min, summary.max, summary.mean),
+            (4, 1.0, 4.0, 2.5)
+        );
+        assert_eq!(summary.median, 2.5);
+        assert!((summary.p95 - 3.85).abs() < 1e-9);
+
+        assert_eq!(Summary::from_values([f64::NAN]), None);
+        assert_eq!(Summary::from_values([]), None);
+    }
+
+    #[test]
+    fn groups_timings() {
+        let measurements = [
+            measurement(1, None, json!([reply(), {"x": "*"}])),
+            measurement(2, None, json!([reply()])),
+            measurement(
+                3,
+                None,
+                json!([{"error": "connect: Network is unreachable"}]),
+            ),
+            measurement(4, None, json!([reply()])),
+        ];
+
+        let groups = group_timings(&measurements, |measurement| match measurement.prb_id {
+            4 => None,
+            prb_id => Some(prb_id % 2),
+        });
+
+        let odd = groups[&1];
+        assert_eq!(odd.replies, 1);
+        assert_eq!(odd.total.map(|summary| summary.count), Some(1));
+        assert_eq!(odd.throughput.map(|summary| summary.mean), Some(100000.0));
+
+        let even = groups[&0];
+        assert_eq!(even.replies, 1);
+        assert_eq!(even.wait.map(|summary| summary.median), Some(30.0));
+        assert_eq!(groups.len(), 2);
+
+        assert!(group_timings(&[], |_| Some(())).is_empty());
+    }
+}
@@ -4,9 +4,11 @@ use std::borrow::Cow;
 use std::ops::Deref;
 
 pub mod dns;
+pub mod group;
 pub mod http;
 pub mod ntp;
 pub mod ping;
+mod stats;
 pub mod tls;
 pub mod traceroute;
 
@@ -42,7 +44,7 @@ pub struct Measurement<'a, T> {
     pub msm_name: Cow<'a, str>,
     /// source probe ID (int)
     pub prb_id: i64,
-    ///
+    /// source address used by the probe (string)
     pub src_addr: Option<Cow<'a, str>>,
     #[cfg_attr(feature = "chrono", serde(with = "chrono::serde::ts_seconds"))]
     pub timestamp: UnixTimestamp,
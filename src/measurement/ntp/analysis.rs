@@ -0,0 +1,252 @@
+//! Clock offset and delay analysis of ntp results, both for a single measurement and compared
+//! across the servers measured by many probes.
+use crate::measurement::ntp::Ntp;
+use crate::measurement::stats::median;
+use crate::measurement::NtpMeasurement;
+use std::collections::HashMap;
+
+/// Summary of the replies of a single measurement using the approach of the NTP clock filter
+/// (RFC 5905 10). All values are in seconds.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct NtpSummary {
+    /// number of replies received
+    pub replies: usize,
+    /// offset of the reply with the lowest round trip time. This reply is the least affected by
+    /// asymmetric queuing delays.
+    pub offset: f64,
+    /// lowest round trip time
+    pub delay: f64,
+    /// root mean square difference between the offset of each reply and `offset`. This is 0 if
+    /// there was only one reply.
+    pub jitter: f64,
+    /// estimate of the maximum error of the server's clock as seen by the probe. This is the root
+    /// distance from RFC 5905 11.2 using the server's precision in place of the dispersion that
+    /// would have accumulated since the reply.
+    pub sync_distance: Option<f64>,
+}
+
+impl<'a> Ntp<'a> {
+    /// Summarize the replies received. Returns `None` if no replies were received.
+    pub fn summary(&self) -> Option<NtpSummary> {
+        let best = self.replies().min_by(|a, b| a.rtt.total_cmp(&b.rtt))?;
+        let replies = self.replies().count();
+
+        let jitter = if replies > 1 {
+            let squares: f64 = self
+                .replies()
+                .map(|reply| (reply.offset - best.offset).powi(2))
+                .sum();
+            (squares / (replies - 1) as f64).sqrt()
+        } else {
+            0.0
+        };
+
+        let root_delay = best.root_delay.or(self.root_delay);
+        let root_dispersion = best.root_dispersion.or(self.root_dispersion);
+        let precision = best.precision.or(self.precision).unwrap_or(0.0);
+
+        let sync_distance = match (root_delay, root_dispersion) {
+            (Some(root_delay), Some(root_dispersion)) => Some(
+                (root_delay + best.rtt.max(0.0)) / 2.0 + root_dispersion + precision.abs() + jitter,
+            ),
+            _ => None,
+        };
+
+        Some(NtpSummary {
+            replies,
+            offset: best.offset,
+            delay: best.rtt,
+            jitter,
+            sync_distance,
+        })
+    }
+}
+
+/// The offset of a server compared to the other servers in a set of measurements
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServerOffset {
+    /// address of the server, or its name if the address was not reported
+    pub server: String,
+    /// number of probes which received a reply from this server
+    pub probes: usize,
+    /// median of the filtered offset reported by each probe
+    pub offset: f64,
+    /// difference between `offset` and the consensus of all servers
+    pub deviation: f64,
+    /// the deviation is large enough to suggest the server disagrees with the consensus
+    pub is_outlier: bool,
+}
+
+/// Compare the offsets of each server measured across many probes. The offset of each server is
+/// the median of the filtered offsets seen by the probes, and the consensus is the median of the
+/// offsets of all servers.
+///
+/// A server is flagged as an outlier when its deviation from the consensus exceeds both
+/// `threshold` seconds and 3 times the median absolute deviation of all servers. The results are
+/// sorted from the largest to the smallest absolute deviation.
+pub fn compare_servers<'a: 'b, 'b, I>(measurements: I, threshold: f64) -> Vec<ServerOffset>
+where
+    I: IntoIterator<Item = &'b NtpMeasurement<'a>>,
+{
+    let mut offsets: HashMap<String, Vec<f64>> = HashMap::new();
+    for measurement in measurements {
+        if let Some(summary) = measurement.summary() {
+            let server = measurement
+                .dst_addr
+                .as_deref()
+                .unwrap_or(&measurement.dst_name);
+            offsets
+                .entry(server.to_string())
+                .or_default()
+                .push(summary.offset);
+        }
+    }
+
+    let mut servers: Vec<ServerOffset> = offsets
+        .into_iter()
+        .map(|(server, mut offsets)| ServerOffset {
+            server,
+            probes: offsets.len(),
+            offset: median(&mut offsets),
+            deviation: 0.0,
+            is_outlier: false,
+        })
+        .collect();
+
+    if servers.is_empty() {
+        return servers;
+    }
+
+    let consensus = median(
+        &mut servers
+            .iter()
+            .map(|server| server.offset)
+            .collect::<Vec<_>>(),
+    );
+    for server in &mut servers {
+        server.deviation = server.offset - consensus;
+    }
+
+    let mad = median(
+        &mut servers
+            .iter()
+            .map(|server| server.deviation.abs())
+            .collect::<Vec<_>>(),
+    );
+    for server in &mut servers {
+        server.is_outlier =
+            server.deviation.abs() > threshold && server.deviation.abs() > 3.0 * mad;
+    }
+
+    servers.sort_by(|a, b| b.deviation.abs().total_cmp(&a.deviation.abs()));
+    servers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    /// 2022-12-01 00:00:00 in NTP seconds
+    const TIME: f64 = 3878841600.0;
+
+    /// A reply with the given offset and round trip time, spending 0.1 ms in the server
+    fn reply(offset: f64, rtt: f64) -> Value {
+        let receive = TIME + rtt / 2.0 + offset;
+        json!({
+            "final-ts": TIME + rtt + 0.0001, "offset": offset, "origin-ts": TIME,
+            "receive-ts": receive, "rtt": rtt, "transmit-ts": receive + 0.0001
+        })
+    }
+
+    fn measurement(prb_id: i64, dst_addr: Option<&str>, result: Value) -> NtpMeasurement<'static> {
+        serde_json::from_value(value(prb_id, dst_addr, result)).unwrap()
+    }
+
+    fn value(prb_id: i64, dst_addr: Option<&str>, result: Value) -> Value {
+        json!({
+            "af": 4, "dst_addr": dst_addr, "dst_name": "ntp.example.net", "from": "203.0.113.5",
+            "fw": 5080, "li": "no", "lts": 20, "mode": "server", "msm_id": 60001,
+            "msm_name": "Ntp", "poll": 8.0, "precision": 9.53674e-07, "prb_id": prb_id,
+            "proto": "UDP", "ref-id": "GPS", "ref-ts": TIME - 12.5, "result": result,
+            "root-delay": 0.002, "root-dispersion": 0.0005, "src_addr": "192.168.1.10",
+            "stratum": 1, "timestamp": 1669852800, "type": "ntp", "version": 4
+        })
+    }
+
+    #[test]
+    fn summarizes_replies() {
+        let measurement = measurement(
+            1,
+            Some("192.0.2.123"),
+            json!([reply(0.002, 0.030), {"x": "*"}, reply(0.001, 0.020), reply(0.004, 0.025)]),
+        );
+        let summary = measurement.summary().unwrap();
+
+        assert_eq!(summary.replies, 3);
+        assert_eq!((summary.offset, summary.delay), (0.001, 0.020));
+        let jitter = (0.00001f64 / 2.0).sqrt();
+        assert!((summary.jitter - jitter).abs() < 1e-12);
+
+        let distance = (0.002 + 0.020) / 2.0 + 0.0005 + 9.53674e-07 + jitter;
+        assert!((summary.sync_distance.unwrap() - distance).abs() < 1e-12);
+    }
+
+    #[test]
+    fn summarizes_single_and_missing_replies() {
+        let mut single = reply(-0.003, 0.040);
+        single["root-delay"] = json!(0.01);
+        single["root-dispersion"] = json!(0.001);
+        single["precision"] = json!(-0.0001);
+        let summary = measurement(1, None, json!([single])).summary().unwrap();
+        assert_eq!((summary.replies, summary.jitter), (1, 0.0));
+        assert!((summary.sync_distance.unwrap() - (0.025 + 0.001 + 0.0001)).abs() < 1e-12);
+
+        let timeouts = measurement(1, None, json!([{"x": "*"}, {"x": "*"}, {"x": "*"}]));
+        assert_eq!(timeouts.summary(), None);
+
+        // Without the root delay the distance to the reference clock is unknown
+        let mut value = value(1, None, json!([reply(0.0, 0.01)]));
+        value.as_object_mut().unwrap().remove("root-delay");
+        let without_root: NtpMeasurement = serde_json::from_value(value).unwrap();
+        assert_eq!(without_root.summary().unwrap().sync_distance, None);
+    }
+
+    #[test]
+    fn flags_servers_disagreeing_with_consensus() {
+        let measurements = [
+            measurement(1, Some("192.0.2.1"), json!([reply(0.001, 0.02)])),
+            measurement(2, Some("192.0.2.1"), json!([reply(0.002, 0.02)])),
+            measurement(3, Some("192.0.2.1"), json!([reply(0.003, 0.02)])),
+            measurement(1, Some("192.0.2.2"), json!([reply(0.0015, 0.02)])),
+            measurement(1, None, json!([reply(0.0025, 0.02)])),
+            measurement(1, Some("192.0.2.4"), json!([reply(0.5, 0.02)])),
+            measurement(2, Some("192.0.2.5"), json!([{"x": "*"}])),
+        ];
+        let servers = compare_servers(&measurements, 0.1);
+
+        let names: Vec<&str> = servers
+            .iter()
+            .map(|server| server.server.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["192.0.2.4", "192.0.2.2", "192.0.2.1", "ntp.example.net"]
+        );
+
+        let outlier = &servers[0];
+        assert!(outlier.is_outlier);
+        assert_eq!(outlier.probes, 1);
+        assert!((outlier.deviation - (0.5 - 0.00225)).abs() < 1e-12);
+
+        let consistent = &servers[2];
+        assert_eq!((consistent.probes, consistent.offset), (3, 0.002));
+        assert!(servers[1..].iter().all(|server| !server.is_outlier));
+
+        // A deviation beyond the spread of the servers but below the threshold is not flagged
+        assert!(compare_servers(&measurements, 1.0)
+            .iter()
+            .all(|server| !server.is_outlier));
+        assert!(compare_servers(&[], 0.1).is_empty());
+    }
+}
@@ -0,0 +1,285 @@
+use crate::general::{from_unix_seconds, unix_seconds, AddressFamily, Protocol, UnixTimestamp};
+use crate::measurement::Response;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::ops::Sub;
+
+pub mod analysis;
+pub mod refid;
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
+pub struct Ntp<'a> {
+    /// address family, 4 or 6 (integer)
+    pub af: AddressFamily,
+    /// name of the destination (string)
+    pub dst_name: Cow<'a, str>,
+    /// port name (string)
+    pub dst_port: Option<Cow<'a, str>>,
+    /// leap indicator, values "no", "61", "59", or "unknown" (string)
+    pub li: Option<LeapIndicator>,
+    /// "server" (string)
+    pub mode: Option<Cow<'a, str>>,
+    /// poll interval in seconds (float)
+    pub poll: Option<f64>,
+    /// precision of the server's clock in seconds (float)
+    pub precision: Option<f64>,
+    /// "UDP" (string)
+    pub proto: Protocol,
+    /// server's reference clock (string)
+    #[serde(rename = "ref-id")]
+    pub ref_id: Option<Cow<'a, str>>,
+    /// server's reference timestamp in NTP seconds (float)
+    #[serde(rename = "ref-ts")]
+    pub ref_ts: Option<NtpTimestamp>,
+    /// results of query (array of objects)
+    pub result: Vec<Response<'a, NtpReply<'a>>>,
+    /// round-trip delay from server to stratum 0 time source in seconds (float)
+    #[serde(rename = "root-delay")]
+    pub root_delay: Option<f64>,
+    /// total dispersion to stratum 0 time source in seconds (float)
+    #[serde(rename = "root-dispersion")]
+    pub root_dispersion: Option<f64>,
+    /// distance in hops from server to primary time source (int)
+    pub stratum: Option<Stratum>,
+    /// NTP protocol version (int)
+    pub version: Option<i32>,
+
+    /// IP address of the destination (string)
+    pub dst_addr: Option<Cow<'a, str>>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
+pub struct NtpReply<'a> {
+    /// NTP time the response from the server is received (float)
+    #[serde(rename = "final-ts")]
+    pub final_ts: NtpTimestamp,
+    /// clock offset between client and server in seconds (float)
+    pub offset: f64,
+    /// NTP time the request was sent (float)
+    #[serde(rename = "origin-ts")]
+    pub origin_ts: NtpTimestamp,
+    /// NTP time the server received the request (float)
+    #[serde(rename = "receive-ts")]
+    pub receive_ts: NtpTimestamp,
+    /// round trip time between client and server in seconds (float)
+    pub rtt: f64,
+    /// NTP time the server sent the response (float)
+    #[serde(rename = "transmit-ts")]
+    pub transmit_ts: NtpTimestamp,
+
+    /// total dispersion to stratum 0 time source in seconds (float)
+    #[serde(rename = "root-dispersion")]
+    pub root_dispersion: Option<f64>,
+    /// leap indicator, values "no", "61", "59", or "unknown" (string)
+    pub li: Option<LeapIndicator>,
+    /// precision of the server's clock in seconds (float)
+    pub precision: Option<f64>,
+    /// server's reference clock (string)
+    #[serde(rename = "ref-id")]
+    pub ref_id: Option<Cow<'a, str>>,
+    /// server's reference timestamp in NTP seconds (float)
+    #[serde(rename = "ref-ts")]
+    pub ref_ts: Option<NtpTimestamp>,
+    /// distance in hops from server to primary time source (int)
+    pub stratum: Option<Stratum>,
+    /// round-trip delay from server to stratum 0 time source in seconds (float)
+    #[serde(rename = "root-delay")]
+    pub root_delay: Option<f64>,
+}
+
+impl<'a> Ntp<'a> {
+    /// Iterate over the replies received, skipping timeouts and errors
+    pub fn replies(&self) -> impl Iterator<Item = &NtpReply<'a>> {
+        self.result.iter().filter_map(|response| match response {
+            Response::Reply(reply) => Some(reply),
+            _ => None,
+        })
+    }
+}
+
+impl<'a> NtpReply<'a> {
+    /// Clock offset recomputed from the four timestamps (RFC 5905 8). This should match `offset`.
+    pub fn computed_offset(&self) -> f64 {
+        ((self.receive_ts - self.origin_ts) + (self.transmit_ts - self.final_ts)) / 2.0
+    }
+
+    /// Round trip delay recomputed from the four timestamps (RFC 5905 8). This should match
+    /// `rtt`.
+    pub fn computed_delay(&self) -> f64 {
+        (self.final_ts - self.origin_ts) - (self.transmit_ts - self.receive_ts)
+    }
+}
+
+/// Seconds since the start of an NTP era. Era 0 started on 1900-01-01 00:00:00 UTC and era 1
+/// starts in 2036 when the 32 bit seconds field wraps around.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, PartialOrd)]
+#[serde(transparent)]
+pub struct NtpTimestamp(pub f64);
+
+impl NtpTimestamp {
+    /// Seconds from the start of NTP era 0 to the unix epoch
+    const UNIX_EPOCH: f64 = 2_208_988_800.0;
+    /// Length of an NTP era in seconds
+    const ERA: f64 = 4_294_967_296.0;
+
+    pub fn seconds(&self) -> f64 {
+        self.0
+    }
+
+    /// Servers report a reference timestamp of 0 when they have never been synchronized
+    pub fn is_zero(&self) -> bool {
+        self.0 == 0.0
+    }
+
+    /// Seconds since the unix epoch. Values which would fall before 1968 are assumed to belong to
+    /// era 1 instead, which keeps conversions correct until 2104.
+    pub fn unix_seconds(&self) -> f64 {
+        if self.0 < Self::ERA / 2.0 {
+            self.0 + Self::ERA - Self::UNIX_EPOCH
+        } else {
+            self.0 - Self::UNIX_EPOCH
+        }
+    }
+
+    /// Seconds since the unix epoch using the era which places this timestamp closest to
+    /// `reference`, such as the `timestamp` of the measurement.
+    pub fn unix_seconds_near(&self, reference: UnixTimestamp) -> f64 {
+        let reference = unix_seconds(reference) as f64 + Self::UNIX_EPOCH;
+        let era = ((reference - self.0) / Self::ERA).round();
+        self.0 + era * Self::ERA - Self::UNIX_EPOCH
+    }
+
+    /// Convert to a unix timestamp. The fractional part of the second is kept when the `chrono`
+    /// feature is enabled. Returns `None` for a zero timestamp.
+    pub fn to_unix_timestamp(&self) -> Option<UnixTimestamp> {
+        if self.is_zero() {
+            return None;
+        }
+
+        let seconds = self.unix_seconds();
+        let timestamp = from_unix_seconds(seconds.floor() as i64)?;
+
+        #[cfg(feature = "chrono")]
+        let timestamp = timestamp.checked_add_signed(chrono::Duration::nanoseconds(
+            (seconds.fract() * 1e9).round() as i64,
+        ))?;
+
+        Some(timestamp)
+    }
+}
+
+/// The difference between two timestamps in seconds. Timestamps in neighboring eras are handled
+/// by assuming the difference is less than half an era (about 68 years).
+impl Sub for NtpTimestamp {
+    type Output = f64;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        let difference = (self.0 - rhs.0) % Self::ERA;
+
+        if difference > Self::ERA / 2.0 {
+            difference - Self::ERA
+        } else if difference < -Self::ERA / 2.0 {
+            difference + Self::ERA
+        } else {
+            difference
+        }
+    }
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum Stratum {
+    String(StratumConst),
+    Distance(i32),
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+pub enum StratumConst {
+    #[serde(rename = "invalid")]
+    Invalid,
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+pub enum LeapIndicator {
+    #[serde(rename = "no")]
+    No,
+    #[serde(rename = "59")]
+    Seconds59,
+    #[serde(rename = "61")]
+    Seconds61,
+    #[serde(rename = "unknown")]
+    Unknown,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::measurement::NtpMeasurement;
+
+    /// Result from a stratum 1 server, with one query which timed out
+    const NTP: &str = r#"{"af":4,"dst_addr":"192.0.2.123","dst_name":"ntp.example.net",
+        "from":"203.0.113.5","fw":5080,"li":"no","lts":20,"mode":"server","msm_id":60001,
+        "msm_name":"Ntp","poll":8,"precision":9.53674e-07,"prb_id":6001,"proto":"UDP",
+        "ref-id":"GPS","ref-ts":3878841587.5,"result":[{"final-ts":3878841600.0301,
+        "offset":0.0015,"origin-ts":3878841600.0,"receive-ts":3878841600.0165,
+        "rtt":0.03,"transmit-ts":3878841600.0166},{"x":"*"}],"root-delay":0,
+        "root-dispersion":0.000122,"src_addr":"192.168.1.10","stratum":1,
+        "timestamp":1669852800,"type":"ntp","version":4}"#;
+
+    #[test]
+    fn recomputes_offset_and_delay() {
+        let measurement: NtpMeasurement = serde_json::from_str(NTP).unwrap();
+        let replies: Vec<&NtpReply> = measurement.replies().collect();
+        assert_eq!(replies.len(), 1);
+
+        let reply = replies[0];
+        assert!((reply.computed_offset() - reply.offset).abs() < 1e-6);
+        assert!((reply.computed_delay() - reply.rtt).abs() < 1e-6);
+    }
+
+    #[test]
+    fn converts_to_unix_time() {
+        let measurement: NtpMeasurement = serde_json::from_str(NTP).unwrap();
+        let ref_ts = measurement.ref_ts.unwrap();
+        assert_eq!(ref_ts.seconds(), 3878841587.5);
+        assert_eq!(ref_ts.unix_seconds(), 1669852787.5);
+        assert_eq!(
+            ref_ts.unix_seconds_near(measurement.timestamp),
+            1669852787.5
+        );
+
+        let timestamp = ref_ts.to_unix_timestamp().unwrap();
+        assert_eq!(unix_seconds(timestamp), 1669852787);
+        #[cfg(feature = "chrono")]
+        assert_eq!(timestamp.timestamp_subsec_millis(), 500);
+
+        // Servers which were never synchronized report a zero reference timestamp
+        assert!(NtpTimestamp(0.0).is_zero());
+        assert_eq!(NtpTimestamp(0.0).to_unix_timestamp(), None);
+    }
+
+    #[test]
+    fn handles_era_rollover() {
+        // 2036-02-07 06:28:16 is the start of era 1
+        let early_era_1 = NtpTimestamp(1000.0);
+        assert_eq!(early_era_1.unix_seconds(), 2085979496.0);
+
+        let near = from_unix_seconds(2085978000).unwrap();
+        assert_eq!(early_era_1.unix_seconds_near(near), 2085979496.0);
+
+        // Timestamps which would fall before 1968 are placed in era 1 unless a reference says
+        // otherwise
+        let before_1968 = NtpTimestamp(2000000000.0);
+        assert_eq!(before_1968.unix_seconds(), 2000000000.0 + 2085978496.0);
+        let epoch = from_unix_seconds(0).unwrap();
+        assert_eq!(before_1968.unix_seconds_near(epoch), -208988800.0);
+
+        // Differences across the rollover stay small
+        let end_of_era_0 = NtpTimestamp(4294967290.0);
+        assert_eq!(NtpTimestamp(5.0) - end_of_era_0, 11.0);
+        assert_eq!(end_of_era_0 - NtpTimestamp(5.0), -11.0);
+        assert_eq!(NtpTimestamp(10.5) - NtpTimestamp(0.5), 10.0);
+    }
+}
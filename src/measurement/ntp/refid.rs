@@ -0,0 +1,214 @@
+//! Interpretation of the reference identifier reported by NTP servers, whose meaning depends on
+//! the stratum of the server (RFC 5905 7.3).
+use crate::measurement::ntp::{Ntp, NtpReply, Stratum};
+use std::fmt::{self, Display, Formatter};
+use std::net::Ipv4Addr;
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum RefId<'a> {
+    /// Kiss-o'-Death code sent by a server which is unsynchronized or refusing service, such as
+    /// "INIT", "RATE" or "DENY"
+    KissCode(&'a str),
+    /// reference clock of a primary (stratum 1) server, such as "GPS", "PPS" or "DCF"
+    RefClock(&'a str),
+    /// upstream server the server is synchronized to. This is the IPv4 address of the upstream
+    /// server or the first four octets of the MD5 hash of its IPv6 address, which can not be told
+    /// apart from the identifier alone.
+    Upstream([u8; 4]),
+    /// a reference identifier which does not have the expected format for the stratum
+    Unknown(&'a str),
+}
+
+impl<'a> RefId<'a> {
+    /// Interpret a reference identifier. The identifier of a secondary server is four octets which
+    /// are either an IPv4 address or part of the hash of an IPv6 address, depending on how the
+    /// server reaches its upstream. This is not related to the address family used by the probe,
+    /// so both are returned as [`RefId::Upstream`].
+    pub fn parse(ref_id: &'a str, stratum: Stratum) -> Self {
+        let ref_id = ref_id.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+
+        match stratum {
+            Stratum::String(_) | Stratum::Distance(0) if is_code(ref_id) => RefId::KissCode(ref_id),
+            Stratum::Distance(1) if is_code(ref_id) => RefId::RefClock(ref_id),
+            Stratum::Distance(2..=15) => match parse_octets(ref_id) {
+                Some(octets) => RefId::Upstream(octets),
+                None => RefId::Unknown(ref_id),
+            },
+            _ => RefId::Unknown(ref_id),
+        }
+    }
+
+    /// This identifier names an upstream server rather than a reference clock or kiss code
+    pub fn is_upstream(&self) -> bool {
+        matches!(self, RefId::Upstream(_))
+    }
+
+    /// The upstream identifier read as the IPv4 address of the upstream server
+    pub fn as_ipv4(&self) -> Option<Ipv4Addr> {
+        match self {
+            RefId::Upstream(octets) => Some(Ipv4Addr::from(*octets)),
+            _ => None,
+        }
+    }
+
+    /// The upstream identifier read as the start of the MD5 hash of the IPv6 address of the
+    /// upstream server
+    pub fn as_ipv6_hash(&self) -> Option<[u8; 4]> {
+        match self {
+            RefId::Upstream(octets) => Some(*octets),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Display for RefId<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RefId::KissCode(code) | RefId::RefClock(code) | RefId::Unknown(code) => {
+                write!(f, "{}", code)
+            }
+            RefId::Upstream(octets) => write!(f, "{}", Ipv4Addr::from(*octets)),
+        }
+    }
+}
+
+impl<'a> Ntp<'a> {
+    /// The reference identifier of the server. Falls back to the first reply when it is not
+    /// reported at the top level of the result.
+    pub fn reference_id(&self) -> Option<RefId<'_>> {
+        if let (Some(ref_id), Some(stratum)) = (&self.ref_id, self.stratum) {
+            return Some(RefId::parse(ref_id, stratum));
+        }
+
+        self.replies().find_map(NtpReply::reference_id)
+    }
+}
+
+impl<'a> NtpReply<'a> {
+    /// The reference identifier of the server in this reply
+    pub fn reference_id(&self) -> Option<RefId<'_>> {
+        Some(RefId::parse(self.ref_id.as_deref()?, self.stratum?))
+    }
+}
+
+/// Kiss codes and reference clock names are up to four printable ASCII characters
+fn is_code(ref_id: &str) -> bool {
+    !ref_id.is_empty() && ref_id.len() <= 4 && ref_id.bytes().all(|byte| byte.is_ascii_graphic())
+}
+
+/// Read the four octets of the identifier from either a dotted quad or 8 hex digits
+fn parse_octets(ref_id: &str) -> Option<[u8; 4]> {
+    if let Ok(addr) = ref_id.parse::<Ipv4Addr>() {
+        return Some(addr.octets());
+    }
+
+    let hex = ref_id
+        .strip_prefix("0x")
+        .or_else(|| ref_id.strip_prefix("0X"))
+        .unwrap_or(ref_id);
+
+    if hex.len() != 8 {
+        return None;
+    }
+
+    u32::from_str_radix(hex, 16).ok().map(u32::to_be_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::measurement::ntp::StratumConst;
+    use crate::measurement::NtpMeasurement;
+    use serde_json::{json, Value};
+
+    fn measurement(top: Value, reply: Value) -> NtpMeasurement<'static> {
+        let mut value = json!({
+            "af": 6, "dst_addr": "2001:db8::123", "dst_name": "ntp.example.net",
+            "from": "2001:db8:1::5", "fw": 5080, "lts": 20, "msm_id": 60001, "msm_name": "Ntp",
+            "prb_id": 6001, "proto": "UDP", "timestamp": 1669852800, "type": "ntp",
+            "result": [{"x": "*"}, reply]
+        });
+        for (key, field) in top.as_object().unwrap() {
+            value[key] = field.clone();
+        }
+        serde_json::from_value(value).unwrap()
+    }
+
+    /// A reply with only the four timestamps and the fields given
+    fn reply(fields: Value) -> Value {
+        let mut reply = json!({
+            "final-ts": 3878841600.0301, "offset": 0.0015, "origin-ts": 3878841600.0,
+            "receive-ts": 3878841600.0165, "rtt": 0.03, "transmit-ts": 3878841600.0166
+        });
+        for (key, field) in fields.as_object().unwrap() {
+            reply[key] = field.clone();
+        }
+        reply
+    }
+
+    #[test]
+    fn interprets_identifier_by_stratum() {
+        let cases = [
+            ("RATE", Stratum::Distance(0), RefId::KissCode("RATE")),
+            (
+                "INIT",
+                Stratum::String(StratumConst::Invalid),
+                RefId::KissCode("INIT"),
+            ),
+            ("GPS\0", Stratum::Distance(1), RefId::RefClock("GPS")),
+            ("PPS ", Stratum::Distance(1), RefId::RefClock("PPS")),
+            (
+                "193.0.0.229",
+                Stratum::Distance(2),
+                RefId::Upstream([193, 0, 0, 229]),
+            ),
+            ("0xc100e5", Stratum::Distance(3), RefId::Unknown("0xc100e5")),
+            (
+                "c100e5a0",
+                Stratum::Distance(15),
+                RefId::Upstream([0xc1, 0x00, 0xe5, 0xa0]),
+            ),
+            ("GPS", Stratum::Distance(2), RefId::Unknown("GPS")),
+            (
+                "10.0.0.1",
+                Stratum::Distance(16),
+                RefId::Unknown("10.0.0.1"),
+            ),
+            ("", Stratum::Distance(1), RefId::Unknown("")),
+        ];
+
+        for (ref_id, stratum, expected) in cases {
+            assert_eq!(RefId::parse(ref_id, stratum), expected, "{:?}", ref_id);
+        }
+    }
+
+    #[test]
+    fn reads_upstream_both_ways() {
+        let upstream = RefId::parse("193.0.0.229", Stratum::Distance(2));
+        assert!(upstream.is_upstream());
+        assert_eq!(upstream.as_ipv4(), Some(Ipv4Addr::new(193, 0, 0, 229)));
+        assert_eq!(upstream.as_ipv6_hash(), Some([193, 0, 0, 229]));
+        assert_eq!(upstream.to_string(), "193.0.0.229");
+
+        let clock = RefId::parse("DCF", Stratum::Distance(1));
+        assert!(!clock.is_upstream());
+        assert_eq!((clock.as_ipv4(), clock.as_ipv6_hash()), (None, None));
+        assert_eq!(clock.to_string(), "DCF");
+    }
+
+    #[test]
+    fn reads_identifier_from_result() {
+        // Reported at the top level, where the address family of the probe does not matter
+        let top = measurement(
+            json!({"ref-id": "193.0.0.229", "stratum": 2}),
+            reply(json!({"ref-id": "GPS", "stratum": 1})),
+        );
+        assert_eq!(top.reference_id(), Some(RefId::Upstream([193, 0, 0, 229])));
+
+        let fallback = measurement(json!({}), reply(json!({"ref-id": "RATE", "stratum": 0})));
+        assert_eq!(fallback.reference_id(), Some(RefId::KissCode("RATE")));
+
+        let missing = measurement(json!({"ref-id": "GPS"}), reply(json!({})));
+        assert_eq!(missing.reference_id(), None);
+    }
+}
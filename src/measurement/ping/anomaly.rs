@@ -0,0 +1,392 @@
+//! Detection of sudden shifts in latency in the per-probe time series built by
+//! [`PingAggregator`], and correlation of shifts seen by many probes towards the same destination.
+use crate::general::{unix_seconds, UnixTimestamp};
+use crate::measurement::ping::series::{Bucket, PingAggregator};
+use crate::measurement::stats::median;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// A change in the typical RTT of a single series. RTTs are in milliseconds.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Shift {
+    /// start of the first bucket at the new level
+    pub start: UnixTimestamp,
+    /// typical RTT before the shift
+    pub before: f64,
+    /// typical RTT after the shift
+    pub after: f64,
+}
+
+impl Shift {
+    /// Size of the shift. This is positive if latency increased.
+    pub fn magnitude(&self) -> f64 {
+        self.after - self.before
+    }
+}
+
+/// A change-point detector which runs over the median RTT of each bucket of a series
+pub trait ChangeDetector {
+    fn detect(&self, series: &[Bucket]) -> Vec<Shift>;
+}
+
+/// Compares the median of the buckets before and after each point in the series. A shift is
+/// reported for each run of points where the difference exceeds the threshold.
+#[derive(Copy, Clone, Debug)]
+pub struct LevelShiftDetector {
+    /// number of buckets on each side of a point
+    pub window: usize,
+    /// smallest difference in milliseconds to report
+    pub threshold: f64,
+}
+
+impl ChangeDetector for LevelShiftDetector {
+    fn detect(&self, series: &[Bucket]) -> Vec<Shift> {
+        let points = medians(series);
+        let window = self.window.max(1);
+        let mut shifts = Vec::new();
+
+        if points.len() < 2 * window {
+            return shifts;
+        }
+
+        let mut run: Vec<(usize, f64, f64)> = Vec::new();
+        for index in window..=points.len() - window {
+            let before = window_median(&points[index - window..index]);
+            let after = window_median(&points[index..index + window]);
+
+            if (after - before).abs() >= self.threshold {
+                run.push((index, before, after));
+            } else if !run.is_empty() {
+                shifts.push(strongest(&points, window, &run));
+                run.clear();
+            }
+        }
+
+        if !run.is_empty() {
+            shifts.push(strongest(&points, window, &run));
+        }
+
+        shifts
+    }
+}
+
+/// Two sided CUSUM detector. The baseline is the median of the first `warmup` buckets and is
+/// moved to the new level after each detected shift.
+#[derive(Copy, Clone, Debug)]
+pub struct CusumDetector {
+    /// number of buckets used to establish the initial baseline
+    pub warmup: usize,
+    /// deviation from the baseline in milliseconds which is tolerated in every bucket
+    pub drift: f64,
+    /// cumulative deviation in milliseconds at which a shift is reported
+    pub threshold: f64,
+}
+
+impl ChangeDetector for CusumDetector {
+    fn detect(&self, series: &[Bucket]) -> Vec<Shift> {
+        let points = medians(series);
+        let warmup = self.warmup.max(1);
+        let mut shifts = Vec::new();
+
+        if points.len() <= warmup {
+            return shifts;
+        }
+
+        let mut baseline = window_median(&points[..warmup]);
+        let (mut upper, mut lower) = (0.0f64, 0.0f64);
+        let (mut upper_start, mut lower_start) = (warmup, warmup);
+
+        for index in warmup..points.len() {
+            let value = points[index].1;
+
+            upper = (upper + value - baseline - self.drift).max(0.0);
+            lower = (lower + baseline - value - self.drift).max(0.0);
+            if upper == 0.0 {
+                upper_start = index + 1;
+            }
+            if lower == 0.0 {
+                lower_start = index + 1;
+            }
+
+            if upper > self.threshold || lower > self.threshold {
+                let start = if upper > self.threshold {
+                    upper_start
+                } else {
+                    lower_start
+                };
+
+                let after = window_median(&points[start..=index]);
+                shifts.push(Shift {
+                    start: points[start].0,
+                    before: baseline,
+                    after,
+                });
+
+                baseline = after;
+                upper = 0.0;
+                lower = 0.0;
+                upper_start = index + 1;
+                lower_start = index + 1;
+            }
+        }
+
+        shifts
+    }
+}
+
+/// Shifts towards a single destination which started at about the same time. Many probes
+/// shifting together suggests a change close to the destination rather than the probes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LatencyEvent {
+    /// address of the destination, or its name if the address was not reported
+    pub dst_addr: String,
+    /// start of the earliest shift
+    pub start: UnixTimestamp,
+    /// median magnitude of the shifts in milliseconds
+    pub magnitude: f64,
+    /// probes which saw the shift along with the shift they saw
+    pub probes: Vec<(i64, Shift)>,
+}
+
+impl PingAggregator {
+    /// Run a detector over every series and group the shifts by destination. Shifts in the same
+    /// direction which start within `tolerance` of the first shift of an event are merged into
+    /// that event. Events are ordered by destination and then start time.
+    pub fn detect_shifts<D: ChangeDetector>(
+        &self,
+        detector: &D,
+        tolerance: Duration,
+    ) -> Vec<LatencyEvent> {
+        let mut by_destination: BTreeMap<&str, Vec<(i64, Shift)>> = BTreeMap::new();
+        for (key, series) in self.iter() {
+            for shift in detector.detect(&series) {
+                by_destination
+                    .entry(key.dst_addr.as_str())
+                    .or_default()
+                    .push((key.prb_id, shift));
+            }
+        }
+
+        let tolerance = tolerance.as_secs() as i64;
+        let mut events = Vec::new();
+
+        for (dst_addr, mut shifts) in by_destination {
+            shifts.sort_by_key(|(prb_id, shift)| (unix_seconds(shift.start), *prb_id));
+
+            let mut open: Vec<LatencyEvent> = Vec::new();
+            for (prb_id, shift) in shifts {
+                let start = unix_seconds(shift.start);
+                let existing = open.iter_mut().rev().find(|event| {
+                    start - unix_seconds(event.start) <= tolerance
+                        && (event.magnitude > 0.0) == (shift.magnitude() > 0.0)
+                });
+
+                match existing {
+                    Some(event) => {
+                        event.probes.push((prb_id, shift));
+                        event.magnitude = median(
+                            &mut event
+                                .probes
+                                .iter()
+                                .map(|(_, shift)| shift.magnitude())
+                                .collect::<Vec<_>>(),
+                        );
+                    }
+                    None => open.push(LatencyEvent {
+                        dst_addr: dst_addr.to_string(),
+                        start: shift.start,
+                        magnitude: shift.magnitude(),
+                        probes: vec![(prb_id, shift)],
+                    }),
+                }
+            }
+
+            events.extend(open);
+        }
+
+        events
+    }
+}
+
+/// Buckets which received at least one reply along with their median RTT
+fn medians(series: &[Bucket]) -> Vec<(UnixTimestamp, f64)> {
+    series
+        .iter()
+        .filter_map(|bucket| Some((bucket.start, bucket.median?)))
+        .collect()
+}
+
+fn window_median(points: &[(UnixTimestamp, f64)]) -> f64 {
+    median(&mut points.iter().map(|(_, value)| *value).collect::<Vec<_>>())
+}
+
+/// Pick the point in a run of points exceeding the threshold which best splits the two levels.
+/// Windows which only partly overlap the shift often have the same medians, so the difference
+/// between the means of the windows is used to find the exact point.
+fn strongest(points: &[(UnixTimestamp, f64)], window: usize, run: &[(usize, f64, f64)]) -> Shift {
+    let mean = |points: &[(UnixTimestamp, f64)]| {
+        points.iter().map(|(_, value)| value).sum::<f64>() / points.len() as f64
+    };
+    let split = |index: usize| {
+        (mean(&points[index..index + window]) - mean(&points[index - window..index])).abs()
+    };
+
+    let (index, before, after) = *run
+        .iter()
+        .max_by(|a, b| split(a.0).total_cmp(&split(b.0)))
+        .expect("run is not empty");
+
+    Shift {
+        start: points[index].0,
+        before,
+        after,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::general::from_unix_seconds;
+    use crate::measurement::PingMeasurement;
+    use serde_json::json;
+
+    /// Start of the first bucket, 2022-12-01 00:00:00
+    const START: i64 = 1669852800;
+
+    /// One bucket per minute with the given median RTTs. `None` stands for a bucket where every
+    /// packet was lost.
+    fn series(medians: &[Option<f64>]) -> Vec<Bucket> {
+        medians
+            .iter()
+            .enumerate()
+            .map(|(index, median)| Bucket {
+                start: from_unix_seconds(START + 60 * index as i64).unwrap(),
+                measurements: 1,
+                received: if median.is_some() { 3 } else { 0 },
+                lost: if median.is_some() { 0 } else { 3 },
+                min: *median,
+                median: *median,
+                p95: *median,
+            })
+            .collect()
+    }
+
+    fn level(values: &[f64]) -> Vec<Option<f64>> {
+        values.iter().copied().map(Some).collect()
+    }
+
+    /// A measurement every minute from a probe, with a single reply of the given RTT
+    fn measurements(prb_id: i64, dst_addr: &str, rtts: &[f64]) -> Vec<PingMeasurement<'static>> {
+        rtts.iter()
+            .enumerate()
+            .map(|(index, rtt)| {
+                serde_json::from_value(json!({
+                    "af": 4, "avg": rtt, "dst_addr": dst_addr, "dst_name": dst_addr, "dup": 0,
+                    "from": "203.0.113.5", "fw": 5080, "lts": 20, "max": rtt, "min": rtt,
+                    "msm_id": 1001, "msm_name": "Ping", "prb_id": prb_id, "proto": "ICMP",
+                    "rcvd": 1, "result": [{"rtt": rtt}], "sent": 1, "size": 48,
+                    "src_addr": "192.168.1.10", "step": 60,
+                    "timestamp": START + 60 * index as i64, "ttl": 55, "type": "ping"
+                }))
+                .unwrap()
+            })
+            .collect()
+    }
+
+    fn starts(shifts: &[Shift]) -> Vec<(i64, f64, f64)> {
+        shifts
+            .iter()
+            .map(|shift| (unix_seconds(shift.start) - START, shift.before, shift.after))
+            .collect()
+    }
+
+    #[test]
+    fn finds_level_shifts() {
+        let detector = LevelShiftDetector {
+            window: 2,
+            threshold: 5.0,
+        };
+
+        let step = series(&level(&[10.0, 10.0, 10.0, 10.0, 30.0, 30.0, 30.0, 30.0]));
+        let shifts = detector.detect(&step);
+        assert_eq!(starts(&shifts), vec![(240, 10.0, 30.0)]);
+        assert_eq!(shifts[0].magnitude(), 20.0);
+
+        // Buckets without replies are skipped
+        let mut gap = level(&[10.0, 10.0, 10.0, 10.0, 30.0, 30.0, 30.0, 30.0]);
+        gap.insert(2, None);
+        assert_eq!(
+            starts(&detector.detect(&series(&gap))),
+            vec![(300, 10.0, 30.0)]
+        );
+
+        let noise = series(&level(&[10.0, 12.0, 9.0, 11.0, 13.0, 10.0, 12.0, 9.0]));
+        assert!(detector.detect(&noise).is_empty());
+        assert!(detector
+            .detect(&series(&level(&[10.0, 10.0, 30.0])))
+            .is_empty());
+        assert!(detector.detect(&[]).is_empty());
+    }
+
+    #[test]
+    fn finds_cusum_shifts() {
+        let detector = CusumDetector {
+            warmup: 3,
+            drift: 1.0,
+            threshold: 10.0,
+        };
+
+        // An increase which is undone later, with small deviations along the way
+        let values = [
+            10.0, 10.0, 10.0, 11.0, 9.0, 10.0, 20.0, 20.0, 20.0, 10.0, 10.0,
+        ];
+        let shifts = detector.detect(&series(&level(&values)));
+        assert_eq!(starts(&shifts), vec![(360, 10.0, 20.0), (540, 20.0, 10.0)]);
+
+        let lost = [Some(10.0), Some(10.0), None, None, None];
+        assert!(detector.detect(&series(&lost)).is_empty());
+        assert!(detector
+            .detect(&series(&level(&[10.0, 50.0, 50.0])))
+            .is_empty());
+    }
+
+    #[test]
+    fn correlates_shifts_towards_a_destination() {
+        let k_root = "193.0.14.129";
+        let up = [10.0, 10.0, 10.0, 10.0, 30.0, 30.0, 30.0, 30.0];
+        let later = [10.0, 10.0, 10.0, 10.0, 10.0, 30.0, 30.0, 30.0];
+        let larger = [10.0, 10.0, 10.0, 10.0, 40.0, 40.0, 40.0, 40.0];
+        let down = [30.0, 30.0, 30.0, 30.0, 10.0, 10.0, 10.0, 10.0];
+        let flat = [10.0; 8];
+
+        let mut aggregator = PingAggregator::new(Duration::from_secs(60)).unwrap();
+        aggregator.extend(&measurements(1, k_root, &up));
+        aggregator.extend(&measurements(2, k_root, &later));
+        aggregator.extend(&measurements(3, k_root, &larger));
+        aggregator.extend(&measurements(4, k_root, &down));
+        aggregator.extend(&measurements(5, "192.0.2.1", &flat));
+
+        let detector = LevelShiftDetector {
+            window: 2,
+            threshold: 5.0,
+        };
+        let events = aggregator.detect_shifts(&detector, Duration::from_secs(60));
+        assert_eq!(events.len(), 2);
+
+        let increase = &events[0];
+        assert_eq!(increase.dst_addr, k_root);
+        assert_eq!(unix_seconds(increase.start) - START, 240);
+        assert_eq!(increase.magnitude, 20.0);
+        let probes: Vec<i64> = increase.probes.iter().map(|(prb_id, _)| *prb_id).collect();
+        assert_eq!(probes, vec![1, 3, 2]);
+
+        let decrease = &events[1];
+        assert_eq!(decrease.magnitude, -20.0);
+        assert_eq!(decrease.probes.len(), 1);
+
+        // Without any tolerance the later shift is an event of its own
+        let events = aggregator.detect_shifts(&detector, Duration::ZERO);
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].magnitude, 25.0);
+    }
+}
@@ -3,6 +3,10 @@ use crate::measurement::Response;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 
+pub mod anomaly;
+pub mod series;
+pub mod stats;
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 #[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
 pub struct Ping<'a> {
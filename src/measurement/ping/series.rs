@@ -0,0 +1,344 @@
+//! Aggregation of ping measurements into fixed width time buckets for each probe and destination.
+use crate::general::{from_unix_seconds, unix_seconds, UnixTimestamp};
+use crate::measurement::stats::percentile;
+use crate::measurement::PingMeasurement;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+/// The bucket width given to [`PingAggregator::new`] can not be used
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BucketWidthError {
+    /// The width is shorter than one second
+    TooShort(Duration),
+    /// The width in seconds does not fit in a signed 64 bit integer
+    TooLong(Duration),
+}
+
+impl Display for BucketWidthError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BucketWidthError::TooShort(width) => {
+                write!(f, "bucket width of {:?} is shorter than one second", width)
+            }
+            BucketWidthError::TooLong(width) => {
+                write!(f, "bucket width of {:?} is too long", width)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BucketWidthError {}
+
+/// Identifies the time series of a single probe towards a single destination
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct SeriesKey {
+    pub prb_id: i64,
+    /// address of the destination, or its name if the address was not reported
+    pub dst_addr: String,
+}
+
+/// RTT and loss statistics for a single time bucket. RTTs are in milliseconds.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Bucket {
+    /// start of the bucket
+    pub start: UnixTimestamp,
+    /// number of measurements in the bucket
+    pub measurements: usize,
+    /// number of replies, excluding duplicates
+    pub received: usize,
+    /// number of packets without a reply
+    pub lost: usize,
+    pub min: Option<f64>,
+    pub median: Option<f64>,
+    /// 95th percentile
+    pub p95: Option<f64>,
+}
+
+impl Bucket {
+    /// Fraction of packets without a reply between 0 and 1
+    pub fn loss_ratio(&self) -> Option<f64> {
+        match self.received + self.lost {
+            0 => None,
+            total => Some(self.lost as f64 / total as f64),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct BucketValues {
+    start: UnixTimestamp,
+    measurements: usize,
+    lost: usize,
+    rtts: Vec<f64>,
+}
+
+/// Collects ping measurements into time buckets of a fixed width. Measurements can be added in
+/// any order and at any time, and statistics are computed when a series is read.
+#[derive(Clone, Debug)]
+pub struct PingAggregator {
+    width: i64,
+    series: BTreeMap<SeriesKey, BTreeMap<i64, BucketValues>>,
+}
+
+impl PingAggregator {
+    /// Create an aggregator with the given bucket width. The width is rounded down to whole
+    /// seconds. Returns [`BucketWidthError::TooShort`] if it is shorter than one second.
+    pub fn new(width: Duration) -> Result<Self, BucketWidthError> {
+        let seconds =
+            i64::try_from(width.as_secs()).map_err(|_| BucketWidthError::TooLong(width))?;
+        if seconds == 0 {
+            return Err(BucketWidthError::TooShort(width));
+        }
+
+        Ok(PingAggregator {
+            width: seconds,
+            series: BTreeMap::new(),
+        })
+    }
+
+    pub fn width(&self) -> Duration {
+        Duration::from_secs(self.width as u64)
+    }
+
+    /// Add a measurement to the bucket containing its `timestamp`. The measurement is skipped if
+    /// the start of that bucket can not be represented as a timestamp, which can only happen for
+    /// timestamps and widths near the limits of the supported range.
+    pub fn add(&mut self, measurement: &PingMeasurement) {
+        let (start, start_timestamp) = match unix_seconds(measurement.timestamp)
+            .div_euclid(self.width)
+            .checked_mul(self.width)
+            .and_then(|start| Some((start, from_unix_seconds(start)?)))
+        {
+            Some(start) => start,
+            None => return,
+        };
+
+        let key = SeriesKey {
+            prb_id: measurement.prb_id,
+            dst_addr: measurement
+                .dst_addr
+                .as_deref()
+                .unwrap_or(&measurement.dst_name)
+                .to_string(),
+        };
+
+        let values = self
+            .series
+            .entry(key)
+            .or_default()
+            .entry(start)
+            .or_insert_with(|| BucketValues {
+                start: start_timestamp,
+                measurements: 0,
+                lost: 0,
+                rtts: Vec::new(),
+            });
+
+        values.measurements += 1;
+        values.lost += measurement.lost();
+        values.rtts.extend(measurement.rtts());
+    }
+
+    /// The keys of every series in order of probe and destination
+    pub fn keys(&self) -> impl Iterator<Item = &SeriesKey> {
+        self.series.keys()
+    }
+
+    /// Buckets of a single series in chronological order. Buckets without any measurements are
+    /// not included.
+    pub fn series(&self, prb_id: i64, dst_addr: &str) -> Option<Vec<Bucket>> {
+        let key = SeriesKey {
+            prb_id,
+            dst_addr: dst_addr.to_string(),
+        };
+
+        self.series.get(&key).map(summarize)
+    }
+
+    /// Iterate over every series in order of probe and destination
+    pub fn iter(&self) -> impl Iterator<Item = (&SeriesKey, Vec<Bucket>)> {
+        self.series
+            .iter()
+            .map(|(key, buckets)| (key, summarize(buckets)))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.series.is_empty()
+    }
+}
+
+impl<'a, 'b: 'a> Extend<&'a PingMeasurement<'b>> for PingAggregator {
+    fn extend<T: IntoIterator<Item = &'a PingMeasurement<'b>>>(&mut self, iter: T) {
+        for measurement in iter {
+            self.add(measurement);
+        }
+    }
+}
+
+fn summarize(buckets: &BTreeMap<i64, BucketValues>) -> Vec<Bucket> {
+    buckets
+        .values()
+        .map(|values| {
+            let mut rtts = values.rtts.clone();
+            rtts.sort_by(f64::total_cmp);
+
+            let (min, median, p95) = match rtts.first() {
+                Some(min) => (
+                    Some(*min),
+                    Some(percentile(&rtts, 0.5)),
+                    Some(percentile(&rtts, 0.95)),
+                ),
+                None => (None, None, None),
+            };
+
+            Bucket {
+                start: values.start,
+                measurements: values.measurements,
+                received: rtts.len(),
+                lost: values.lost,
+                min,
+                median,
+                p95,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn measurement(
+        prb_id: i64,
+        dst_addr: Option<&str>,
+        timestamp: i64,
+        result: Value,
+    ) -> PingMeasurement<'static> {
+        serde_json::from_value(json!({
+            "af": 4, "avg": 12.0, "dst_addr": dst_addr, "dst_name": "k.root-servers.net",
+            "dup": 0, "from": "203.0.113.5", "fw": 5080, "lts": 20, "max": 14.0, "min": 10.0,
+            "msm_id": 1001, "msm_name": "Ping", "prb_id": prb_id, "proto": "ICMP", "rcvd": 3,
+            "result": result, "sent": 3, "size": 48, "src_addr": "192.168.1.10", "step": 240,
+            "timestamp": timestamp, "ttl": 55, "type": "ping"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn rejects_unusable_widths() {
+        assert_eq!(
+            PingAggregator::new(Duration::from_millis(999)).unwrap_err(),
+            BucketWidthError::TooShort(Duration::from_millis(999))
+        );
+        assert!(matches!(
+            PingAggregator::new(Duration::MAX),
+            Err(BucketWidthError::TooLong(_))
+        ));
+        assert_eq!(
+            BucketWidthError::TooShort(Duration::ZERO).to_string(),
+            "bucket width of 0ns is shorter than one second"
+        );
+
+        let aggregator = PingAggregator::new(Duration::from_millis(90500)).unwrap();
+        assert_eq!(aggregator.width(), Duration::from_secs(90));
+        assert!(aggregator.is_empty());
+    }
+
+    #[test]
+    fn aggregates_buckets() {
+        let k_root = Some("193.0.14.129");
+        let measurements = [
+            // added out of order, with a timeout, an error and a duplicate
+            measurement(1, k_root, 1669852870, json!([{"rtt": 20.0}, {"x": "*"}])),
+            measurement(1, k_root, 1669852800, json!([{"rtt": 10.0}, {"rtt": 14.0}])),
+            measurement(
+                1,
+                k_root,
+                1669852830,
+                json!([{"rtt": 12.0}, {"rtt": 12.0, "dup": 1}]),
+            ),
+            measurement(1, k_root, 1669852899, json!([{"error": "sendto failed"}])),
+            measurement(2, k_root, 1669852800, json!([{"rtt": 30.0}])),
+            measurement(1, None, 1669852800, json!([{"x": "*"}])),
+        ];
+
+        let mut aggregator = PingAggregator::new(MINUTE).unwrap();
+        aggregator.extend(&measurements);
+
+        let keys: Vec<(i64, &str)> = aggregator
+            .keys()
+            .map(|key| (key.prb_id, key.dst_addr.as_str()))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                (1, "193.0.14.129"),
+                (1, "k.root-servers.net"),
+                (2, "193.0.14.129")
+            ]
+        );
+
+        let buckets = aggregator.series(1, "193.0.14.129").unwrap();
+        assert_eq!(buckets.len(), 2);
+
+        let first = &buckets[0];
+        assert_eq!(unix_seconds(first.start), 1669852800);
+        assert_eq!((first.measurements, first.received, first.lost), (2, 3, 0));
+        assert_eq!((first.min, first.median), (Some(10.0), Some(12.0)));
+        assert!((first.p95.unwrap() - 13.8).abs() < 1e-12);
+        assert_eq!(first.loss_ratio(), Some(0.0));
+
+        let second = &buckets[1];
+        assert_eq!(unix_seconds(second.start), 1669852860);
+        assert_eq!(
+            (second.measurements, second.received, second.lost),
+            (2, 1, 2)
+        );
+        assert_eq!(second.median, Some(20.0));
+        assert!((second.loss_ratio().unwrap() - 2.0 / 3.0).abs() < 1e-12);
+
+        let unresolved = aggregator.series(1, "k.root-servers.net").unwrap();
+        assert_eq!(
+            (unresolved[0].min, unresolved[0].loss_ratio()),
+            (None, Some(1.0))
+        );
+
+        assert!(aggregator.series(3, "193.0.14.129").is_none());
+        assert_eq!(aggregator.iter().count(), 3);
+    }
+
+    #[test]
+    fn reports_empty_buckets_without_loss_ratio() {
+        let mut aggregator = PingAggregator::new(MINUTE).unwrap();
+        aggregator.add(&measurement(1, Some("193.0.14.129"), 1669852800, json!([])));
+
+        let bucket = aggregator.series(1, "193.0.14.129").unwrap()[0];
+        assert_eq!(
+            (bucket.measurements, bucket.received, bucket.lost),
+            (1, 0, 0)
+        );
+        assert_eq!(bucket.loss_ratio(), None);
+    }
+
+    #[test]
+    fn skips_unrepresentable_buckets() {
+        // The start of the bucket is outside of the supported range of timestamps with chrono and
+        // does not fit in an i64 without it
+        #[cfg(feature = "chrono")]
+        let (timestamp, width) = (-1, Duration::from_secs(i64::MAX as u64));
+        #[cfg(not(feature = "chrono"))]
+        let (timestamp, width) = (i64::MIN, Duration::from_secs((1 << 62) + 1));
+
+        let mut aggregator = PingAggregator::new(width).unwrap();
+        aggregator.add(&measurement(1, Some("193.0.14.129"), timestamp, json!([])));
+        assert!(aggregator.is_empty());
+
+        aggregator.add(&measurement(1, Some("193.0.14.129"), 0, json!([])));
+        let bucket = aggregator.series(1, "193.0.14.129").unwrap()[0];
+        assert_eq!((unix_seconds(bucket.start), bucket.measurements), (0, 1));
+    }
+}
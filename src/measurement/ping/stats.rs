@@ -0,0 +1,171 @@
+//! Statistics derived from the individual replies of a ping measurement.
+use crate::measurement::ping::{Ping, PingReply};
+use crate::measurement::stats::percentile;
+use crate::measurement::Response;
+
+impl<'a> PingReply<'a> {
+    /// The reply was a duplicate of an earlier reply
+    pub fn is_duplicate(&self) -> bool {
+        self.dup.is_some_and(|dup| dup != 0)
+    }
+}
+
+impl<'a> Ping<'a> {
+    /// Round trip times of the replies in the order they were received. Duplicate replies are
+    /// skipped.
+    pub fn rtts(&self) -> impl Iterator<Item = f64> + '_ {
+        self.result.iter().filter_map(|response| match response {
+            Response::Reply(reply) if !reply.is_duplicate() => Some(reply.rtt),
+            _ => None,
+        })
+    }
+
+    /// Number of packets which timed out or could not be sent
+    pub fn lost(&self) -> usize {
+        self.result
+            .iter()
+            .filter(|response| {
+                matches!(response, Response::Timeout { .. } | Response::Error { .. })
+            })
+            .count()
+    }
+
+    /// Fraction of packets without a reply between 0 and 1. Returns `None` if no packets were
+    /// sent.
+    pub fn loss_ratio(&self) -> Option<f64> {
+        let lost = self.lost();
+        let total = lost + self.rtts().count();
+
+        match total {
+            0 => None,
+            total => Some(lost as f64 / total as f64),
+        }
+    }
+
+    pub fn mean_rtt(&self) -> Option<f64> {
+        let (count, sum) = self
+            .rtts()
+            .fold((0usize, 0.0), |(count, sum), rtt| (count + 1, sum + rtt));
+
+        match count {
+            0 => None,
+            count => Some(sum / count as f64),
+        }
+    }
+
+    pub fn median_rtt(&self) -> Option<f64> {
+        self.percentile_rtt(50.0)
+    }
+
+    /// Percentile of the round trip times, with `p` between 0 and 100. Values between two replies
+    /// are linearly interpolated.
+    pub fn percentile_rtt(&self, p: f64) -> Option<f64> {
+        let mut rtts: Vec<f64> = self.rtts().collect();
+        if rtts.is_empty() {
+            return None;
+        }
+
+        rtts.sort_by(f64::total_cmp);
+        Some(percentile(&rtts, p / 100.0))
+    }
+
+    /// Sample standard deviation of the round trip times. Requires at least 2 replies.
+    pub fn stddev_rtt(&self) -> Option<f64> {
+        let mean = self.mean_rtt()?;
+        let count = self.rtts().count();
+        if count < 2 {
+            return None;
+        }
+
+        let squares: f64 = self.rtts().map(|rtt| (rtt - mean).powi(2)).sum();
+        Some((squares / (count - 1) as f64).sqrt())
+    }
+
+    /// Smoothed mean deviation of the difference between consecutive round trip times, using the
+    /// estimator for interarrival jitter from RFC 3550 6.4.1. Requires at least 2 replies.
+    pub fn jitter(&self) -> Option<f64> {
+        let mut rtts = self.rtts();
+        let mut previous = rtts.next()?;
+        let mut jitter = None;
+
+        for rtt in rtts {
+            let estimate = jitter.unwrap_or(0.0);
+            jitter = Some(estimate + ((rtt - previous).abs() - estimate) / 16.0);
+            previous = rtt;
+        }
+
+        jitter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::measurement::PingMeasurement;
+    use serde_json::{json, Value};
+
+    fn measurement(result: Value) -> PingMeasurement<'static> {
+        serde_json::from_value(json!({
+            "af": 4, "avg": 12.0, "dst_addr": "193.0.14.129", "dst_name": "k.root-servers.net",
+            "dup": 1, "from": "203.0.113.5", "fw": 5080, "lts": 20, "max": 14.0, "min": 10.0,
+            "msm_id": 1001, "msm_name": "Ping", "prb_id": 6001, "proto": "ICMP", "rcvd": 3,
+            "result": result, "sent": 5, "size": 48, "src_addr": "192.168.1.10", "step": 240,
+            "timestamp": 1669852800, "ttl": 55, "type": "ping"
+        }))
+        .unwrap()
+    }
+
+    /// Three replies, a duplicate of the last one, a timeout and a packet which could not be sent
+    fn result() -> Value {
+        json!([
+            {"rtt": 10.0},
+            {"rtt": 14.0, "ttl": 54},
+            {"x": "*"},
+            {"rtt": 12.0, "dup": 0},
+            {"rtt": 11.0, "dup": 1},
+            {"error": "sendto failed: Network is unreachable"}
+        ])
+    }
+
+    #[test]
+    fn counts_replies_and_losses() {
+        let ping = measurement(result());
+        assert_eq!(ping.rtts().collect::<Vec<_>>(), vec![10.0, 14.0, 12.0]);
+        assert_eq!(ping.lost(), 2);
+        assert_eq!(ping.loss_ratio(), Some(0.4));
+
+        let lost = measurement(json!([{"x": "*"}, {"x": "*"}, {"x": "*"}]));
+        assert_eq!((lost.lost(), lost.loss_ratio()), (3, Some(1.0)));
+        assert_eq!(lost.mean_rtt(), None);
+
+        let empty = measurement(json!([]));
+        assert_eq!((empty.lost(), empty.loss_ratio()), (0, None));
+    }
+
+    #[test]
+    fn computes_rtt_statistics() {
+        let ping = measurement(result());
+        assert_eq!(ping.mean_rtt(), Some(12.0));
+        assert_eq!(ping.median_rtt(), Some(12.0));
+        assert_eq!(ping.percentile_rtt(0.0), Some(10.0));
+        assert_eq!(ping.percentile_rtt(100.0), Some(14.0));
+        assert!((ping.percentile_rtt(90.0).unwrap() - 13.6).abs() < 1e-12);
+        assert_eq!(ping.stddev_rtt(), Some(2.0));
+
+        // |14 - 10| / 16, then moving 1/16 of the way towards |12 - 14|
+        assert_eq!(ping.jitter(), Some(0.359375));
+    }
+
+    #[test]
+    fn needs_enough_replies() {
+        let single = measurement(json!([{"x": "*"}, {"rtt": 25.5}, {"x": "*"}]));
+        assert_eq!(single.median_rtt(), Some(25.5));
+        assert_eq!(single.stddev_rtt(), None);
+        assert_eq!(single.jitter(), None);
+
+        let empty = measurement(json!([]));
+        assert_eq!(empty.mean_rtt(), None);
+        assert_eq!(empty.median_rtt(), None);
+        assert_eq!(empty.stddev_rtt(), None);
+        assert_eq!(empty.jitter(), None);
+    }
+}
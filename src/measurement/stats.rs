@@ -0,0 +1,15 @@
+//! Statistics helpers shared by the analysis of the different measurement types.
+
+/// Linear interpolation between the closest ranks of a sorted, non-empty slice. `fraction` is
+/// between 0 and 1.
+pub(crate) fn percentile(sorted: &[f64], fraction: f64) -> f64 {
+    let rank = fraction.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+/// Median of a non-empty slice. The values are sorted in place.
+pub(crate) fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(f64::total_cmp);
+    percentile(values, 0.5)
+}
@@ -0,0 +1,521 @@
+//! Decoding of the X.509 certificate chains reported by sslcert measurements.
+use crate::general::{from_unix_seconds, unix_seconds, UnixTimestamp};
+use crate::measurement::tls::Tls;
+use ring::digest;
+use std::fmt::{self, Display, Formatter};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use x509_parser::certificate::X509Certificate;
+use x509_parser::error::{PEMError, X509Error};
+use x509_parser::extensions::GeneralName;
+use x509_parser::nom;
+use x509_parser::objects::{oid2sn, oid_registry};
+use x509_parser::oid_registry::{
+    Oid, OID_KEY_TYPE_DSA, OID_KEY_TYPE_EC_PUBLIC_KEY, OID_PKCS1_RSAENCRYPTION,
+    OID_PKCS1_RSASSAPSS, OID_SIG_ED25519, OID_SIG_ED448,
+};
+use x509_parser::pem::parse_x509_pem;
+use x509_parser::prelude::FromDer;
+use x509_parser::public_key::PublicKey;
+
+#[derive(Debug)]
+pub enum CertificateError {
+    /// The certificate was not valid PEM
+    Pem(PEMError),
+    /// The certificate could not be parsed as DER encoded X.509
+    X509(X509Error),
+}
+
+impl Display for CertificateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CertificateError::Pem(err) => write!(f, "invalid PEM certificate: {}", err),
+            CertificateError::X509(err) => write!(f, "invalid X.509 certificate: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for CertificateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CertificateError::Pem(err) => Some(err),
+            CertificateError::X509(err) => Some(err),
+        }
+    }
+}
+
+impl From<nom::Err<PEMError>> for CertificateError {
+    fn from(err: nom::Err<PEMError>) -> Self {
+        match err {
+            nom::Err::Error(err) | nom::Err::Failure(err) => CertificateError::Pem(err),
+            nom::Err::Incomplete(_) => CertificateError::Pem(PEMError::IncompletePEM),
+        }
+    }
+}
+
+impl From<nom::Err<X509Error>> for CertificateError {
+    fn from(err: nom::Err<X509Error>) -> Self {
+        match err {
+            nom::Err::Error(err) | nom::Err::Failure(err) => CertificateError::X509(err),
+            nom::Err::Incomplete(_) => CertificateError::X509(X509Error::InvalidCertificate),
+        }
+    }
+}
+
+impl<'a> Tls<'a> {
+    /// Decode the certificates sent by the server. The first certificate is the one presented by
+    /// the server, and each following certificate should be the issuer of the one before it.
+    pub fn decode_certificates(&self) -> Result<Vec<Certificate>, CertificateError> {
+        self.cert
+            .iter()
+            .flatten()
+            .map(|pem| Certificate::from_pem(pem))
+            .collect()
+    }
+}
+
+/// The parts of an X.509 certificate relevant to monitoring. The original DER encoding is kept so
+/// signatures can still be checked.
+#[derive(Clone, Debug)]
+pub struct Certificate {
+    der: Vec<u8>,
+    /// distinguished name of the subject in the form "C=US, O=Example, CN=example.com"
+    pub subject: String,
+    /// distinguished name of the issuer
+    pub issuer: String,
+    /// first common name of the subject
+    pub common_name: Option<String>,
+    /// serial number as colon separated hex bytes
+    pub serial: String,
+    pub subject_alt_names: Vec<SubjectAltName>,
+    pub not_before: UnixTimestamp,
+    pub not_after: UnixTimestamp,
+    pub key_type: KeyType,
+    /// public key size in bits
+    pub key_size: usize,
+    /// short name of the signature algorithm such as "sha256WithRSAEncryption", or the dotted OID
+    /// if it is not known
+    pub signature_algorithm: String,
+    /// SHA-256 hash of the DER encoded certificate
+    pub fingerprint: [u8; 32],
+    /// the basic constraints extension marks this certificate as a CA
+    pub is_ca: bool,
+}
+
+impl Certificate {
+    pub fn from_pem(pem: &str) -> Result<Self, CertificateError> {
+        let (_, pem) = parse_x509_pem(pem.trim().as_bytes())?;
+        Certificate::from_der(&pem.contents)
+    }
+
+    pub fn from_der(der: &[u8]) -> Result<Self, CertificateError> {
+        let (_, cert) = X509Certificate::from_der(der)?;
+
+        let subject_alt_names = match cert.subject_alternative_name() {
+            Ok(Some(extension)) => extension
+                .value
+                .general_names
+                .iter()
+                .filter_map(SubjectAltName::from_general_name)
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let spki = cert.public_key();
+        let key_type = KeyType::from_algorithm(
+            &spki.algorithm.algorithm,
+            spki.algorithm
+                .parameters
+                .as_ref()
+                .and_then(|params| params.as_oid().ok()),
+        );
+
+        let key_size = match spki.parsed() {
+            Ok(PublicKey::RSA(rsa)) => significant_bits(rsa.modulus),
+            Ok(key) if key.key_size() > 0 => key.key_size(),
+            _ => match key_type {
+                KeyType::Ed25519 => 256,
+                KeyType::Ed448 => 456,
+                _ => 0,
+            },
+        };
+
+        let common_name = cert
+            .subject()
+            .iter_common_name()
+            .next()
+            .and_then(|cn| cn.as_str().ok())
+            .map(str::to_string);
+
+        let mut fingerprint = [0; 32];
+        fingerprint.copy_from_slice(digest::digest(&digest::SHA256, der).as_ref());
+
+        let validity = cert.validity();
+        let not_before = from_unix_seconds(validity.not_before.timestamp());
+        let not_after = from_unix_seconds(validity.not_after.timestamp());
+        let (not_before, not_after) = match (not_before, not_after) {
+            (Some(not_before), Some(not_after)) => (not_before, not_after),
+            _ => return Err(CertificateError::X509(X509Error::InvalidDate)),
+        };
+
+        Ok(Certificate {
+            der: der.to_vec(),
+            subject: cert.subject().to_string(),
+            issuer: cert.issuer().to_string(),
+            common_name,
+            serial: cert.raw_serial_as_string(),
+            subject_alt_names,
+            not_before,
+            not_after,
+            key_type,
+            key_size,
+            signature_algorithm: oid_name(&cert.signature_algorithm.algorithm),
+            fingerprint,
+            is_ca: cert.is_ca(),
+        })
+    }
+
+    /// The DER encoding the certificate was decoded from
+    pub fn der(&self) -> &[u8] {
+        &self.der
+    }
+
+    /// SHA-256 fingerprint as lowercase hex
+    pub fn fingerprint_hex(&self) -> String {
+        self.fingerprint
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    pub fn is_valid_at(&self, time: UnixTimestamp) -> bool {
+        let time = unix_seconds(time);
+        unix_seconds(self.not_before) <= time && time <= unix_seconds(self.not_after)
+    }
+
+    /// Check if the certificate is valid for a hostname or IP address using the rules from RFC 6125
+    /// 6.4. The common name is only checked if there are no DNS subject alternative names.
+    pub fn matches_hostname(&self, hostname: &str) -> bool {
+        if let Ok(ip) = hostname.parse::<IpAddr>() {
+            return self.subject_alt_names.contains(&SubjectAltName::Ip(ip));
+        }
+
+        let hostname = hostname.trim_end_matches('.');
+        let mut dns_names = self
+            .subject_alt_names
+            .iter()
+            .filter_map(|name| match name {
+                SubjectAltName::Dns(name) => Some(name.as_str()),
+                _ => None,
+            })
+            .peekable();
+
+        if dns_names.peek().is_none() {
+            return self
+                .common_name
+                .as_deref()
+                .is_some_and(|name| matches_pattern(name, hostname));
+        }
+
+        dns_names.any(|pattern| matches_pattern(pattern, hostname))
+    }
+
+    pub fn is_self_issued(&self) -> bool {
+        self.subject == self.issuer
+    }
+
+    /// Check if this certificate was issued and signed by another certificate.
+    pub fn check_issued_by(&self, issuer: &Certificate) -> ChainLink {
+        if self.issuer != issuer.subject {
+            return ChainLink::IssuerMismatch;
+        }
+
+        // Both were already parsed successfully once and the encoding can not be changed since
+        let (cert, issuer) = match (
+            X509Certificate::from_der(&self.der),
+            X509Certificate::from_der(&issuer.der),
+        ) {
+            (Ok((_, cert)), Ok((_, issuer))) => (cert, issuer),
+            _ => return ChainLink::InvalidSignature,
+        };
+
+        match cert.verify_signature(Some(issuer.public_key())) {
+            Ok(()) => ChainLink::Valid,
+            Err(X509Error::SignatureUnsupportedAlgorithm) => ChainLink::UnsupportedAlgorithm,
+            Err(_) => ChainLink::InvalidSignature,
+        }
+    }
+}
+
+/// Check that each certificate in a chain was issued by the certificate following it. The result
+/// contains one entry for each adjacent pair of certificates.
+pub fn check_chain(chain: &[Certificate]) -> Vec<ChainLink> {
+    chain
+        .windows(2)
+        .map(|pair| pair[0].check_issued_by(&pair[1]))
+        .collect()
+}
+
+/// Relationship between a certificate and the next certificate in the chain
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum ChainLink {
+    /// The next certificate issued and signed this certificate
+    Valid,
+    /// The issuer of this certificate is not the subject of the next certificate
+    IssuerMismatch,
+    /// The signature on this certificate was not made by the next certificate's key
+    InvalidSignature,
+    /// The signature algorithm is not supported, so the signature could not be checked
+    UnsupportedAlgorithm,
+}
+
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+pub enum SubjectAltName {
+    Dns(String),
+    Ip(IpAddr),
+    Email(String),
+    Uri(String),
+}
+
+impl SubjectAltName {
+    fn from_general_name(name: &GeneralName) -> Option<Self> {
+        Some(match name {
+            GeneralName::DNSName(name) => SubjectAltName::Dns(name.to_string()),
+            GeneralName::RFC822Name(email) => SubjectAltName::Email(email.to_string()),
+            GeneralName::URI(uri) => SubjectAltName::Uri(uri.to_string()),
+            GeneralName::IPAddress(bytes) => match bytes.len() {
+                4 => SubjectAltName::Ip(IpAddr::V4(Ipv4Addr::from(
+                    <[u8; 4]>::try_from(*bytes).ok()?,
+                ))),
+                16 => SubjectAltName::Ip(IpAddr::V6(Ipv6Addr::from(
+                    <[u8; 16]>::try_from(*bytes).ok()?,
+                ))),
+                _ => return None,
+            },
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+pub enum KeyType {
+    Rsa,
+    RsaPss,
+    /// Elliptic curve key using the named curve, such as "prime256v1" or "secp384r1"
+    Ec {
+        curve: Option<String>,
+    },
+    Ed25519,
+    Ed448,
+    Dsa,
+    /// Any other key type given by its algorithm name or OID
+    Other(String),
+}
+
+impl KeyType {
+    fn from_algorithm(algorithm: &Oid, parameters: Option<Oid>) -> Self {
+        if *algorithm == OID_PKCS1_RSAENCRYPTION {
+            KeyType::Rsa
+        } else if *algorithm == OID_PKCS1_RSASSAPSS {
+            KeyType::RsaPss
+        } else if *algorithm == OID_KEY_TYPE_EC_PUBLIC_KEY {
+            KeyType::Ec {
+                curve: parameters.as_ref().map(oid_name),
+            }
+        } else if *algorithm == OID_SIG_ED25519 {
+            KeyType::Ed25519
+        } else if *algorithm == OID_SIG_ED448 {
+            KeyType::Ed448
+        } else if *algorithm == OID_KEY_TYPE_DSA {
+            KeyType::Dsa
+        } else {
+            KeyType::Other(oid_name(algorithm))
+        }
+    }
+}
+
+/// Match a hostname against a name from a certificate. Wildcards are only allowed as the complete
+/// left-most label and match exactly one label.
+fn matches_pattern(pattern: &str, hostname: &str) -> bool {
+    let pattern = pattern.trim_end_matches('.');
+
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => match hostname.split_once('.') {
+            Some((label, rest)) => !label.is_empty() && rest.eq_ignore_ascii_case(suffix),
+            None => false,
+        },
+        None => pattern.eq_ignore_ascii_case(hostname),
+    }
+}
+
+fn oid_name(oid: &Oid) -> String {
+    match oid2sn(oid, oid_registry()) {
+        Ok(name) => name.to_string(),
+        Err(_) => oid.to_id_string(),
+    }
+}
+
+/// Number of bits in a big endian integer after removing any leading zeros
+fn significant_bits(value: &[u8]) -> usize {
+    match value.iter().position(|&byte| byte != 0) {
+        Some(index) => (value.len() - index) * 8 - value[index].leading_zeros() as usize,
+        None => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::general::from_unix_seconds;
+    use crate::measurement::TlsMeasurement;
+
+    /// RSA 2048 certificate for www.example.com issued by `ROOT` and valid from 2022-11-01 until
+    /// 2023-11-01
+    const LEAF: &str = include_str!("testdata/leaf.pem");
+    /// Self-signed P-384 CA
+    const ROOT: &str = include_str!("testdata/root.pem");
+    /// CA with the same name as `ROOT`, but a different key
+    const IMPOSTOR: &str = include_str!("testdata/impostor.pem");
+    const ED25519: &str = include_str!("testdata/ed25519.pem");
+
+    fn measurement(certs: &[&str]) -> String {
+        serde_json::json!({
+            "af": 4, "cert": certs, "dst_addr": "192.0.2.1", "dst_name": "www.example.com",
+            "dst_port": "443", "from": "203.0.113.5", "fw": 5080, "method": "TLS",
+            "msm_id": 40001, "msm_name": "SSLCert", "prb_id": 6001, "rt": 80.5,
+            "server_cipher": "C02F", "src_addr": "192.168.1.10", "timestamp": 1669852800,
+            "ttc": 40.2, "type": "sslcert", "ver": "1.2"
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn decodes_chain() {
+        let json = measurement(&[LEAF, ROOT]);
+        let measurement: TlsMeasurement = serde_json::from_str(&json).unwrap();
+        let chain = measurement.decode_certificates().unwrap();
+        assert_eq!(chain.len(), 2);
+
+        let leaf = &chain[0];
+        assert_eq!(leaf.subject, "C=NL, O=Example, CN=www.example.com");
+        assert_eq!(leaf.issuer, "C=NL, O=Example, CN=Example Root CA");
+        assert_eq!(leaf.common_name.as_deref(), Some("www.example.com"));
+        assert_eq!(leaf.serial, "0a:0b:0c");
+        assert_eq!(
+            leaf.subject_alt_names,
+            vec![
+                SubjectAltName::Dns("www.example.com".to_string()),
+                SubjectAltName::Dns("*.example.net".to_string()),
+                SubjectAltName::Ip("192.0.2.1".parse().unwrap()),
+            ]
+        );
+        assert_eq!(unix_seconds(leaf.not_before), 1667260800);
+        assert_eq!(unix_seconds(leaf.not_after), 1698796800);
+        assert_eq!((&leaf.key_type, leaf.key_size), (&KeyType::Rsa, 2048));
+        assert_eq!(leaf.signature_algorithm, "ecdsa-with-SHA256");
+        assert_eq!(
+            leaf.fingerprint_hex(),
+            "1994a017538e70b530ecb674e738b29036132487cb75902956b5f1006db9f917"
+        );
+        assert!(!leaf.is_ca);
+        assert!(!leaf.is_self_issued());
+        assert_eq!(
+            Certificate::from_der(leaf.der()).unwrap().fingerprint,
+            leaf.fingerprint
+        );
+
+        let root = &chain[1];
+        assert_eq!(
+            root.key_type,
+            KeyType::Ec {
+                curve: Some("secp384r1".to_string())
+            }
+        );
+        assert_eq!(root.key_size, 384);
+        assert!(root.is_ca);
+        assert!(root.is_self_issued());
+        assert_eq!(check_chain(&chain), vec![ChainLink::Valid]);
+    }
+
+    #[test]
+    fn checks_issuers() {
+        let leaf = Certificate::from_pem(LEAF).unwrap();
+        let root = Certificate::from_pem(ROOT).unwrap();
+        let impostor = Certificate::from_pem(IMPOSTOR).unwrap();
+
+        assert_eq!(leaf.check_issued_by(&root), ChainLink::Valid);
+        assert_eq!(root.check_issued_by(&root), ChainLink::Valid);
+        assert_eq!(root.check_issued_by(&leaf), ChainLink::IssuerMismatch);
+        assert_eq!(leaf.check_issued_by(&impostor), ChainLink::InvalidSignature);
+        assert_eq!(
+            check_chain(&[leaf.clone(), impostor, root]),
+            vec![ChainLink::InvalidSignature, ChainLink::InvalidSignature]
+        );
+        assert!(check_chain(&[leaf]).is_empty());
+    }
+
+    #[test]
+    fn matches_hostnames() {
+        let leaf = Certificate::from_pem(LEAF).unwrap();
+
+        assert!(leaf.matches_hostname("www.example.com"));
+        assert!(leaf.matches_hostname("WWW.Example.com."));
+        assert!(leaf.matches_hostname("cdn.example.net"));
+        assert!(leaf.matches_hostname("192.0.2.1"));
+        assert!(!leaf.matches_hostname("example.net"));
+        assert!(!leaf.matches_hostname("a.cdn.example.net"));
+        assert!(!leaf.matches_hostname("example.com"));
+        assert!(!leaf.matches_hostname("192.0.2.2"));
+
+        // Without DNS names the common name is used
+        let ed25519 = Certificate::from_pem(ED25519).unwrap();
+        assert!(ed25519.subject_alt_names.is_empty());
+        assert!(ed25519.matches_hostname("ed.example.org"));
+        assert!(!ed25519.matches_hostname("www.ed.example.org"));
+    }
+
+    #[test]
+    fn checks_validity() {
+        let leaf = Certificate::from_pem(LEAF).unwrap();
+        let at = |seconds| from_unix_seconds(seconds).unwrap();
+
+        assert!(leaf.is_valid_at(at(1667260800)));
+        assert!(leaf.is_valid_at(at(1698796800)));
+        assert!(!leaf.is_valid_at(at(1667260799)));
+        assert!(!leaf.is_valid_at(at(1698796801)));
+    }
+
+    #[test]
+    fn decodes_ed25519_keys() {
+        let cert = Certificate::from_pem(ED25519).unwrap();
+        assert_eq!((&cert.key_type, cert.key_size), (&KeyType::Ed25519, 256));
+        assert_eq!(cert.signature_algorithm, "ed25519");
+        assert_eq!(cert.check_issued_by(&cert), ChainLink::Valid);
+    }
+
+    #[test]
+    fn rejects_invalid_certificates() {
+        assert!(matches!(
+            Certificate::from_pem("not a certificate"),
+            Err(CertificateError::Pem(_))
+        ));
+        assert!(matches!(
+            Certificate::from_der(&[0x30, 0x03, 0x02, 0x01, 0x01]),
+            Err(CertificateError::X509(_))
+        ));
+
+        let json = measurement(&[
+            LEAF,
+            "-----BEGIN CERTIFICATE-----\nAAAA\n-----END CERTIFICATE-----",
+        ]);
+        let measurement: TlsMeasurement = serde_json::from_str(&json).unwrap();
+        assert!(measurement.decode_certificates().is_err());
+
+        // Servers which sent an alert have no certificates
+        let json = r#"{"af":4,"alert":{"level":2,"description":40},"dst_addr":"192.0.2.1",
+            "dst_name":"www.example.com","dst_port":"443","from":"203.0.113.5","fw":5080,
+            "method":"TLS","msm_id":40001,"msm_name":"SSLCert","prb_id":6001,
+            "timestamp":1669852800,"type":"sslcert","ver":"1.2"}"#;
+        let measurement: TlsMeasurement = serde_json::from_str(json).unwrap();
+        assert!(measurement.decode_certificates().unwrap().is_empty());
+    }
+}
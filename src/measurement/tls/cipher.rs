@@ -0,0 +1,865 @@
+//! Decoding of the negotiated cipher suite and protocol version using the names from the IANA TLS
+//! Cipher Suites registry.
+use crate::measurement::tls::{Method, Tls};
+use std::fmt::{self, Display, Formatter};
+
+impl<'a> Tls<'a> {
+    /// The cipher suite selected by the server. Returns `None` if `server_cipher` is missing or is
+    /// not a valid hexadecimal number.
+    pub fn cipher_suite(&self) -> Option<CipherSuite> {
+        let hex = self.server_cipher.as_deref()?.trim();
+        let hex = hex
+            .strip_prefix("0x")
+            .or_else(|| hex.strip_prefix("0X"))
+            .unwrap_or(hex);
+
+        u16::from_str_radix(hex, 16).ok().map(CipherSuite)
+    }
+
+    /// The negotiated protocol version. `ver` is usually only the version number, so `method` is
+    /// used to tell "3.0" for SSL apart from the TLS record version "3.1" to "3.4".
+    pub fn protocol_version(&self) -> Option<ProtocolVersion> {
+        let ver = self.ver.as_deref()?.trim();
+        let lower = ver.to_ascii_lowercase();
+
+        if let Some(number) = lower.strip_prefix("sslv").or(lower.strip_prefix("ssl")) {
+            return ProtocolVersion::from_ssl_number(number.trim());
+        } else if let Some(number) = lower.strip_prefix("tlsv").or(lower.strip_prefix("tls")) {
+            return ProtocolVersion::from_tls_number(number.trim());
+        }
+
+        match self.method {
+            Some(Method::SSL) => ProtocolVersion::from_ssl_number(&lower),
+            _ => ProtocolVersion::from_tls_number(&lower),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub enum ProtocolVersion {
+    Ssl2,
+    Ssl3,
+    Tls10,
+    Tls11,
+    Tls12,
+    Tls13,
+}
+
+impl ProtocolVersion {
+    /// Decode the version used on the wire in the record layer, such as 0x0303 for TLS 1.2
+    pub fn from_wire(version: u16) -> Option<Self> {
+        Some(match version {
+            0x0002 => ProtocolVersion::Ssl2,
+            0x0300 => ProtocolVersion::Ssl3,
+            0x0301 => ProtocolVersion::Tls10,
+            0x0302 => ProtocolVersion::Tls11,
+            0x0303 => ProtocolVersion::Tls12,
+            0x0304 => ProtocolVersion::Tls13,
+            _ => return None,
+        })
+    }
+
+    fn from_ssl_number(number: &str) -> Option<Self> {
+        match number {
+            "2" | "2.0" => Some(ProtocolVersion::Ssl2),
+            "3" | "3.0" => Some(ProtocolVersion::Ssl3),
+            _ => None,
+        }
+    }
+
+    fn from_tls_number(number: &str) -> Option<Self> {
+        match number {
+            "1" | "1.0" | "3.1" => Some(ProtocolVersion::Tls10),
+            "1.1" | "3.2" => Some(ProtocolVersion::Tls11),
+            "1.2" | "3.3" => Some(ProtocolVersion::Tls12),
+            "1.3" | "3.4" => Some(ProtocolVersion::Tls13),
+            "3.0" => Some(ProtocolVersion::Ssl3),
+            _ => None,
+        }
+    }
+
+    /// SSL 2.0 (RFC 6176), SSL 3.0 (RFC 7568) and TLS 1.0 and 1.1 (RFC 8996) must no longer be
+    /// negotiated.
+    pub fn is_deprecated(&self) -> bool {
+        *self < ProtocolVersion::Tls12
+    }
+}
+
+impl Display for ProtocolVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolVersion::Ssl2 => write!(f, "SSLv2"),
+            ProtocolVersion::Ssl3 => write!(f, "SSLv3"),
+            ProtocolVersion::Tls10 => write!(f, "TLSv1.0"),
+            ProtocolVersion::Tls11 => write!(f, "TLSv1.1"),
+            ProtocolVersion::Tls12 => write!(f, "TLSv1.2"),
+            ProtocolVersion::Tls13 => write!(f, "TLSv1.3"),
+        }
+    }
+}
+
+/// A cipher suite identified by its two byte code. The name and components of the suite are only
+/// known for suites listed in the IANA registry which were defined for general use.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct CipherSuite(pub u16);
+
+impl CipherSuite {
+    /// Name from the IANA registry such as "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256"
+    pub fn name(&self) -> Option<&'static str> {
+        CIPHER_SUITES
+            .binary_search_by_key(&self.0, |(code, _)| *code)
+            .ok()
+            .map(|index| CIPHER_SUITES[index].1)
+    }
+
+    /// Split the name into the key exchange, bulk cipher and hash parts
+    fn parts(&self) -> Option<(Option<&'static str>, &'static str, Option<&'static str>)> {
+        let name = self.name()?.strip_prefix("TLS_")?;
+        if name.ends_with("_SCSV") {
+            return None;
+        }
+
+        let (key_exchange, rest) = match name.split_once("_WITH_") {
+            Some((key_exchange, rest)) => (Some(key_exchange), rest),
+            None => (None, name),
+        };
+
+        for hash in ["_NULL", "_MD5", "_SHA", "_SHA256", "_SHA384"] {
+            if let Some(cipher) = rest.strip_suffix(hash) {
+                return Some((key_exchange, cipher, Some(&hash[1..])));
+            }
+        }
+
+        Some((key_exchange, rest, None))
+    }
+
+    /// Key exchange and authentication. This is `None` for TLS 1.3 suites since both are
+    /// negotiated separately from the cipher suite in TLS 1.3.
+    pub fn key_exchange(&self) -> Option<(KeyExchange, Authentication)> {
+        let key_exchange = self.parts()?.0?;
+        let key_exchange = key_exchange.strip_suffix("_EXPORT").unwrap_or(key_exchange);
+
+        Some(match key_exchange {
+            "NULL" => (KeyExchange::Null, Authentication::Null),
+            "RSA" => (KeyExchange::Rsa, Authentication::Rsa),
+            "RSA_PSK" => (KeyExchange::Rsa, Authentication::Psk),
+            "DH_DSS" => (KeyExchange::Dh, Authentication::Dss),
+            "DH_RSA" => (KeyExchange::Dh, Authentication::Rsa),
+            "DH_anon" => (KeyExchange::Dhe, Authentication::Anonymous),
+            "DHE_DSS" => (KeyExchange::Dhe, Authentication::Dss),
+            "DHE_RSA" => (KeyExchange::Dhe, Authentication::Rsa),
+            "DHE_PSK" | "PSK_DHE" => (KeyExchange::Dhe, Authentication::Psk),
+            "ECDH_ECDSA" => (KeyExchange::Ecdh, Authentication::Ecdsa),
+            "ECDH_RSA" => (KeyExchange::Ecdh, Authentication::Rsa),
+            "ECDH_anon" => (KeyExchange::Ecdhe, Authentication::Anonymous),
+            "ECDHE_ECDSA" => (KeyExchange::Ecdhe, Authentication::Ecdsa),
+            "ECDHE_RSA" => (KeyExchange::Ecdhe, Authentication::Rsa),
+            "ECDHE_PSK" => (KeyExchange::Ecdhe, Authentication::Psk),
+            "PSK" => (KeyExchange::Psk, Authentication::Psk),
+            "KRB5" => (KeyExchange::Krb5, Authentication::Krb5),
+            "SRP_SHA" => (KeyExchange::Srp, Authentication::Srp),
+            "SRP_SHA_RSA" => (KeyExchange::Srp, Authentication::Rsa),
+            "SRP_SHA_DSS" => (KeyExchange::Srp, Authentication::Dss),
+            _ => return None,
+        })
+    }
+
+    pub fn cipher(&self) -> Option<BulkCipher> {
+        Some(match self.parts()?.1 {
+            "NULL" => BulkCipher::Null,
+            "RC2_CBC_40" => BulkCipher::Rc2Export,
+            "RC4_40" => BulkCipher::Rc4Export,
+            "RC4_128" => BulkCipher::Rc4,
+            "DES40_CBC" | "DES_CBC_40" => BulkCipher::DesExport,
+            "DES_CBC" => BulkCipher::Des,
+            "3DES_EDE_CBC" => BulkCipher::TripleDes,
+            "IDEA_CBC" => BulkCipher::Idea,
+            "SEED_CBC" => BulkCipher::Seed,
+            "AES_128_CBC" => BulkCipher::Aes128Cbc,
+            "AES_256_CBC" => BulkCipher::Aes256Cbc,
+            "AES_128_GCM" => BulkCipher::Aes128Gcm,
+            "AES_256_GCM" => BulkCipher::Aes256Gcm,
+            "AES_128_CCM" => BulkCipher::Aes128Ccm,
+            "AES_256_CCM" => BulkCipher::Aes256Ccm,
+            "AES_128_CCM_8" => BulkCipher::Aes128Ccm8,
+            "AES_256_CCM_8" => BulkCipher::Aes256Ccm8,
+            "CAMELLIA_128_CBC" => BulkCipher::Camellia128Cbc,
+            "CAMELLIA_256_CBC" => BulkCipher::Camellia256Cbc,
+            "ARIA_128_GCM" => BulkCipher::Aria128Gcm,
+            "ARIA_256_GCM" => BulkCipher::Aria256Gcm,
+            "CHACHA20_POLY1305" => BulkCipher::ChaCha20Poly1305,
+            _ => return None,
+        })
+    }
+
+    /// Hash used for the record MAC, or only for the PRF in the case of AEAD ciphers
+    pub fn hash(&self) -> Option<Hash> {
+        Some(match self.parts()?.2? {
+            "MD5" => Hash::Md5,
+            "SHA" => Hash::Sha1,
+            "SHA256" => Hash::Sha256,
+            "SHA384" => Hash::Sha384,
+            _ => return None,
+        })
+    }
+
+    /// The suite uses ephemeral keys so past sessions can not be decrypted with the server's
+    /// long term key. All TLS 1.3 suites provide forward secrecy.
+    pub fn forward_secrecy(&self) -> bool {
+        match self.key_exchange() {
+            Some((KeyExchange::Dhe | KeyExchange::Ecdhe, _)) => true,
+            Some(_) => false,
+            // TLS 1.3 suites from RFC 8446 B.4
+            None => matches!(self.0, 0x1301..=0x1305),
+        }
+    }
+
+    pub fn is_aead(&self) -> bool {
+        self.cipher().is_some_and(|cipher| cipher.is_aead())
+    }
+
+    /// Export grade suites intentionally limited to 40 or 56 bit keys
+    pub fn is_export(&self) -> bool {
+        self.name().is_some_and(|name| name.contains("_EXPORT"))
+    }
+
+    /// The suite has been deprecated or prohibited by an RFC. This covers NULL encryption,
+    /// anonymous key exchange and export suites (RFC 5246), RC4 (RFC 7465) and single DES and IDEA
+    /// (RFC 5469).
+    pub fn is_deprecated(&self) -> bool {
+        if self.is_export() {
+            return true;
+        }
+
+        if let Some((_, Authentication::Anonymous | Authentication::Null)) = self.key_exchange() {
+            return true;
+        }
+
+        matches!(
+            self.cipher(),
+            Some(
+                BulkCipher::Null
+                    | BulkCipher::Rc2Export
+                    | BulkCipher::Rc4Export
+                    | BulkCipher::Rc4
+                    | BulkCipher::DesExport
+                    | BulkCipher::Des
+                    | BulkCipher::Idea
+            )
+        )
+    }
+
+    /// Classify the suite for compliance reports. Returns `None` for suites not in the registry.
+    pub fn strength(&self) -> Option<Strength> {
+        self.cipher()?;
+
+        Some(if self.is_deprecated() || self.hash() == Some(Hash::Md5) {
+            Strength::Insecure
+        } else if self.forward_secrecy() && self.is_aead() {
+            Strength::Strong
+        } else {
+            Strength::Weak
+        })
+    }
+}
+
+impl Display for CipherSuite {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "0x{:04X}", self.0),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum KeyExchange {
+    Null,
+    Rsa,
+    /// static Diffie-Hellman
+    Dh,
+    /// ephemeral Diffie-Hellman
+    Dhe,
+    /// static elliptic curve Diffie-Hellman
+    Ecdh,
+    /// ephemeral elliptic curve Diffie-Hellman
+    Ecdhe,
+    Psk,
+    Krb5,
+    Srp,
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum Authentication {
+    Null,
+    Anonymous,
+    Rsa,
+    Dss,
+    Ecdsa,
+    Psk,
+    Krb5,
+    Srp,
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum BulkCipher {
+    Null,
+    /// RC2 with a 40 bit key
+    Rc2Export,
+    /// RC4 with a 40 bit key
+    Rc4Export,
+    Rc4,
+    /// DES with a 40 bit key
+    DesExport,
+    Des,
+    TripleDes,
+    Idea,
+    Seed,
+    Aes128Cbc,
+    Aes256Cbc,
+    Aes128Gcm,
+    Aes256Gcm,
+    Aes128Ccm,
+    Aes256Ccm,
+    /// AES-CCM with an 8 octet authentication tag
+    Aes128Ccm8,
+    /// AES-CCM with an 8 octet authentication tag
+    Aes256Ccm8,
+    Camellia128Cbc,
+    Camellia256Cbc,
+    Aria128Gcm,
+    Aria256Gcm,
+    ChaCha20Poly1305,
+}
+
+impl BulkCipher {
+    /// Authenticated encryption with associated data
+    pub fn is_aead(&self) -> bool {
+        matches!(
+            self,
+            BulkCipher::Aes128Gcm
+                | BulkCipher::Aes256Gcm
+                | BulkCipher::Aes128Ccm
+                | BulkCipher::Aes256Ccm
+                | BulkCipher::Aes128Ccm8
+                | BulkCipher::Aes256Ccm8
+                | BulkCipher::Aria128Gcm
+                | BulkCipher::Aria256Gcm
+                | BulkCipher::ChaCha20Poly1305
+        )
+    }
+
+    /// Effective key size in bits
+    pub fn key_bits(&self) -> u16 {
+        match self {
+            BulkCipher::Null => 0,
+            BulkCipher::Rc2Export | BulkCipher::Rc4Export | BulkCipher::DesExport => 40,
+            BulkCipher::Des => 56,
+            BulkCipher::TripleDes => 112,
+            BulkCipher::Rc4
+            | BulkCipher::Idea
+            | BulkCipher::Seed
+            | BulkCipher::Aes128Cbc
+            | BulkCipher::Aes128Gcm
+            | BulkCipher::Aes128Ccm
+            | BulkCipher::Aes128Ccm8
+            | BulkCipher::Camellia128Cbc
+            | BulkCipher::Aria128Gcm => 128,
+            BulkCipher::Aes256Cbc
+            | BulkCipher::Aes256Gcm
+            | BulkCipher::Aes256Ccm
+            | BulkCipher::Aes256Ccm8
+            | BulkCipher::Camellia256Cbc
+            | BulkCipher::Aria256Gcm
+            | BulkCipher::ChaCha20Poly1305 => 256,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum Hash {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha384,
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Strength {
+    /// Deprecated suites or suites using broken primitives
+    Insecure,
+    /// Suites without forward secrecy or without authenticated encryption
+    Weak,
+    /// Suites with both forward secrecy and authenticated encryption
+    Strong,
+}
+
+impl Display for Strength {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Strength::Insecure => write!(f, "insecure"),
+            Strength::Weak => write!(f, "weak"),
+            Strength::Strong => write!(f, "strong"),
+        }
+    }
+}
+
+/// Cipher suites from the IANA TLS Cipher Suites registry, sorted by code
+const CIPHER_SUITES: &[(u16, &str)] = &[
+    (0x0000, "TLS_NULL_WITH_NULL_NULL"),
+    (0x0001, "TLS_RSA_WITH_NULL_MD5"),
+    (0x0002, "TLS_RSA_WITH_NULL_SHA"),
+    (0x0003, "TLS_RSA_EXPORT_WITH_RC4_40_MD5"),
+    (0x0004, "TLS_RSA_WITH_RC4_128_MD5"),
+    (0x0005, "TLS_RSA_WITH_RC4_128_SHA"),
+    (0x0006, "TLS_RSA_EXPORT_WITH_RC2_CBC_40_MD5"),
+    (0x0007, "TLS_RSA_WITH_IDEA_CBC_SHA"),
+    (0x0008, "TLS_RSA_EXPORT_WITH_DES40_CBC_SHA"),
+    (0x0009, "TLS_RSA_WITH_DES_CBC_SHA"),
+    (0x000A, "TLS_RSA_WITH_3DES_EDE_CBC_SHA"),
+    (0x000B, "TLS_DH_DSS_EXPORT_WITH_DES40_CBC_SHA"),
+    (0x000C, "TLS_DH_DSS_WITH_DES_CBC_SHA"),
+    (0x000D, "TLS_DH_DSS_WITH_3DES_EDE_CBC_SHA"),
+    (0x000E, "TLS_DH_RSA_EXPORT_WITH_DES40_CBC_SHA"),
+    (0x000F, "TLS_DH_RSA_WITH_DES_CBC_SHA"),
+    (0x0010, "TLS_DH_RSA_WITH_3DES_EDE_CBC_SHA"),
+    (0x0011, "TLS_DHE_DSS_EXPORT_WITH_DES40_CBC_SHA"),
+    (0x0012, "TLS_DHE_DSS_WITH_DES_CBC_SHA"),
+    (0x0013, "TLS_DHE_DSS_WITH_3DES_EDE_CBC_SHA"),
+    (0x0014, "TLS_DHE_RSA_EXPORT_WITH_DES40_CBC_SHA"),
+    (0x0015, "TLS_DHE_RSA_WITH_DES_CBC_SHA"),
+    (0x0016, "TLS_DHE_RSA_WITH_3DES_EDE_CBC_SHA"),
+    (0x0017, "TLS_DH_anon_EXPORT_WITH_RC4_40_MD5"),
+    (0x0018, "TLS_DH_anon_WITH_RC4_128_MD5"),
+    (0x0019, "TLS_DH_anon_EXPORT_WITH_DES40_CBC_SHA"),
+    (0x001A, "TLS_DH_anon_WITH_DES_CBC_SHA"),
+    (0x001B, "TLS_DH_anon_WITH_3DES_EDE_CBC_SHA"),
+    (0x001E, "TLS_KRB5_WITH_DES_CBC_SHA"),
+    (0x001F, "TLS_KRB5_WITH_3DES_EDE_CBC_SHA"),
+    (0x0020, "TLS_KRB5_WITH_RC4_128_SHA"),
+    (0x0021, "TLS_KRB5_WITH_IDEA_CBC_SHA"),
+    (0x0022, "TLS_KRB5_WITH_DES_CBC_MD5"),
+    (0x0023, "TLS_KRB5_WITH_3DES_EDE_CBC_MD5"),
+    (0x0024, "TLS_KRB5_WITH_RC4_128_MD5"),
+    (0x0025, "TLS_KRB5_WITH_IDEA_CBC_MD5"),
+    (0x0026, "TLS_KRB5_EXPORT_WITH_DES_CBC_40_SHA"),
+    (0x0027, "TLS_KRB5_EXPORT_WITH_RC2_CBC_40_SHA"),
+    (0x0028, "TLS_KRB5_EXPORT_WITH_RC4_40_SHA"),
+    (0x0029, "TLS_KRB5_EXPORT_WITH_DES_CBC_40_MD5"),
+    (0x002A, "TLS_KRB5_EXPORT_WITH_RC2_CBC_40_MD5"),
+    (0x002B, "TLS_KRB5_EXPORT_WITH_RC4_40_MD5"),
+    (0x002C, "TLS_PSK_WITH_NULL_SHA"),
+    (0x002D, "TLS_DHE_PSK_WITH_NULL_SHA"),
+    (0x002E, "TLS_RSA_PSK_WITH_NULL_SHA"),
+    (0x002F, "TLS_RSA_WITH_AES_128_CBC_SHA"),
+    (0x0030, "TLS_DH_DSS_WITH_AES_128_CBC_SHA"),
+    (0x0031, "TLS_DH_RSA_WITH_AES_128_CBC_SHA"),
+    (0x0032, "TLS_DHE_DSS_WITH_AES_128_CBC_SHA"),
+    (0x0033, "TLS_DHE_RSA_WITH_AES_128_CBC_SHA"),
+    (0x0034, "TLS_DH_anon_WITH_AES_128_CBC_SHA"),
+    (0x0035, "TLS_RSA_WITH_AES_256_CBC_SHA"),
+    (0x0036, "TLS_DH_DSS_WITH_AES_256_CBC_SHA"),
+    (0x0037, "TLS_DH_RSA_WITH_AES_256_CBC_SHA"),
+    (0x0038, "TLS_DHE_DSS_WITH_AES_256_CBC_SHA"),
+    (0x0039, "TLS_DHE_RSA_WITH_AES_256_CBC_SHA"),
+    (0x003A, "TLS_DH_anon_WITH_AES_256_CBC_SHA"),
+    (0x003B, "TLS_RSA_WITH_NULL_SHA256"),
+    (0x003C, "TLS_RSA_WITH_AES_128_CBC_SHA256"),
+    (0x003D, "TLS_RSA_WITH_AES_256_CBC_SHA256"),
+    (0x003E, "TLS_DH_DSS_WITH_AES_128_CBC_SHA256"),
+    (0x003F, "TLS_DH_RSA_WITH_AES_128_CBC_SHA256"),
+    (0x0040, "TLS_DHE_DSS_WITH_AES_128_CBC_SHA256"),
+    (0x0041, "TLS_RSA_WITH_CAMELLIA_128_CBC_SHA"),
+    (0x0042, "TLS_DH_DSS_WITH_CAMELLIA_128_CBC_SHA"),
+    (0x0043, "TLS_DH_RSA_WITH_CAMELLIA_128_CBC_SHA"),
+    (0x0044, "TLS_DHE_DSS_WITH_CAMELLIA_128_CBC_SHA"),
+    (0x0045, "TLS_DHE_RSA_WITH_CAMELLIA_128_CBC_SHA"),
+    (0x0046, "TLS_DH_anon_WITH_CAMELLIA_128_CBC_SHA"),
+    (0x0067, "TLS_DHE_RSA_WITH_AES_128_CBC_SHA256"),
+    (0x0068, "TLS_DH_DSS_WITH_AES_256_CBC_SHA256"),
+    (0x0069, "TLS_DH_RSA_WITH_AES_256_CBC_SHA256"),
+    (0x006A, "TLS_DHE_DSS_WITH_AES_256_CBC_SHA256"),
+    (0x006B, "TLS_DHE_RSA_WITH_AES_256_CBC_SHA256"),
+    (0x006C, "TLS_DH_anon_WITH_AES_128_CBC_SHA256"),
+    (0x006D, "TLS_DH_anon_WITH_AES_256_CBC_SHA256"),
+    (0x0084, "TLS_RSA_WITH_CAMELLIA_256_CBC_SHA"),
+    (0x0085, "TLS_DH_DSS_WITH_CAMELLIA_256_CBC_SHA"),
+    (0x0086, "TLS_DH_RSA_WITH_CAMELLIA_256_CBC_SHA"),
+    (0x0087, "TLS_DHE_DSS_WITH_CAMELLIA_256_CBC_SHA"),
+    (0x0088, "TLS_DHE_RSA_WITH_CAMELLIA_256_CBC_SHA"),
+    (0x0089, "TLS_DH_anon_WITH_CAMELLIA_256_CBC_SHA"),
+    (0x008A, "TLS_PSK_WITH_RC4_128_SHA"),
+    (0x008B, "TLS_PSK_WITH_3DES_EDE_CBC_SHA"),
+    (0x008C, "TLS_PSK_WITH_AES_128_CBC_SHA"),
+    (0x008D, "TLS_PSK_WITH_AES_256_CBC_SHA"),
+    (0x008E, "TLS_DHE_PSK_WITH_RC4_128_SHA"),
+    (0x008F, "TLS_DHE_PSK_WITH_3DES_EDE_CBC_SHA"),
+    (0x0090, "TLS_DHE_PSK_WITH_AES_128_CBC_SHA"),
+    (0x0091, "TLS_DHE_PSK_WITH_AES_256_CBC_SHA"),
+    (0x0092, "TLS_RSA_PSK_WITH_RC4_128_SHA"),
+    (0x0093, "TLS_RSA_PSK_WITH_3DES_EDE_CBC_SHA"),
+    (0x0094, "TLS_RSA_PSK_WITH_AES_128_CBC_SHA"),
+    (0x0095, "TLS_RSA_PSK_WITH_AES_256_CBC_SHA"),
+    (0x0096, "TLS_RSA_WITH_SEED_CBC_SHA"),
+    (0x0097, "TLS_DH_DSS_WITH_SEED_CBC_SHA"),
+    (0x0098, "TLS_DH_RSA_WITH_SEED_CBC_SHA"),
+    (0x0099, "TLS_DHE_DSS_WITH_SEED_CBC_SHA"),
+    (0x009A, "TLS_DHE_RSA_WITH_SEED_CBC_SHA"),
+    (0x009B, "TLS_DH_anon_WITH_SEED_CBC_SHA"),
+    (0x009C, "TLS_RSA_WITH_AES_128_GCM_SHA256"),
+    (0x009D, "TLS_RSA_WITH_AES_256_GCM_SHA384"),
+    (0x009E, "TLS_DHE_RSA_WITH_AES_128_GCM_SHA256"),
+    (0x009F, "TLS_DHE_RSA_WITH_AES_256_GCM_SHA384"),
+    (0x00A0, "TLS_DH_RSA_WITH_AES_128_GCM_SHA256"),
+    (0x00A1, "TLS_DH_RSA_WITH_AES_256_GCM_SHA384"),
+    (0x00A2, "TLS_DHE_DSS_WITH_AES_128_GCM_SHA256"),
+    (0x00A3, "TLS_DHE_DSS_WITH_AES_256_GCM_SHA384"),
+    (0x00A4, "TLS_DH_DSS_WITH_AES_128_GCM_SHA256"),
+    (0x00A5, "TLS_DH_DSS_WITH_AES_256_GCM_SHA384"),
+    (0x00A6, "TLS_DH_anon_WITH_AES_128_GCM_SHA256"),
+    (0x00A7, "TLS_DH_anon_WITH_AES_256_GCM_SHA384"),
+    (0x00A8, "TLS_PSK_WITH_AES_128_GCM_SHA256"),
+    (0x00A9, "TLS_PSK_WITH_AES_256_GCM_SHA384"),
+    (0x00AA, "TLS_DHE_PSK_WITH_AES_128_GCM_SHA256"),
+    (0x00AB, "TLS_DHE_PSK_WITH_AES_256_GCM_SHA384"),
+    (0x00AC, "TLS_RSA_PSK_WITH_AES_128_GCM_SHA256"),
+    (0x00AD, "TLS_RSA_PSK_WITH_AES_256_GCM_SHA384"),
+    (0x00AE, "TLS_PSK_WITH_AES_128_CBC_SHA256"),
+    (0x00AF, "TLS_PSK_WITH_AES_256_CBC_SHA384"),
+    (0x00B0, "TLS_PSK_WITH_NULL_SHA256"),
+    (0x00B1, "TLS_PSK_WITH_NULL_SHA384"),
+    (0x00B2, "TLS_DHE_PSK_WITH_AES_128_CBC_SHA256"),
+    (0x00B3, "TLS_DHE_PSK_WITH_AES_256_CBC_SHA384"),
+    (0x00B4, "TLS_DHE_PSK_WITH_NULL_SHA256"),
+    (0x00B5, "TLS_DHE_PSK_WITH_NULL_SHA384"),
+    (0x00B6, "TLS_RSA_PSK_WITH_AES_128_CBC_SHA256"),
+    (0x00B7, "TLS_RSA_PSK_WITH_AES_256_CBC_SHA384"),
+    (0x00B8, "TLS_RSA_PSK_WITH_NULL_SHA256"),
+    (0x00B9, "TLS_RSA_PSK_WITH_NULL_SHA384"),
+    (0x00BA, "TLS_RSA_WITH_CAMELLIA_128_CBC_SHA256"),
+    (0x00BB, "TLS_DH_DSS_WITH_CAMELLIA_128_CBC_SHA256"),
+    (0x00BC, "TLS_DH_RSA_WITH_CAMELLIA_128_CBC_SHA256"),
+    (0x00BD, "TLS_DHE_DSS_WITH_CAMELLIA_128_CBC_SHA256"),
+    (0x00BE, "TLS_DHE_RSA_WITH_CAMELLIA_128_CBC_SHA256"),
+    (0x00BF, "TLS_DH_anon_WITH_CAMELLIA_128_CBC_SHA256"),
+    (0x00C0, "TLS_RSA_WITH_CAMELLIA_256_CBC_SHA256"),
+    (0x00C1, "TLS_DH_DSS_WITH_CAMELLIA_256_CBC_SHA256"),
+    (0x00C2, "TLS_DH_RSA_WITH_CAMELLIA_256_CBC_SHA256"),
+    (0x00C3, "TLS_DHE_DSS_WITH_CAMELLIA_256_CBC_SHA256"),
+    (0x00C4, "TLS_DHE_RSA_WITH_CAMELLIA_256_CBC_SHA256"),
+    (0x00C5, "TLS_DH_anon_WITH_CAMELLIA_256_CBC_SHA256"),
+    (0x00FF, "TLS_EMPTY_RENEGOTIATION_INFO_SCSV"),
+    (0x1301, "TLS_AES_128_GCM_SHA256"),
+    (0x1302, "TLS_AES_256_GCM_SHA384"),
+    (0x1303, "TLS_CHACHA20_POLY1305_SHA256"),
+    (0x1304, "TLS_AES_128_CCM_SHA256"),
+    (0x1305, "TLS_AES_128_CCM_8_SHA256"),
+    (0x5600, "TLS_FALLBACK_SCSV"),
+    (0xC001, "TLS_ECDH_ECDSA_WITH_NULL_SHA"),
+    (0xC002, "TLS_ECDH_ECDSA_WITH_RC4_128_SHA"),
+    (0xC003, "TLS_ECDH_ECDSA_WITH_3DES_EDE_CBC_SHA"),
+    (0xC004, "TLS_ECDH_ECDSA_WITH_AES_128_CBC_SHA"),
+    (0xC005, "TLS_ECDH_ECDSA_WITH_AES_256_CBC_SHA"),
+    (0xC006, "TLS_ECDHE_ECDSA_WITH_NULL_SHA"),
+    (0xC007, "TLS_ECDHE_ECDSA_WITH_RC4_128_SHA"),
+    (0xC008, "TLS_ECDHE_ECDSA_WITH_3DES_EDE_CBC_SHA"),
+    (0xC009, "TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA"),
+    (0xC00A, "TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA"),
+    (0xC00B, "TLS_ECDH_RSA_WITH_NULL_SHA"),
+    (0xC00C, "TLS_ECDH_RSA_WITH_RC4_128_SHA"),
+    (0xC00D, "TLS_ECDH_RSA_WITH_3DES_EDE_CBC_SHA"),
+    (0xC00E, "TLS_ECDH_RSA_WITH_AES_128_CBC_SHA"),
+    (0xC00F, "TLS_ECDH_RSA_WITH_AES_256_CBC_SHA"),
+    (0xC010, "TLS_ECDHE_RSA_WITH_NULL_SHA"),
+    (0xC011, "TLS_ECDHE_RSA_WITH_RC4_128_SHA"),
+    (0xC012, "TLS_ECDHE_RSA_WITH_3DES_EDE_CBC_SHA"),
+    (0xC013, "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA"),
+    (0xC014, "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA"),
+    (0xC015, "TLS_ECDH_anon_WITH_NULL_SHA"),
+    (0xC016, "TLS_ECDH_anon_WITH_RC4_128_SHA"),
+    (0xC017, "TLS_ECDH_anon_WITH_3DES_EDE_CBC_SHA"),
+    (0xC018, "TLS_ECDH_anon_WITH_AES_128_CBC_SHA"),
+    (0xC019, "TLS_ECDH_anon_WITH_AES_256_CBC_SHA"),
+    (0xC01A, "TLS_SRP_SHA_WITH_3DES_EDE_CBC_SHA"),
+    (0xC01B, "TLS_SRP_SHA_RSA_WITH_3DES_EDE_CBC_SHA"),
+    (0xC01C, "TLS_SRP_SHA_DSS_WITH_3DES_EDE_CBC_SHA"),
+    (0xC01D, "TLS_SRP_SHA_WITH_AES_128_CBC_SHA"),
+    (0xC01E, "TLS_SRP_SHA_RSA_WITH_AES_128_CBC_SHA"),
+    (0xC01F, "TLS_SRP_SHA_DSS_WITH_AES_128_CBC_SHA"),
+    (0xC020, "TLS_SRP_SHA_WITH_AES_256_CBC_SHA"),
+    (0xC021, "TLS_SRP_SHA_RSA_WITH_AES_256_CBC_SHA"),
+    (0xC022, "TLS_SRP_SHA_DSS_WITH_AES_256_CBC_SHA"),
+    (0xC023, "TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA256"),
+    (0xC024, "TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA384"),
+    (0xC025, "TLS_ECDH_ECDSA_WITH_AES_128_CBC_SHA256"),
+    (0xC026, "TLS_ECDH_ECDSA_WITH_AES_256_CBC_SHA384"),
+    (0xC027, "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA256"),
+    (0xC028, "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA384"),
+    (0xC029, "TLS_ECDH_RSA_WITH_AES_128_CBC_SHA256"),
+    (0xC02A, "TLS_ECDH_RSA_WITH_AES_256_CBC_SHA384"),
+    (0xC02B, "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256"),
+    (0xC02C, "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384"),
+    (0xC02D, "TLS_ECDH_ECDSA_WITH_AES_128_GCM_SHA256"),
+    (0xC02E, "TLS_ECDH_ECDSA_WITH_AES_256_GCM_SHA384"),
+    (0xC02F, "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256"),
+    (0xC030, "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384"),
+    (0xC031, "TLS_ECDH_RSA_WITH_AES_128_GCM_SHA256"),
+    (0xC032, "TLS_ECDH_RSA_WITH_AES_256_GCM_SHA384"),
+    (0xC033, "TLS_ECDHE_PSK_WITH_RC4_128_SHA"),
+    (0xC034, "TLS_ECDHE_PSK_WITH_3DES_EDE_CBC_SHA"),
+    (0xC035, "TLS_ECDHE_PSK_WITH_AES_128_CBC_SHA"),
+    (0xC036, "TLS_ECDHE_PSK_WITH_AES_256_CBC_SHA"),
+    (0xC037, "TLS_ECDHE_PSK_WITH_AES_128_CBC_SHA256"),
+    (0xC038, "TLS_ECDHE_PSK_WITH_AES_256_CBC_SHA384"),
+    (0xC039, "TLS_ECDHE_PSK_WITH_NULL_SHA"),
+    (0xC03A, "TLS_ECDHE_PSK_WITH_NULL_SHA256"),
+    (0xC03B, "TLS_ECDHE_PSK_WITH_NULL_SHA384"),
+    (0xC050, "TLS_RSA_WITH_ARIA_128_GCM_SHA256"),
+    (0xC051, "TLS_RSA_WITH_ARIA_256_GCM_SHA384"),
+    (0xC052, "TLS_DHE_RSA_WITH_ARIA_128_GCM_SHA256"),
+    (0xC053, "TLS_DHE_RSA_WITH_ARIA_256_GCM_SHA384"),
+    (0xC05C, "TLS_ECDHE_ECDSA_WITH_ARIA_128_GCM_SHA256"),
+    (0xC05D, "TLS_ECDHE_ECDSA_WITH_ARIA_256_GCM_SHA384"),
+    (0xC060, "TLS_ECDHE_RSA_WITH_ARIA_128_GCM_SHA256"),
+    (0xC061, "TLS_ECDHE_RSA_WITH_ARIA_256_GCM_SHA384"),
+    (0xC072, "TLS_ECDHE_ECDSA_WITH_CAMELLIA_128_CBC_SHA256"),
+    (0xC073, "TLS_ECDHE_ECDSA_WITH_CAMELLIA_256_CBC_SHA384"),
+    (0xC074, "TLS_ECDH_ECDSA_WITH_CAMELLIA_128_CBC_SHA256"),
+    (0xC075, "TLS_ECDH_ECDSA_WITH_CAMELLIA_256_CBC_SHA384"),
+    (0xC076, "TLS_ECDHE_RSA_WITH_CAMELLIA_128_CBC_SHA256"),
+    (0xC077, "TLS_ECDHE_RSA_WITH_CAMELLIA_256_CBC_SHA384"),
+    (0xC078, "TLS_ECDH_RSA_WITH_CAMELLIA_128_CBC_SHA256"),
+    (0xC079, "TLS_ECDH_RSA_WITH_CAMELLIA_256_CBC_SHA384"),
+    (0xC09C, "TLS_RSA_WITH_AES_128_CCM"),
+    (0xC09D, "TLS_RSA_WITH_AES_256_CCM"),
+    (0xC09E, "TLS_DHE_RSA_WITH_AES_128_CCM"),
+    (0xC09F, "TLS_DHE_RSA_WITH_AES_256_CCM"),
+    (0xC0A0, "TLS_RSA_WITH_AES_128_CCM_8"),
+    (0xC0A1, "TLS_RSA_WITH_AES_256_CCM_8"),
+    (0xC0A2, "TLS_DHE_RSA_WITH_AES_128_CCM_8"),
+    (0xC0A3, "TLS_DHE_RSA_WITH_AES_256_CCM_8"),
+    (0xC0A4, "TLS_PSK_WITH_AES_128_CCM"),
+    (0xC0A5, "TLS_PSK_WITH_AES_256_CCM"),
+    (0xC0A6, "TLS_DHE_PSK_WITH_AES_128_CCM"),
+    (0xC0A7, "TLS_DHE_PSK_WITH_AES_256_CCM"),
+    (0xC0A8, "TLS_PSK_WITH_AES_128_CCM_8"),
+    (0xC0A9, "TLS_PSK_WITH_AES_256_CCM_8"),
+    (0xC0AA, "TLS_PSK_DHE_WITH_AES_128_CCM_8"),
+    (0xC0AB, "TLS_PSK_DHE_WITH_AES_256_CCM_8"),
+    (0xC0AC, "TLS_ECDHE_ECDSA_WITH_AES_128_CCM"),
+    (0xC0AD, "TLS_ECDHE_ECDSA_WITH_AES_256_CCM"),
+    (0xC0AE, "TLS_ECDHE_ECDSA_WITH_AES_128_CCM_8"),
+    (0xC0AF, "TLS_ECDHE_ECDSA_WITH_AES_256_CCM_8"),
+    (0xCCA8, "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256"),
+    (0xCCA9, "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256"),
+    (0xCCAA, "TLS_DHE_RSA_WITH_CHACHA20_POLY1305_SHA256"),
+    (0xCCAB, "TLS_PSK_WITH_CHACHA20_POLY1305_SHA256"),
+    (0xCCAC, "TLS_ECDHE_PSK_WITH_CHACHA20_POLY1305_SHA256"),
+    (0xCCAD, "TLS_DHE_PSK_WITH_CHACHA20_POLY1305_SHA256"),
+    (0xCCAE, "TLS_RSA_PSK_WITH_CHACHA20_POLY1305_SHA256"),
+    (0xD001, "TLS_ECDHE_PSK_WITH_AES_128_GCM_SHA256"),
+    (0xD002, "TLS_ECDHE_PSK_WITH_AES_256_GCM_SHA384"),
+    (0xD003, "TLS_ECDHE_PSK_WITH_AES_128_CCM_8_SHA256"),
+    (0xD005, "TLS_ECDHE_PSK_WITH_AES_128_CCM_SHA256"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::measurement::TlsMeasurement;
+
+    fn measurement(
+        method: &str,
+        ver: Option<&str>,
+        cipher: Option<&str>,
+    ) -> TlsMeasurement<'static> {
+        serde_json::from_value(serde_json::json!({
+            "af": 4, "cert": [], "dst_addr": "192.0.2.1", "dst_name": "www.example.com",
+            "dst_port": "443", "from": "203.0.113.5", "fw": 5080, "method": method,
+            "msm_id": 40001, "msm_name": "SSLCert", "prb_id": 6001, "rt": 80.5,
+            "server_cipher": cipher, "src_addr": "192.168.1.10", "timestamp": 1669852800,
+            "ttc": 40.2, "type": "sslcert", "ver": ver
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn registry_is_sorted() {
+        assert!(CIPHER_SUITES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn parses_server_cipher() {
+        for hex in ["C02F", "c02f", "0xC02F", "0Xc02f", " C02F "] {
+            let measurement = measurement("TLS", Some("1.2"), Some(hex));
+            assert_eq!(
+                measurement.cipher_suite(),
+                Some(CipherSuite(0xC02F)),
+                "{}",
+                hex
+            );
+        }
+
+        assert_eq!(measurement("TLS", None, Some("ZZ")).cipher_suite(), None);
+        assert_eq!(measurement("TLS", None, Some("1C02F")).cipher_suite(), None);
+        assert_eq!(measurement("TLS", None, None).cipher_suite(), None);
+    }
+
+    #[test]
+    fn parses_protocol_version() {
+        let cases = [
+            ("TLS", "1.2", Some(ProtocolVersion::Tls12)),
+            ("TLS", "3.3", Some(ProtocolVersion::Tls12)),
+            ("TLS", "1.3", Some(ProtocolVersion::Tls13)),
+            ("TLS", "1", Some(ProtocolVersion::Tls10)),
+            ("TLS", "3.0", Some(ProtocolVersion::Ssl3)),
+            ("SSL", "3.0", Some(ProtocolVersion::Ssl3)),
+            ("SSL", "2", Some(ProtocolVersion::Ssl2)),
+            ("SSL", "TLSv1.1", Some(ProtocolVersion::Tls11)),
+            ("TLS", "SSLv3", Some(ProtocolVersion::Ssl3)),
+            ("TLS", "tls 1.2", Some(ProtocolVersion::Tls12)),
+            ("SSL", "1.2", None),
+            ("TLS", "9.9", None),
+        ];
+
+        for (method, ver, expected) in cases {
+            let measurement = measurement(method, Some(ver), None);
+            assert_eq!(
+                measurement.protocol_version(),
+                expected,
+                "{} {}",
+                method,
+                ver
+            );
+        }
+        assert_eq!(measurement("TLS", None, None).protocol_version(), None);
+    }
+
+    #[test]
+    fn decodes_wire_versions() {
+        assert_eq!(
+            ProtocolVersion::from_wire(0x0303),
+            Some(ProtocolVersion::Tls12)
+        );
+        assert_eq!(
+            ProtocolVersion::from_wire(0x0304),
+            Some(ProtocolVersion::Tls13)
+        );
+        assert_eq!(
+            ProtocolVersion::from_wire(0x0002),
+            Some(ProtocolVersion::Ssl2)
+        );
+        assert_eq!(ProtocolVersion::from_wire(0x7F1C), None);
+
+        assert!(ProtocolVersion::Tls11.is_deprecated());
+        assert!(!ProtocolVersion::Tls12.is_deprecated());
+        assert_eq!(ProtocolVersion::Tls10.to_string(), "TLSv1.0");
+        assert_eq!(ProtocolVersion::Ssl3.to_string(), "SSLv3");
+    }
+
+    #[test]
+    fn classifies_suites() {
+        let suite = CipherSuite(0xC02F);
+        assert_eq!(suite.name(), Some("TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256"));
+        assert_eq!(
+            suite.key_exchange(),
+            Some((KeyExchange::Ecdhe, Authentication::Rsa))
+        );
+        assert_eq!(suite.cipher(), Some(BulkCipher::Aes128Gcm));
+        assert_eq!(suite.hash(), Some(Hash::Sha256));
+        assert!(suite.forward_secrecy() && suite.is_aead());
+        assert_eq!(suite.strength(), Some(Strength::Strong));
+
+        // TLS 1.3 suites leave out the key exchange
+        let suite = CipherSuite(0x1303);
+        assert_eq!(suite.key_exchange(), None);
+        assert_eq!(suite.cipher(), Some(BulkCipher::ChaCha20Poly1305));
+        assert_eq!(suite.cipher().map(|cipher| cipher.key_bits()), Some(256));
+        assert!(suite.forward_secrecy());
+        assert_eq!(suite.strength(), Some(Strength::Strong));
+        assert!((0x1301..=0x1305).all(|code| CipherSuite(code).forward_secrecy()));
+
+        // Suites without a known key exchange are not assumed to be TLS 1.3 suites
+        assert!(!CipherSuite(0x00FF).forward_secrecy());
+        assert!(!CipherSuite(0x1306).forward_secrecy());
+        assert!(!CipherSuite(0xFFFF).forward_secrecy());
+
+        let suite = CipherSuite(0xD003);
+        assert_eq!(
+            suite.key_exchange(),
+            Some((KeyExchange::Ecdhe, Authentication::Psk))
+        );
+        assert_eq!(suite.cipher(), Some(BulkCipher::Aes128Ccm8));
+        assert_eq!(suite.hash(), Some(Hash::Sha256));
+
+        let weak = [
+            // no forward secrecy
+            (0x0035, BulkCipher::Aes256Cbc, Hash::Sha1),
+            (0x000A, BulkCipher::TripleDes, Hash::Sha1),
+            // no authenticated encryption
+            (0xC013, BulkCipher::Aes128Cbc, Hash::Sha1),
+            (0x0067, BulkCipher::Aes128Cbc, Hash::Sha256),
+        ];
+        for (code, cipher, hash) in weak {
+            let suite = CipherSuite(code);
+            assert_eq!(suite.cipher(), Some(cipher), "{}", suite);
+            assert_eq!(suite.hash(), Some(hash), "{}", suite);
+            assert!(!suite.is_deprecated(), "{}", suite);
+            assert_eq!(suite.strength(), Some(Strength::Weak), "{}", suite);
+        }
+    }
+
+    #[test]
+    fn classifies_insecure_suites() {
+        let suite = CipherSuite(0x0003);
+        assert!(suite.is_export());
+        assert_eq!(
+            suite.key_exchange(),
+            Some((KeyExchange::Rsa, Authentication::Rsa))
+        );
+        assert_eq!(suite.cipher(), Some(BulkCipher::Rc4Export));
+        assert_eq!(suite.hash(), Some(Hash::Md5));
+
+        let suite = CipherSuite(0x0000);
+        assert_eq!(
+            suite.key_exchange(),
+            Some((KeyExchange::Null, Authentication::Null))
+        );
+        assert_eq!(suite.cipher(), Some(BulkCipher::Null));
+        assert_eq!(suite.hash(), None);
+
+        let suite = CipherSuite(0x00A7);
+        assert_eq!(
+            suite.key_exchange(),
+            Some((KeyExchange::Dhe, Authentication::Anonymous))
+        );
+        assert!(suite.forward_secrecy() && suite.is_aead());
+
+        // export, NULL, anonymous, RC4, DES, IDEA and MD5
+        for code in [
+            0x0003, 0x0000, 0x0001, 0x00A7, 0x0017, 0x0005, 0x0009, 0x0007, 0x0004,
+        ] {
+            let suite = CipherSuite(code);
+            assert_eq!(suite.strength(), Some(Strength::Insecure), "{}", suite);
+        }
+    }
+
+    #[test]
+    fn leaves_unknown_suites_unclassified() {
+        // Signalling suites are registered but do not describe a cipher
+        let suite = CipherSuite(0x00FF);
+        assert_eq!(suite.name(), Some("TLS_EMPTY_RENEGOTIATION_INFO_SCSV"));
+        assert_eq!(suite.cipher(), None);
+        assert!(!suite.forward_secrecy());
+        assert_eq!(suite.strength(), None);
+
+        // GREASE value
+        let suite = CipherSuite(0x0A0A);
+        assert_eq!(suite.name(), None);
+        assert_eq!(suite.key_exchange(), None);
+        assert_eq!(suite.strength(), None);
+        assert_eq!(suite.to_string(), "0x0A0A");
+        assert_eq!(CipherSuite(0x5600).to_string(), "TLS_FALLBACK_SCSV");
+        assert_eq!(Strength::Weak.to_string(), "weak");
+    }
+}
@@ -0,0 +1,221 @@
+use crate::general::AddressFamily;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt::{self, Display, Formatter};
+
+#[cfg(feature = "x509")]
+pub mod certificate;
+pub mod cipher;
+#[cfg(feature = "x509")]
+pub mod report;
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
+pub struct Tls<'a> {
+    /// address family, 4 or 6 (integer)
+    pub af: Option<AddressFamily>,
+    /// [optional] error sent by server (see RFC 5246, Section 7.2)
+    pub alert: Option<Alert>,
+    /// [optional] results of query, not present if "alert" is present (array of strings)
+    /// Each element of the array is a string containing a base 64 encoded certificate.
+    /// Newlines are replaced with "\n"
+    pub cert: Option<Vec<Cow<'a, str>>>,
+    /// IP address of the destination (string)
+    pub dst_addr: Option<Cow<'a, str>>,
+    /// name of the destination (string)
+    pub dst_name: Cow<'a, str>,
+    /// port name (string)
+    pub dst_port: Cow<'a, str>,
+    /// "SSL" or "TLS" (string)
+    pub method: Option<Method>,
+    /// [optional] response time in milli seconds from starting to connect to receiving the
+    /// certificates (float)
+    pub rt: Option<f64>,
+    /// [optional] cipher selected by server as a hexadecimal number (string)
+    pub server_cipher: Option<Cow<'a, str>>,
+    /// [optional] time in milli seconds that it took to connect (over TCP) to the target (float)
+    pub ttc: Option<f64>,
+    /// (SSL/TLS) protocol version (string)
+    pub ver: Option<Cow<'a, str>>,
+    /// description of error (string)
+    pub err: Option<Cow<'a, str>>,
+
+    /// description of error (string)
+    pub error: Option<Cow<'a, str>>,
+    /// description of error (string)
+    pub dnserr: Option<Cow<'a, str>>,
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
+pub struct Alert {
+    /// AlertLevel (integer)
+    level: i64,
+    /// AlertDescription (integer)
+    description: i64,
+}
+
+impl Alert {
+    pub fn level(&self) -> AlertLevel {
+        match self.level {
+            1 => AlertLevel::Warning,
+            2 => AlertLevel::Fatal,
+            x => AlertLevel::Other(x),
+        }
+    }
+
+    pub fn description(&self) -> AlertDescription {
+        AlertDescription::from(self.description)
+    }
+}
+
+impl Display for Alert {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.description(), self.level())
+    }
+}
+
+/// AlertLevel from RFC 5246 7.2
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum AlertLevel {
+    Warning,
+    Fatal,
+    Other(i64),
+}
+
+impl Display for AlertLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AlertLevel::Warning => write!(f, "warning"),
+            AlertLevel::Fatal => write!(f, "fatal"),
+            AlertLevel::Other(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+/// AlertDescription from RFC 5246 7.2 along with the values added by later RFCs. Variants are
+/// displayed using the names from the RFC.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum AlertDescription {
+    CloseNotify,
+    UnexpectedMessage,
+    BadRecordMac,
+    DecryptionFailed,
+    RecordOverflow,
+    DecompressionFailure,
+    HandshakeFailure,
+    NoCertificate,
+    BadCertificate,
+    UnsupportedCertificate,
+    CertificateRevoked,
+    CertificateExpired,
+    CertificateUnknown,
+    IllegalParameter,
+    UnknownCa,
+    AccessDenied,
+    DecodeError,
+    DecryptError,
+    ExportRestriction,
+    ProtocolVersion,
+    InsufficientSecurity,
+    InternalError,
+    InappropriateFallback,
+    UserCanceled,
+    NoRenegotiation,
+    MissingExtension,
+    UnsupportedExtension,
+    UnrecognizedName,
+    BadCertificateStatusResponse,
+    UnknownPskIdentity,
+    CertificateRequired,
+    NoApplicationProtocol,
+    Other(i64),
+}
+
+impl From<i64> for AlertDescription {
+    fn from(value: i64) -> Self {
+        match value {
+            0 => AlertDescription::CloseNotify,
+            10 => AlertDescription::UnexpectedMessage,
+            20 => AlertDescription::BadRecordMac,
+            21 => AlertDescription::DecryptionFailed,
+            22 => AlertDescription::RecordOverflow,
+            30 => AlertDescription::DecompressionFailure,
+            40 => AlertDescription::HandshakeFailure,
+            41 => AlertDescription::NoCertificate,
+            42 => AlertDescription::BadCertificate,
+            43 => AlertDescription::UnsupportedCertificate,
+            44 => AlertDescription::CertificateRevoked,
+            45 => AlertDescription::CertificateExpired,
+            46 => AlertDescription::CertificateUnknown,
+            47 => AlertDescription::IllegalParameter,
+            48 => AlertDescription::UnknownCa,
+            49 => AlertDescription::AccessDenied,
+            50 => AlertDescription::DecodeError,
+            51 => AlertDescription::DecryptError,
+            60 => AlertDescription::ExportRestriction,
+            70 => AlertDescription::ProtocolVersion,
+            71 => AlertDescription::InsufficientSecurity,
+            80 => AlertDescription::InternalError,
+            86 => AlertDescription::InappropriateFallback,
+            90 => AlertDescription::UserCanceled,
+            100 => AlertDescription::NoRenegotiation,
+            109 => AlertDescription::MissingExtension,
+            110 => AlertDescription::UnsupportedExtension,
+            112 => AlertDescription::UnrecognizedName,
+            113 => AlertDescription::BadCertificateStatusResponse,
+            115 => AlertDescription::UnknownPskIdentity,
+            116 => AlertDescription::CertificateRequired,
+            120 => AlertDescription::NoApplicationProtocol,
+            x => AlertDescription::Other(x),
+        }
+    }
+}
+
+impl Display for AlertDescription {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AlertDescription::CloseNotify => write!(f, "close_notify"),
+            AlertDescription::UnexpectedMessage => write!(f, "unexpected_message"),
+            AlertDescription::BadRecordMac => write!(f, "bad_record_mac"),
+            AlertDescription::DecryptionFailed => write!(f, "decryption_failed"),
+            AlertDescription::RecordOverflow => write!(f, "record_overflow"),
+            AlertDescription::DecompressionFailure => write!(f, "decompression_failure"),
+            AlertDescription::HandshakeFailure => write!(f, "handshake_failure"),
+            AlertDescription::NoCertificate => write!(f, "no_certificate"),
+            AlertDescription::BadCertificate => write!(f, "bad_certificate"),
+            AlertDescription::UnsupportedCertificate => write!(f, "unsupported_certificate"),
+            AlertDescription::CertificateRevoked => write!(f, "certificate_revoked"),
+            AlertDescription::CertificateExpired => write!(f, "certificate_expired"),
+            AlertDescription::CertificateUnknown => write!(f, "certificate_unknown"),
+            AlertDescription::IllegalParameter => write!(f, "illegal_parameter"),
+            AlertDescription::UnknownCa => write!(f, "unknown_ca"),
+            AlertDescription::AccessDenied => write!(f, "access_denied"),
+            AlertDescription::DecodeError => write!(f, "decode_error"),
+            AlertDescription::DecryptError => write!(f, "decrypt_error"),
+            AlertDescription::ExportRestriction => write!(f, "export_restriction"),
+            AlertDescription::ProtocolVersion => write!(f, "protocol_version"),
+            AlertDescription::InsufficientSecurity => write!(f, "insufficient_security"),
+            AlertDescription::InternalError => write!(f, "internal_error"),
+            AlertDescription::InappropriateFallback => write!(f, "inappropriate_fallback"),
+            AlertDescription::UserCanceled => write!(f, "user_canceled"),
+            AlertDescription::NoRenegotiation => write!(f, "no_renegotiation"),
+            AlertDescription::MissingExtension => write!(f, "missing_extension"),
+            AlertDescription::UnsupportedExtension => write!(f, "unsupported_extension"),
+            AlertDescription::UnrecognizedName => write!(f, "unrecognized_name"),
+            AlertDescription::BadCertificateStatusResponse => {
+                write!(f, "bad_certificate_status_response")
+            }
+            AlertDescription::UnknownPskIdentity => write!(f, "unknown_psk_identity"),
+            AlertDescription::CertificateRequired => write!(f, "certificate_required"),
+            AlertDescription::NoApplicationProtocol => write!(f, "no_application_protocol"),
+            AlertDescription::Other(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+pub enum Method {
+    SSL,
+    TLS,
+}
@@ -0,0 +1,487 @@
+//! Cross-probe analysis of sslcert results to find expired certificates, hostname mismatches,
+//! possible interception and TLS alerts.
+use crate::general::{unix_seconds, UnixTimestamp};
+use crate::measurement::tls::certificate::Certificate;
+use crate::measurement::tls::{AlertDescription, AlertLevel};
+use crate::measurement::TlsMeasurement;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+/// Findings for a set of sslcert measurements, grouped by the probe which made the measurement.
+/// Probes without any findings are not included.
+#[derive(Clone, Debug, Default)]
+pub struct TlsReport {
+    findings: BTreeMap<i64, Vec<Finding>>,
+}
+
+impl TlsReport {
+    /// Check each measurement on its own and compare its leaf certificate with those seen by the
+    /// other probes measuring the same target within the same `window` of time. Using the interval
+    /// of the measurement as the window compares the probes of a single round, so certificates
+    /// being replaced between rounds are not reported. Windows are aligned to the Unix epoch and
+    /// rounded down to whole seconds; windows shorter than a second are treated as one second.
+    ///
+    /// Each probe counts once towards the majority of a window, using the last leaf certificate it
+    /// saw in the window, so a probe measuring the target several times does not outvote others.
+    pub fn new<'a: 'b, 'b, I>(measurements: I, window: Duration) -> Self
+    where
+        I: IntoIterator<Item = &'b TlsMeasurement<'a>>,
+    {
+        let window = window.as_secs().max(1) as i64;
+        let decoded: Vec<_> = measurements
+            .into_iter()
+            .map(|measurement| (measurement, measurement.decode_certificates()))
+            .collect();
+
+        // Find the last leaf certificate each probe saw for each target in each window
+        let mut votes: HashMap<RoundKey, HashMap<i64, (i64, [u8; 32])>> = HashMap::new();
+        for (measurement, certs) in &decoded {
+            if let Ok(Some(leaf)) = certs.as_ref().map(|certs| certs.first()) {
+                let time = unix_seconds(measurement.timestamp);
+                let vote = votes
+                    .entry(round_key(measurement, window))
+                    .or_default()
+                    .entry(measurement.prb_id)
+                    .or_insert((time, leaf.fingerprint));
+
+                if time >= vote.0 {
+                    *vote = (time, leaf.fingerprint);
+                }
+            }
+        }
+
+        let references: HashMap<_, _> = votes
+            .into_iter()
+            .map(|(round, votes)| {
+                let mut counts: HashMap<[u8; 32], usize> = HashMap::new();
+                for (_, fingerprint) in votes.into_values() {
+                    *counts.entry(fingerprint).or_default() += 1;
+                }
+                (round, reference(&counts))
+            })
+            .collect();
+
+        let mut report = TlsReport::default();
+        for (measurement, certs) in &decoded {
+            let mut issues = Vec::new();
+
+            if let Some(alert) = &measurement.alert {
+                issues.push(Issue::Alert {
+                    level: alert.level(),
+                    description: alert.description(),
+                });
+            }
+
+            match certs {
+                Ok(certs) => {
+                    issues.extend(validity_issues(certs, measurement.timestamp));
+
+                    if let Some(leaf) = certs.first() {
+                        if !leaf.matches_hostname(&measurement.dst_name) {
+                            issues.push(Issue::HostnameMismatch);
+                        }
+
+                        match references.get(&round_key(measurement, window)) {
+                            Some(Reference::Majority(majority))
+                                if *majority != leaf.fingerprint =>
+                            {
+                                issues.push(Issue::UnexpectedLeaf {
+                                    fingerprint: leaf.fingerprint,
+                                    majority: *majority,
+                                });
+                            }
+                            Some(Reference::Ambiguous { distinct }) => {
+                                issues.push(Issue::AmbiguousLeaf {
+                                    fingerprint: leaf.fingerprint,
+                                    distinct: *distinct,
+                                });
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Err(err) => issues.push(Issue::UndecodableCertificate(err.to_string())),
+            }
+
+            if issues.is_empty() {
+                continue;
+            }
+
+            let findings = report.findings.entry(measurement.prb_id).or_default();
+            findings.extend(issues.into_iter().map(|issue| Finding {
+                msm_id: measurement.msm_id,
+                timestamp: measurement.timestamp,
+                dst_name: measurement.dst_name.to_string(),
+                dst_addr: measurement.dst_addr.as_deref().map(str::to_string),
+                issue,
+            }));
+        }
+
+        report
+    }
+
+    /// Findings for a single probe
+    pub fn probe(&self, prb_id: i64) -> &[Finding] {
+        self.findings.get(&prb_id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Iterate over the findings of each probe in order of probe ID
+    pub fn iter(&self) -> impl Iterator<Item = (i64, &[Finding])> {
+        self.findings
+            .iter()
+            .map(|(prb_id, findings)| (*prb_id, findings.as_slice()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Finding {
+    pub msm_id: i64,
+    pub timestamp: UnixTimestamp,
+    pub dst_name: String,
+    pub dst_addr: Option<String>,
+    pub issue: Issue,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Issue {
+    /// The certificate at this position in the chain had expired at the time of the measurement
+    Expired {
+        position: usize,
+        not_after: UnixTimestamp,
+    },
+    /// The certificate at this position in the chain was not yet valid at the time of the
+    /// measurement
+    NotYetValid {
+        position: usize,
+        not_before: UnixTimestamp,
+    },
+    /// The leaf certificate is not valid for `dst_name`
+    HostnameMismatch,
+    /// The leaf certificate differs from the one seen by more than half of the probes measuring
+    /// the same target in the same window. This may be a sign of TLS interception.
+    UnexpectedLeaf {
+        fingerprint: [u8; 32],
+        majority: [u8; 32],
+    },
+    /// The probes measuring the same target in the same window saw several leaf certificates and
+    /// none of them was seen by more than half of the probes, so it is not known which one is
+    /// expected. Targets served by several providers look like this.
+    AmbiguousLeaf {
+        fingerprint: [u8; 32],
+        /// number of different leaf certificates seen in the window
+        distinct: usize,
+    },
+    /// The server responded with an alert instead of a certificate
+    Alert {
+        level: AlertLevel,
+        description: AlertDescription,
+    },
+    /// One of the certificates could not be decoded
+    UndecodableCertificate(String),
+}
+
+fn validity_issues(certs: &[Certificate], time: UnixTimestamp) -> impl Iterator<Item = Issue> + '_ {
+    certs
+        .iter()
+        .enumerate()
+        .filter(move |(_, cert)| !cert.is_valid_at(time))
+        .map(move |(position, cert)| {
+            if unix_seconds(time) > unix_seconds(cert.not_after) {
+                Issue::Expired {
+                    position,
+                    not_after: cert.not_after,
+                }
+            } else {
+                Issue::NotYetValid {
+                    position,
+                    not_before: cert.not_before,
+                }
+            }
+        })
+}
+
+/// Target and window of a measurement
+type RoundKey<'a> = (String, &'a str, i64);
+
+/// Targets are identified by name and port since the same name may be served by different
+/// certificates on different ports.
+fn round_key<'a>(measurement: &'a TlsMeasurement, window: i64) -> RoundKey<'a> {
+    (
+        measurement.dst_name.to_ascii_lowercase(),
+        measurement.dst_port.as_ref(),
+        unix_seconds(measurement.timestamp).div_euclid(window),
+    )
+}
+
+/// The leaf certificate expected for a target in a window
+enum Reference {
+    /// fingerprint seen by more than half of the probes
+    Majority([u8; 32]),
+    /// no fingerprint was seen by more than half of the probes
+    Ambiguous { distinct: usize },
+}
+
+fn reference(counts: &HashMap<[u8; 32], usize>) -> Reference {
+    let total: usize = counts.values().sum();
+
+    counts
+        .iter()
+        .find(|(_, count)| **count * 2 > total)
+        .map(|(fingerprint, _)| Reference::Majority(*fingerprint))
+        .unwrap_or(Reference::Ambiguous {
+            distinct: counts.len(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Certificate for www.example.com valid from 2022-11-01 until 2023-11-01
+    const LEAF: &str = include_str!("testdata/leaf.pem");
+    /// CA which issued `LEAF`, valid from 2022-11-01 until 2032-11-01
+    const ROOT: &str = include_str!("testdata/root.pem");
+    /// Certificate for ed.example.org, standing in for one presented by an interceptor
+    const OTHER: &str = include_str!("testdata/ed25519.pem");
+
+    /// 2022-12-01 00:00:00, while both certificates were valid
+    const TIME: i64 = 1669852800;
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    fn measurement(prb_id: i64, timestamp: i64, certs: &[&str]) -> TlsMeasurement<'static> {
+        serde_json::from_value(json(prb_id, timestamp, certs)).unwrap()
+    }
+
+    fn json(prb_id: i64, timestamp: i64, certs: &[&str]) -> serde_json::Value {
+        serde_json::json!({
+            "af": 4, "cert": certs, "dst_addr": "192.0.2.1", "dst_name": "www.example.com",
+            "dst_port": "443", "from": "203.0.113.5", "fw": 5080, "method": "TLS",
+            "msm_id": 40001, "msm_name": "SSLCert", "prb_id": prb_id, "rt": 80.5,
+            "server_cipher": "C02F", "src_addr": "192.168.1.10", "timestamp": timestamp,
+            "ttc": 40.2, "type": "sslcert", "ver": "1.2"
+        })
+    }
+
+    fn fingerprint(pem: &str) -> [u8; 32] {
+        measurement(1, TIME, &[pem]).decode_certificates().unwrap()[0].fingerprint
+    }
+
+    fn issues(report: &TlsReport, prb_id: i64) -> Vec<&Issue> {
+        report
+            .probe(prb_id)
+            .iter()
+            .map(|finding| &finding.issue)
+            .collect()
+    }
+
+    fn leaf_issues(report: &TlsReport, prb_id: i64) -> Vec<&Issue> {
+        issues(report, prb_id)
+            .into_iter()
+            .filter(|issue| {
+                matches!(
+                    issue,
+                    Issue::UnexpectedLeaf { .. } | Issue::AmbiguousLeaf { .. }
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn flags_leaves_differing_from_the_majority() {
+        let measurements = [
+            measurement(1, TIME, &[LEAF, ROOT]),
+            measurement(2, TIME + 10, &[LEAF, ROOT]),
+            measurement(3, TIME + 20, &[LEAF, ROOT]),
+            measurement(4, TIME + 30, &[OTHER]),
+        ];
+        let report = TlsReport::new(&measurements, HOUR);
+
+        for prb_id in 1..=3 {
+            assert!(report.probe(prb_id).is_empty());
+        }
+        assert_eq!(
+            issues(&report, 4),
+            vec![
+                &Issue::HostnameMismatch,
+                &Issue::UnexpectedLeaf {
+                    fingerprint: fingerprint(OTHER),
+                    majority: fingerprint(LEAF),
+                },
+            ]
+        );
+        assert_eq!(report.iter().count(), 1);
+    }
+
+    #[test]
+    fn reports_leaves_without_majority_as_ambiguous() {
+        let tied = [
+            measurement(1, TIME, &[LEAF, ROOT]),
+            measurement(2, TIME, &[LEAF, ROOT]),
+            measurement(3, TIME, &[OTHER]),
+            measurement(4, TIME, &[OTHER]),
+        ];
+        let report = TlsReport::new(&tied, HOUR);
+        for prb_id in 1..=4 {
+            let pem = if prb_id <= 2 { LEAF } else { OTHER };
+            assert_eq!(
+                leaf_issues(&report, prb_id),
+                vec![&Issue::AmbiguousLeaf {
+                    fingerprint: fingerprint(pem),
+                    distinct: 2,
+                }]
+            );
+        }
+
+        // Two out of four is the most common leaf, but not a majority
+        let plurality = [
+            measurement(1, TIME, &[LEAF, ROOT]),
+            measurement(2, TIME, &[LEAF, ROOT]),
+            measurement(3, TIME, &[OTHER]),
+            measurement(4, TIME, &[ROOT]),
+        ];
+        let report = TlsReport::new(&plurality, HOUR);
+        assert_eq!(
+            leaf_issues(&report, 1),
+            vec![&Issue::AmbiguousLeaf {
+                fingerprint: fingerprint(LEAF),
+                distinct: 3,
+            }]
+        );
+    }
+
+    #[test]
+    fn counts_one_vote_per_probe() {
+        // Probe 4 measured the target more often than the other probes together
+        let measurements = [
+            measurement(1, TIME, &[LEAF, ROOT]),
+            measurement(2, TIME, &[LEAF, ROOT]),
+            measurement(3, TIME, &[LEAF, ROOT]),
+            measurement(4, TIME, &[OTHER]),
+            measurement(4, TIME + 60, &[OTHER]),
+            measurement(4, TIME + 120, &[OTHER]),
+            measurement(4, TIME + 180, &[OTHER]),
+        ];
+
+        let report = TlsReport::new(&measurements, HOUR);
+        for prb_id in 1..=3 {
+            assert!(leaf_issues(&report, prb_id).is_empty());
+        }
+        assert_eq!(leaf_issues(&report, 4).len(), 4);
+        assert!(leaf_issues(&report, 4).iter().all(|issue| {
+            **issue
+                == Issue::UnexpectedLeaf {
+                    fingerprint: fingerprint(OTHER),
+                    majority: fingerprint(LEAF),
+                }
+        }));
+    }
+
+    #[test]
+    fn compares_leaves_within_each_window() {
+        // The certificate was replaced between two rounds of the measurement
+        let measurements = [
+            measurement(1, TIME, &[LEAF, ROOT]),
+            measurement(2, TIME, &[LEAF, ROOT]),
+            measurement(3, TIME, &[LEAF, ROOT]),
+            measurement(4, TIME, &[LEAF, ROOT]),
+            measurement(1, TIME + 3600, &[OTHER]),
+            measurement(2, TIME + 3600, &[OTHER]),
+            measurement(3, TIME + 3600, &[OTHER]),
+        ];
+
+        let report = TlsReport::new(&measurements, HOUR);
+        for prb_id in 1..=4 {
+            assert!(leaf_issues(&report, prb_id).is_empty());
+        }
+
+        // A window covering both rounds mistakes the old certificate for an interception
+        let report = TlsReport::new(&measurements, Duration::from_secs(86400));
+        let unexpected = Issue::UnexpectedLeaf {
+            fingerprint: fingerprint(LEAF),
+            majority: fingerprint(OTHER),
+        };
+        assert_eq!(leaf_issues(&report, 1), vec![&unexpected]);
+        assert_eq!(leaf_issues(&report, 4), vec![&unexpected]);
+
+        // Windows shorter than a second are not empty
+        let report = TlsReport::new(&measurements[..1], Duration::ZERO);
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn checks_validity_and_hostnames() {
+        let expired = measurement(1, 1700000000, &[LEAF, ROOT]);
+        let early = measurement(2, 1667000000, &[LEAF, ROOT]);
+        let mut mismatch = json(3, TIME, &[LEAF, ROOT]);
+        mismatch["dst_name"] = "mail.example.com".into();
+        let mismatch: TlsMeasurement = serde_json::from_value(mismatch).unwrap();
+
+        let report = TlsReport::new([&expired, &early, &mismatch], HOUR);
+        let chain = expired.decode_certificates().unwrap();
+        assert_eq!(
+            issues(&report, 1),
+            vec![&Issue::Expired {
+                position: 0,
+                not_after: chain[0].not_after,
+            }]
+        );
+        assert_eq!(
+            issues(&report, 2),
+            vec![
+                &Issue::NotYetValid {
+                    position: 0,
+                    not_before: chain[0].not_before,
+                },
+                &Issue::NotYetValid {
+                    position: 1,
+                    not_before: chain[1].not_before,
+                },
+            ]
+        );
+        assert_eq!(issues(&report, 3), vec![&Issue::HostnameMismatch]);
+    }
+
+    #[test]
+    fn reports_alerts_and_undecodable_certificates() {
+        let alert: TlsMeasurement = serde_json::from_value(serde_json::json!({
+            "af": 4, "alert": {"level": 2, "description": 40}, "dst_addr": "192.0.2.1",
+            "dst_name": "www.example.com", "dst_port": "443", "from": "203.0.113.5", "fw": 5080,
+            "method": "TLS", "msm_id": 40001, "msm_name": "SSLCert", "prb_id": 1,
+            "src_addr": "192.168.1.10", "timestamp": TIME, "ttc": 40.2, "type": "sslcert",
+            "ver": "1.2"
+        }))
+        .unwrap();
+        let garbage = measurement(2, TIME, &["-----BEGIN CERTIFICATE-----\nAAAA\n"]);
+
+        let report = TlsReport::new([&alert, &garbage], HOUR);
+        assert_eq!(
+            issues(&report, 1),
+            vec![&Issue::Alert {
+                level: AlertLevel::Fatal,
+                description: AlertDescription::HandshakeFailure,
+            }]
+        );
+        assert!(matches!(
+            issues(&report, 2)[..],
+            [Issue::UndecodableCertificate(_)]
+        ));
+
+        let finding = &report.probe(2)[0];
+        assert_eq!(finding.msm_id, 40001);
+        assert_eq!(unix_seconds(finding.timestamp), TIME);
+        assert_eq!(finding.dst_name, "www.example.com");
+        assert_eq!(finding.dst_addr.as_deref(), Some("192.0.2.1"));
+    }
+
+    #[test]
+    fn handles_empty_input() {
+        let report = TlsReport::new(&[], HOUR);
+        assert!(report.is_empty());
+        assert!(report.probe(1).is_empty());
+        assert_eq!(report.iter().count(), 0);
+    }
+}
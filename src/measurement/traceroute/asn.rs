@@ -0,0 +1,568 @@
+//! Mapping of traceroute hops to the autonomous systems which originate them, using a prefix to
+//! AS table such as the CAIDA RouteViews pfx2as data sets.
+use crate::measurement::traceroute::{TraceHop, Traceroute};
+use crate::measurement::Measurement;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum PrefixTableError {
+    /// The table could not be read
+    Io(io::Error),
+    /// The line with this number (starting at 1) was not a valid entry
+    InvalidLine { line: usize, content: String },
+}
+
+impl Display for PrefixTableError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PrefixTableError::Io(err) => write!(f, "failed to read prefix table: {}", err),
+            PrefixTableError::InvalidLine { line, content } => {
+                write!(
+                    f,
+                    "invalid prefix table entry on line {}: {:?}",
+                    line, content
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PrefixTableError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PrefixTableError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PrefixTableError {
+    fn from(err: io::Error) -> Self {
+        PrefixTableError::Io(err)
+    }
+}
+
+/// Origin ASes of announced prefixes with longest prefix match lookups. Each address family keeps
+/// one map per prefix length, and lookups try the lengths in use from longest to shortest.
+#[derive(Clone, Debug, Default)]
+pub struct PrefixTable {
+    v4: PrefixMap<u32>,
+    v6: PrefixMap<u128>,
+}
+
+#[derive(Clone, Debug)]
+struct PrefixMap<K> {
+    /// announced prefixes by length, keyed by the network address
+    prefixes: HashMap<u8, HashMap<K, Vec<u32>>>,
+    /// prefix lengths with at least one entry, longest first
+    lengths: Vec<u8>,
+}
+
+impl<K> Default for PrefixMap<K> {
+    fn default() -> Self {
+        PrefixMap {
+            prefixes: HashMap::new(),
+            lengths: Vec::new(),
+        }
+    }
+}
+
+impl<K: Copy + Eq + std::hash::Hash> PrefixMap<K> {
+    fn insert(&mut self, network: K, length: u8, origins: &[u32]) {
+        if !self.lengths.contains(&length) {
+            self.lengths.push(length);
+            self.lengths.sort_unstable_by(|a, b| b.cmp(a));
+        }
+
+        let entry = self
+            .prefixes
+            .entry(length)
+            .or_default()
+            .entry(network)
+            .or_default();
+        entry.extend_from_slice(origins);
+        entry.sort_unstable();
+        entry.dedup();
+    }
+
+    fn lookup(&self, mask: impl Fn(u8) -> K) -> Option<(u8, &[u32])> {
+        self.lengths.iter().find_map(|length| {
+            let origins = self.prefixes.get(length)?.get(&mask(*length))?;
+            Some((*length, origins.as_slice()))
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.prefixes.values().map(HashMap::len).sum()
+    }
+}
+
+impl PrefixTable {
+    pub fn new() -> Self {
+        PrefixTable::default()
+    }
+
+    /// Load a table from a file, see [`PrefixTable::from_reader`] for the format
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, PrefixTableError> {
+        PrefixTable::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Read a table with one prefix per line. Both the pfx2as format, where the address, prefix
+    /// length and origin are separated by whitespace (`192.0.2.0 24 64496`), and CIDR notation
+    /// followed by the origin (`192.0.2.0/24 64496`) are accepted.
+    ///
+    /// Prefixes announced by several ASes list the origins separated by `_`, and AS sets separate
+    /// their members with `,`. Every AS listed is recorded as an origin of the prefix. Empty lines
+    /// and lines starting with `#` are skipped.
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, PrefixTableError> {
+        let mut table = PrefixTable::new();
+
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let entry = line.trim();
+            if entry.is_empty() || entry.starts_with('#') {
+                continue;
+            }
+
+            let (addr, length, origins) =
+                parse_entry(entry).ok_or_else(|| PrefixTableError::InvalidLine {
+                    line: index + 1,
+                    content: line.clone(),
+                })?;
+
+            if !table.insert(addr, length, &origins) {
+                return Err(PrefixTableError::InvalidLine {
+                    line: index + 1,
+                    content: line,
+                });
+            }
+        }
+
+        Ok(table)
+    }
+
+    /// Record `origins` as announcing the prefix. Host bits of `addr` are ignored. Returns `false`
+    /// without changing the table if the length is too long for the address family.
+    pub fn insert(&mut self, addr: IpAddr, length: u8, origins: &[u32]) -> bool {
+        match addr {
+            IpAddr::V4(addr) if length <= 32 => {
+                self.v4.insert(mask_v4(addr, length), length, origins);
+            }
+            IpAddr::V6(addr) if length <= 128 => {
+                self.v6.insert(mask_v6(addr, length), length, origins);
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Origin ASes of the most specific prefix containing the address
+    pub fn lookup(&self, addr: IpAddr) -> Option<&[u32]> {
+        self.lookup_prefix(addr).map(|(_, _, origins)| origins)
+    }
+
+    /// The most specific prefix containing the address as its network address and length, along
+    /// with its origin ASes
+    pub fn lookup_prefix(&self, addr: IpAddr) -> Option<(IpAddr, u8, &[u32])> {
+        match addr {
+            IpAddr::V4(addr) => {
+                self.v4
+                    .lookup(|length| mask_v4(addr, length))
+                    .map(|(length, origins)| {
+                        let network = Ipv4Addr::from(mask_v4(addr, length));
+                        (IpAddr::V4(network), length, origins)
+                    })
+            }
+            IpAddr::V6(addr) => {
+                self.v6
+                    .lookup(|length| mask_v6(addr, length))
+                    .map(|(length, origins)| {
+                        let network = Ipv6Addr::from(mask_v6(addr, length));
+                        (IpAddr::V6(network), length, origins)
+                    })
+            }
+        }
+    }
+
+    /// Number of prefixes in the table
+    pub fn len(&self) -> usize {
+        self.v4.len() + self.v6.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn mask_v4(addr: Ipv4Addr, length: u8) -> u32 {
+    match length {
+        0 => 0,
+        _ => u32::from(addr) & (u32::MAX << (32 - u32::from(length))),
+    }
+}
+
+fn mask_v6(addr: Ipv6Addr, length: u8) -> u128 {
+    match length {
+        0 => 0,
+        _ => u128::from(addr) & (u128::MAX << (128 - u32::from(length))),
+    }
+}
+
+/// Split a line into the prefix and its origins, accepting both supported formats
+fn parse_entry(entry: &str) -> Option<(IpAddr, u8, Vec<u32>)> {
+    let mut fields = entry.split_whitespace();
+    let prefix = fields.next()?;
+
+    let (addr, length) = match prefix.split_once('/') {
+        Some((addr, length)) => (addr, length),
+        None => (prefix, fields.next()?),
+    };
+
+    let origins = fields
+        .next()?
+        .split(['_', ','])
+        .map(|asn| asn.trim_start_matches("AS").parse().ok())
+        .collect::<Option<Vec<u32>>>()?;
+
+    if fields.next().is_some() {
+        return None;
+    }
+
+    Some((addr.parse().ok()?, length.parse().ok()?, origins))
+}
+
+/// The address is in private, shared, loopback, link local or otherwise special purpose address
+/// space (RFC 6890) which does not appear in the global routing table. Such addresses say nothing
+/// about the AS a hop belongs to.
+pub fn is_special_purpose(addr: IpAddr) -> bool {
+    match addr {
+        IpAddr::V4(addr) => {
+            let [a, b, c, _] = addr.octets();
+            addr.is_private()
+                || addr.is_loopback()
+                || addr.is_link_local()
+                || addr.is_unspecified()
+                || addr.is_broadcast()
+                || addr.is_documentation()
+                || addr.is_multicast()
+                // Shared address space for carrier grade NAT (RFC 6598)
+                || (a == 100 && (64..128).contains(&b))
+                // IETF protocol assignments (RFC 6890)
+                || (a == 192 && b == 0 && c == 0)
+                // Benchmarking (RFC 2544)
+                || (a == 198 && (b == 18 || b == 19))
+                // Reserved (RFC 1112)
+                || a >= 240
+                // "This network" (RFC 1122)
+                || a == 0
+        }
+        IpAddr::V6(addr) => {
+            if let Some(mapped) = addr.to_ipv4_mapped() {
+                return is_special_purpose(IpAddr::V4(mapped));
+            }
+
+            let segments = addr.segments();
+            addr.is_loopback()
+                || addr.is_unspecified()
+                || addr.is_multicast()
+                // Unique local addresses (RFC 4193)
+                || (segments[0] & 0xfe00) == 0xfc00
+                // Link local unicast (RFC 4291)
+                || (segments[0] & 0xffc0) == 0xfe80
+                // Documentation (RFC 3849)
+                || (segments[0] == 0x2001 && segments[1] == 0x0db8)
+        }
+    }
+}
+
+/// A single step of the AS path of a traceroute
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+pub enum AsHop {
+    /// origin ASes of the addresses which replied. There is more than one if the replies came
+    /// from prefixes with different origins or from a prefix announced by several ASes.
+    Asn(Vec<u32>),
+    /// no reply was received for this hop
+    Unresponsive,
+    /// the hop replied from public addresses which are not in the prefix table
+    Unknown,
+}
+
+impl Display for AsHop {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AsHop::Asn(origins) => {
+                for (index, origin) in origins.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, "_")?;
+                    }
+                    write!(f, "AS{}", origin)?;
+                }
+                Ok(())
+            }
+            AsHop::Unresponsive => write!(f, "*"),
+            AsHop::Unknown => write!(f, "?"),
+        }
+    }
+}
+
+impl<'a> Measurement<'a, Traceroute<'a>> {
+    /// Map each hop of the traceroute to the origin ASes of the addresses which replied. Hops
+    /// which only replied from private, shared or other special purpose addresses are left out
+    /// as they do not belong to any AS, and consecutive hops mapping to the same ASes or to
+    /// unknown addresses are merged into one. Each unresponsive hop keeps an entry of its own, so
+    /// the number of hops an AS may have hidden is not lost.
+    ///
+    /// The path starts with the AS of the probe's public address when it is in the table.
+    pub fn as_path(&self, table: &PrefixTable) -> Vec<AsHop> {
+        let source = self
+            .from
+            .parse()
+            .ok()
+            .filter(|addr| !is_special_purpose(*addr))
+            .and_then(|addr| table.lookup(addr))
+            .map(|origins| AsHop::Asn(origins.to_vec()));
+
+        let mut path: Vec<AsHop> = Vec::new();
+        for hop in source
+            .into_iter()
+            .chain(self.result.iter().filter_map(|hop| hop_as(hop, table)))
+        {
+            if hop == AsHop::Unresponsive || path.last() != Some(&hop) {
+                path.push(hop);
+            }
+        }
+
+        path
+    }
+}
+
+/// Find the origins of a single hop. Returns `None` for hops which only replied from special
+/// purpose addresses.
+fn hop_as(hop: &TraceHop, table: &PrefixTable) -> Option<AsHop> {
+    let mut replies = hop.iter_replies().peekable();
+    if replies.peek().is_none() {
+        return Some(AsHop::Unresponsive);
+    }
+
+    let public: Vec<IpAddr> = replies
+        .filter_map(|reply| reply.from.parse().ok())
+        .filter(|addr| !is_special_purpose(*addr))
+        .collect();
+
+    if public.is_empty() {
+        return None;
+    }
+
+    let origins: BTreeSet<u32> = public
+        .iter()
+        .filter_map(|addr| table.lookup(*addr))
+        .flatten()
+        .copied()
+        .collect();
+
+    match origins.is_empty() {
+        true => Some(AsHop::Unknown),
+        false => Some(AsHop::Asn(origins.into_iter().collect())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::measurement::traceroute::testing::{hop, hops, traceroute, traceroute_json};
+    use crate::measurement::TracerouteMeasurement;
+    use serde_json::json;
+    use std::error::Error;
+
+    /// Excerpt of a pfx2as table mixing both supported formats
+    const TABLE: &str = "\
+# prefix       length  origin
+193.0.0.0      21      3333
+193.0.0.0/24   AS3333
+80.249.208.0/21 1200
+185.1.0.0      16      64496_64497
+2001:67c:2e8:: 48      3333
+2001:7f8::/32  64498,64499
+
+0.0.0.0/0      1
+";
+
+    fn address(addr: &str) -> IpAddr {
+        addr.parse().unwrap()
+    }
+
+    #[test]
+    fn reads_prefix_tables() {
+        let table = PrefixTable::from_reader(TABLE.as_bytes()).unwrap();
+        assert_eq!(table.len(), 7);
+        assert!(!table.is_empty());
+
+        assert_eq!(table.lookup(address("193.0.0.1")), Some(&[3333][..]));
+        assert_eq!(
+            table.lookup_prefix(address("193.0.6.139")),
+            Some((address("193.0.0.0"), 21, &[3333][..]))
+        );
+        assert_eq!(
+            table.lookup(address("185.1.200.3")),
+            Some(&[64496, 64497][..])
+        );
+        assert_eq!(
+            table.lookup_prefix(address("2001:67c:2e8:22::c100:68b")),
+            Some((address("2001:67c:2e8::"), 48, &[3333][..]))
+        );
+        assert_eq!(
+            table.lookup(address("2001:7f8:1::a500:1200:1")),
+            Some(&[64498, 64499][..])
+        );
+        assert_eq!(table.lookup(address("2a00::1")), None);
+
+        // The default route matches every IPv4 address not covered by a longer prefix
+        assert_eq!(
+            table.lookup_prefix(address("8.8.8.8")),
+            Some((address("0.0.0.0"), 0, &[1][..]))
+        );
+        assert!(PrefixTable::new().is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_entries() {
+        let cases = [
+            ("193.0.0.0 21", 1),
+            ("# comment\n193.0.0.0 21 3333\n193.0.0.0/33 3333", 3),
+            ("2001:67c:2e8::/129 3333", 1),
+            ("193.0.0.0/21 AS-RIPE", 1),
+            ("193.0.0.0/21 3333 extra", 1),
+            ("ripe.net/21 3333", 1),
+        ];
+
+        for (input, expected) in cases {
+            match PrefixTable::from_reader(input.as_bytes()) {
+                Err(PrefixTableError::InvalidLine { line, content }) => {
+                    assert_eq!(line, expected, "{:?}", input);
+                    assert_eq!(content, input.lines().nth(line - 1).unwrap());
+                }
+                other => panic!("{:?} gave {:?}", input, other),
+            }
+        }
+
+        let err = PrefixTable::from_reader("193.0.0.0".as_bytes()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid prefix table entry on line 1: \"193.0.0.0\""
+        );
+        assert!(err.source().is_none());
+
+        let err = PrefixTable::load("testdata/does-not-exist.pfx2as").unwrap_err();
+        assert!(matches!(err, PrefixTableError::Io(_)));
+        assert!(err.source().is_some());
+
+        let mut table = PrefixTable::new();
+        assert!(!table.insert(address("193.0.0.0"), 40, &[3333]));
+        assert!(table.insert(address("193.0.0.255"), 24, &[3333]));
+        assert_eq!(
+            table.lookup_prefix(address("193.0.0.1")),
+            Some((address("193.0.0.0"), 24, &[3333][..]))
+        );
+    }
+
+    #[test]
+    fn recognises_special_purpose_addresses() {
+        let special = [
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "100.64.0.1",
+            "127.0.0.1",
+            "169.254.1.1",
+            "192.0.0.8",
+            "192.0.2.1",
+            "198.18.0.1",
+            "224.0.0.5",
+            "240.0.0.1",
+            "255.255.255.255",
+            "0.1.2.3",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "ff02::1",
+            "2001:db8::1",
+            "::ffff:10.0.0.1",
+        ];
+        for addr in special {
+            assert!(is_special_purpose(address(addr)), "{}", addr);
+        }
+
+        for addr in [
+            "193.0.6.139",
+            "100.128.0.1",
+            "2001:67c:2e8::1",
+            "::ffff:193.0.0.1",
+        ] {
+            assert!(!is_special_purpose(address(addr)), "{}", addr);
+        }
+    }
+
+    #[test]
+    fn maps_route_to_as_path() {
+        let table = PrefixTable::from_reader(TABLE.as_bytes()).unwrap();
+        let mut value = traceroute_json(
+            6001,
+            "193.0.6.139",
+            json!([
+                // home router and carrier grade NAT
+                hop(1, &["192.168.1.1"]),
+                hop(2, &["100.64.0.1"]),
+                hop(3, &["8.8.4.4"]),
+                hop(4, &["*", "*", "*"]),
+                {"error": "sendto failed"},
+                hop(6, &["80.249.208.1"]),
+                hop(7, &["185.1.0.1", "80.249.208.2"]),
+                hop(8, &["193.0.0.1"]),
+                hop(9, &["193.0.6.139"]),
+            ]),
+        );
+        value["from"] = json!("8.8.8.8");
+        let measurement: TracerouteMeasurement = serde_json::from_value(value).unwrap();
+
+        let path = measurement.as_path(&table);
+        assert_eq!(
+            path,
+            vec![
+                AsHop::Asn(vec![1]),
+                AsHop::Unresponsive,
+                AsHop::Unresponsive,
+                AsHop::Asn(vec![1200]),
+                AsHop::Asn(vec![1200, 64496, 64497]),
+                AsHop::Asn(vec![3333]),
+            ]
+        );
+        let path: Vec<String> = path.iter().map(AsHop::to_string).collect();
+        assert_eq!(path[..3], ["AS1", "*", "*"]);
+        assert_eq!(path[4], "AS1200_AS64496_AS64497");
+
+        // Without a default route the public addresses are unknown
+        let partial = PrefixTable::from_reader("193.0.0.0/21 3333".as_bytes()).unwrap();
+        let path = measurement.as_path(&partial);
+        assert_eq!(
+            path,
+            vec![
+                AsHop::Unknown,
+                AsHop::Unresponsive,
+                AsHop::Unresponsive,
+                AsHop::Unknown,
+                AsHop::Asn(vec![3333])
+            ]
+        );
+        assert_eq!(AsHop::Unknown.to_string(), "?");
+
+        let empty = traceroute(1, "193.0.6.139", hops(&[]));
+        assert!(empty.as_path(&table).is_empty());
+    }
+}
@@ -0,0 +1,332 @@
+//! Detection of load balanced hops from the addresses seen by traceroutes of a probe towards the
+//! same target using different Paris IDs.
+use crate::measurement::TracerouteMeasurement;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// How a load balancer spreads traffic over its next hops
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum Balancing {
+    /// each flow sticks to one next hop. Every Paris ID always saw the same address, while
+    /// different Paris IDs saw different addresses.
+    PerFlow,
+    /// packets of the same flow take different next hops. A single Paris traceroute saw more
+    /// than one address at the hop.
+    PerPacket,
+    /// the hop saw several addresses, but not in a pattern which shows how they are balanced.
+    /// This includes a single Paris ID seeing different addresses in different runs, which can
+    /// also be caused by a change of route.
+    Unclassified,
+}
+
+/// A hop which replied from more than one address
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BalancedHop {
+    pub hop: u32,
+    pub balancing: Balancing,
+    /// every address seen at the hop
+    pub addresses: Vec<String>,
+    /// addresses seen at the hop by each Paris ID. Traceroutes with a Paris ID of 0 or without
+    /// one are not included.
+    pub flows: BTreeMap<i64, Vec<String>>,
+}
+
+/// Load balancing on the paths from a single probe towards a single target
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FlowDiversity {
+    pub prb_id: i64,
+    /// address of the target, or its name if the address was not reported
+    pub target: String,
+    /// number of traceroutes in the group
+    pub traceroutes: usize,
+    /// Paris IDs used by the traceroutes in the group
+    pub paris_ids: Vec<i64>,
+    /// hops which replied from more than one address, in order of hop
+    pub hops: Vec<BalancedHop>,
+}
+
+impl FlowDiversity {
+    /// Hops which replied from more than one address
+    pub fn is_balanced(&self, hop: u32) -> bool {
+        self.hops.iter().any(|balanced| balanced.hop == hop)
+    }
+
+    /// The address was seen at a load balanced hop, so seeing a different address there in
+    /// another traceroute of the group does not mean the path changed
+    pub fn is_alternative(&self, hop: u32, address: &str) -> bool {
+        self.hops
+            .iter()
+            .any(|balanced| balanced.hop == hop && balanced.addresses.iter().any(|a| a == address))
+    }
+}
+
+#[derive(Default)]
+struct HopAddresses<'b> {
+    addresses: BTreeSet<&'b str>,
+    flows: BTreeMap<i64, BTreeSet<&'b str>>,
+    /// a single Paris traceroute replied from more than one address
+    per_packet: bool,
+}
+
+#[derive(Default)]
+struct Group<'b> {
+    traceroutes: usize,
+    paris_ids: BTreeSet<i64>,
+    hops: BTreeMap<u32, HopAddresses<'b>>,
+}
+
+/// Group traceroutes by probe and target and report the hops which replied from more than one
+/// address. Groups without any such hops are left out.
+///
+/// A Paris traceroute keeps the flow identifiers of its packets the same, so seeing several
+/// addresses at one hop within a single run shows per-packet balancing. Per-flow balancing shows
+/// as each Paris ID consistently following one path. Traceroutes with a Paris ID of 0 or without
+/// one vary their flow identifiers, so they add to the addresses seen but are not used to
+/// classify the balancing.
+pub fn detect_load_balancing<'a: 'b, 'b, I>(measurements: I) -> Vec<FlowDiversity>
+where
+    I: IntoIterator<Item = &'b TracerouteMeasurement<'a>>,
+{
+    let mut groups: BTreeMap<(i64, &'b str), Group<'b>> = BTreeMap::new();
+
+    for measurement in measurements {
+        let target = measurement
+            .dst_addr
+            .as_deref()
+            .unwrap_or(&measurement.dst_name);
+        let group = groups.entry((measurement.prb_id, target)).or_default();
+        group.traceroutes += 1;
+        group.paris_ids.extend(measurement.paris_id);
+
+        let paris_id = measurement.paris_id.filter(|id| *id != 0);
+
+        for (number, hop) in measurement.numbered_hops() {
+            let seen: BTreeSet<&str> = hop
+                .iter_replies()
+                .map(|reply| reply.from.as_ref())
+                .collect();
+            if seen.is_empty() {
+                continue;
+            }
+
+            let values = group.hops.entry(number).or_default();
+            if let Some(paris_id) = paris_id {
+                values.per_packet |= seen.len() > 1;
+                values
+                    .flows
+                    .entry(paris_id)
+                    .or_default()
+                    .extend(seen.iter().copied());
+            }
+            values.addresses.extend(seen);
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter_map(|((prb_id, target), group)| {
+            let hops: Vec<BalancedHop> = group
+                .hops
+                .into_iter()
+                .filter(|(_, values)| values.addresses.len() > 1)
+                .map(|(hop, values)| BalancedHop {
+                    hop,
+                    balancing: classify(&values),
+                    addresses: values.addresses.iter().map(|a| a.to_string()).collect(),
+                    flows: values
+                        .flows
+                        .iter()
+                        .map(|(id, seen)| (*id, seen.iter().map(|a| a.to_string()).collect()))
+                        .collect(),
+                })
+                .collect();
+
+            if hops.is_empty() {
+                return None;
+            }
+
+            Some(FlowDiversity {
+                prb_id,
+                target: target.to_string(),
+                traceroutes: group.traceroutes,
+                paris_ids: group.paris_ids.into_iter().collect(),
+                hops,
+            })
+        })
+        .collect()
+}
+
+fn classify(values: &HopAddresses) -> Balancing {
+    if values.per_packet {
+        return Balancing::PerPacket;
+    }
+
+    let distinct: BTreeSet<&str> = values.flows.values().flatten().copied().collect();
+    if distinct.len() > 1 && values.flows.values().all(|seen| seen.len() == 1) {
+        return Balancing::PerFlow;
+    }
+
+    Balancing::Unclassified
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::measurement::traceroute::testing::{hops, traceroute_json};
+    use serde_json::{json, Value};
+
+    const K_ROOT: &str = "193.0.14.129";
+
+    /// A traceroute towards K-root using the given Paris ID, or none
+    fn traceroute(
+        prb_id: i64,
+        paris_id: Option<i64>,
+        result: Value,
+    ) -> TracerouteMeasurement<'static> {
+        let mut value = traceroute_json(prb_id, K_ROOT, result);
+        match paris_id {
+            Some(paris_id) => value["paris_id"] = json!(paris_id),
+            None => {
+                value.as_object_mut().unwrap().remove("paris_id");
+            }
+        }
+        serde_json::from_value(value).unwrap()
+    }
+
+    /// Runs of probe 1 where hop 2 is balanced per flow, hop 3 per packet, and hop 4 only differs
+    /// for traceroutes which vary their flow
+    fn measurements() -> Vec<TracerouteMeasurement<'static>> {
+        vec![
+            traceroute(
+                1,
+                Some(1),
+                hops(&[
+                    &["192.168.1.1"],
+                    &["80.249.208.1", "80.249.208.1"],
+                    &["193.0.0.1", "193.0.0.2", "*"],
+                    &["193.0.4.1"],
+                    &[K_ROOT],
+                ]),
+            ),
+            traceroute(
+                1,
+                Some(2),
+                hops(&[
+                    &["192.168.1.1"],
+                    &["80.249.208.2", "*"],
+                    &["193.0.0.1"],
+                    &["193.0.4.1"],
+                    &[K_ROOT],
+                ]),
+            ),
+            traceroute(
+                1,
+                Some(1),
+                hops(&[
+                    &["192.168.1.1"],
+                    &["80.249.208.1"],
+                    &["193.0.0.2"],
+                    &["193.0.4.1"],
+                    &[K_ROOT],
+                ]),
+            ),
+            traceroute(
+                1,
+                Some(0),
+                hops(&[&["192.168.1.1"], &["*"], &["*"], &["193.0.4.2"], &[K_ROOT]]),
+            ),
+            traceroute(
+                1,
+                None,
+                hops(&[&["192.168.1.1"], &["*"], &["*"], &["193.0.4.3"], &[K_ROOT]]),
+            ),
+        ]
+    }
+
+    #[test]
+    fn classifies_balanced_hops() {
+        let diversity = detect_load_balancing(&measurements());
+        assert_eq!(diversity.len(), 1);
+
+        let diversity = &diversity[0];
+        assert_eq!((diversity.prb_id, diversity.target.as_str()), (1, K_ROOT));
+        assert_eq!(diversity.traceroutes, 5);
+        assert_eq!(diversity.paris_ids, vec![0, 1, 2]);
+
+        let balancing: Vec<(u32, Balancing)> = diversity
+            .hops
+            .iter()
+            .map(|hop| (hop.hop, hop.balancing))
+            .collect();
+        assert_eq!(
+            balancing,
+            vec![
+                (2, Balancing::PerFlow),
+                (3, Balancing::PerPacket),
+                (4, Balancing::Unclassified),
+            ]
+        );
+
+        let per_flow = &diversity.hops[0];
+        assert_eq!(per_flow.addresses, vec!["80.249.208.1", "80.249.208.2"]);
+        assert_eq!(
+            per_flow.flows,
+            BTreeMap::from([
+                (1, vec!["80.249.208.1".to_string()]),
+                (2, vec!["80.249.208.2".to_string()]),
+            ])
+        );
+
+        // Only traceroutes with a Paris ID other than 0 are counted as flows
+        let unclassified = &diversity.hops[2];
+        assert_eq!(
+            unclassified.addresses,
+            vec!["193.0.4.1", "193.0.4.2", "193.0.4.3"]
+        );
+        assert_eq!(
+            unclassified.flows.keys().copied().collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+    }
+
+    #[test]
+    fn looks_up_alternatives() {
+        let diversity = detect_load_balancing(&measurements()).remove(0);
+        assert!(!diversity.is_balanced(1));
+        assert!(diversity.is_balanced(2));
+        assert!(!diversity.is_balanced(5));
+        assert!(diversity.is_alternative(3, "193.0.0.2"));
+        assert!(!diversity.is_alternative(3, "80.249.208.1"));
+        assert!(!diversity.is_alternative(1, "192.168.1.1"));
+    }
+
+    #[test]
+    fn separates_flow_changes_from_balancing() {
+        // The same Paris ID seeing another address in a later run may be a change of route
+        let changed = vec![
+            traceroute(2, Some(4), hops(&[&["80.249.208.1"], &[K_ROOT]])),
+            traceroute(2, Some(4), hops(&[&["80.249.208.2"], &[K_ROOT]])),
+        ];
+        let diversity = detect_load_balancing(&changed);
+        assert_eq!(diversity[0].hops[0].balancing, Balancing::Unclassified);
+    }
+
+    #[test]
+    fn groups_by_probe_and_target() {
+        let mut unnamed = traceroute_json(3, K_ROOT, hops(&[&["80.249.208.2"], &[K_ROOT]]));
+        unnamed.as_object_mut().unwrap().remove("dst_addr");
+        unnamed["dst_name"] = json!("k.root-servers.net");
+        unnamed["paris_id"] = json!(2);
+
+        let measurements = vec![
+            traceroute(3, Some(1), hops(&[&["80.249.208.1"], &[K_ROOT]])),
+            serde_json::from_value(unnamed).unwrap(),
+            traceroute(4, Some(1), hops(&[&["80.249.208.1"], &["*"], &[K_ROOT]])),
+            traceroute(4, Some(2), hops(&[&["80.249.208.1"], &[K_ROOT]])),
+        ];
+
+        // The traceroute reported only by name is a group of its own, so no group saw more than one
+        // address at a hop
+        assert!(detect_load_balancing(&measurements).is_empty());
+        assert!(detect_load_balancing(&[]).is_empty());
+    }
+}
@@ -0,0 +1,374 @@
+//! Detection of changes in the path taken by successive runs of a traceroute measurement from the
+//! same probe.
+use crate::general::{unix_seconds, UnixTimestamp};
+use crate::measurement::traceroute::asn::{is_special_purpose, PrefixTable};
+use crate::measurement::TracerouteMeasurement;
+use smallvec::SmallVec;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::net::IpAddr;
+
+/// A part of the path which changed between two successive runs
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathChange {
+    pub msm_id: i64,
+    pub prb_id: i64,
+    /// timestamp of the run before the change
+    pub before: UnixTimestamp,
+    /// timestamp of the first run with the new path
+    pub after: UnixTimestamp,
+    /// first hop of the changed part of the path
+    pub first_hop: u32,
+    /// last hop of the changed part of the path
+    pub last_hop: u32,
+    /// addresses seen at each hop from `first_hop` to `last_hop` in the run before the change
+    pub old: Vec<Vec<String>>,
+    /// addresses seen at each hop from `first_hop` to `last_hop` in the run after the change
+    pub new: Vec<Vec<String>>,
+    /// origin ASes of the addresses in `old`, if a prefix table was given
+    pub old_asns: Option<Vec<u32>>,
+    /// origin ASes of the addresses in `new`, if a prefix table was given
+    pub new_asns: Option<Vec<u32>>,
+}
+
+impl PathChange {
+    /// The origin ASes differ between the old and the new path. This is `None` when no prefix
+    /// table was given.
+    pub fn is_as_change(&self) -> Option<bool> {
+        Some(self.old_asns.as_ref()? != self.new_asns.as_ref()?)
+    }
+}
+
+/// Addresses which replied at each hop of a single run, keyed by hop number
+type HopSets<'b> = BTreeMap<u32, SmallVec<[&'b str; 3]>>;
+
+/// Compare each run of a traceroute with the runs from the same probe before it and report the
+/// parts of the path which changed. Runs are grouped by measurement and probe and ordered by
+/// their timestamp, so measurements can be given in any order.
+///
+/// A hop only counts as changed when it replied from addresses which were not seen at that hop
+/// in any of the last `history` runs. Successive runs use different Paris IDs, so per-flow load
+/// balancing makes a hop alternate between several addresses; keeping a history of about as many
+/// runs as there are Paris IDs in use (16 by default) stops these from being reported once each
+/// alternative has been seen. As a consequence, a return to a path seen within the history is not
+/// reported either. Hops without replies are never counted as changed and do not end a changed
+/// part of the path.
+///
+/// When a prefix table is given, the events also carry the origin ASes of the old and new
+/// addresses. Addresses in private or shared address space are left out of these.
+pub fn detect_path_changes<'a: 'b, 'b, I>(
+    measurements: I,
+    history: usize,
+    table: Option<&PrefixTable>,
+) -> Vec<PathChange>
+where
+    I: IntoIterator<Item = &'b TracerouteMeasurement<'a>>,
+{
+    let mut runs: BTreeMap<(i64, i64), Vec<&'b TracerouteMeasurement<'a>>> = BTreeMap::new();
+    for measurement in measurements {
+        runs.entry((measurement.msm_id, measurement.prb_id))
+            .or_default()
+            .push(measurement);
+    }
+
+    let history = history.max(1);
+    let mut changes = Vec::new();
+
+    for ((msm_id, prb_id), mut runs) in runs {
+        runs.sort_by_key(|measurement| unix_seconds(measurement.timestamp));
+
+        let mut previous: VecDeque<(UnixTimestamp, HopSets<'b>)> = VecDeque::new();
+        for measurement in runs {
+            let current: HopSets = measurement.numbered_route().collect();
+
+            if let Some((before, last)) = previous.back() {
+                for (first_hop, last_hop) in changed_ranges(&previous, &current) {
+                    let old = addresses(last, first_hop, last_hop);
+                    let new = addresses(&current, first_hop, last_hop);
+
+                    changes.push(PathChange {
+                        msm_id,
+                        prb_id,
+                        before: *before,
+                        after: measurement.timestamp,
+                        first_hop,
+                        last_hop,
+                        old_asns: table.map(|table| origins(table, &old)),
+                        new_asns: table.map(|table| origins(table, &new)),
+                        old,
+                        new,
+                    });
+                }
+            }
+
+            if previous.len() == history {
+                previous.pop_front();
+            }
+            previous.push_back((measurement.timestamp, current));
+        }
+    }
+
+    changes
+}
+
+/// Find the ranges of hops in the current run which replied only from addresses never seen at
+/// the same hop in the previous runs
+fn changed_ranges(
+    previous: &VecDeque<(UnixTimestamp, HopSets)>,
+    current: &HopSets,
+) -> Vec<(u32, u32)> {
+    let mut ranges = Vec::new();
+    let mut open: Option<(u32, u32)> = None;
+
+    for (hop, addresses) in current {
+        if addresses.is_empty() {
+            continue;
+        }
+
+        let known: BTreeSet<&str> = previous
+            .iter()
+            .filter_map(|(_, sets)| sets.get(hop))
+            .flatten()
+            .copied()
+            .collect();
+
+        if known.is_empty() {
+            continue;
+        }
+
+        if addresses.iter().any(|address| known.contains(address)) {
+            ranges.extend(open.take());
+        } else {
+            open = match open {
+                Some((first, _)) => Some((first, *hop)),
+                None => Some((*hop, *hop)),
+            };
+        }
+    }
+
+    ranges.extend(open);
+    ranges
+}
+
+fn addresses(sets: &HopSets, first_hop: u32, last_hop: u32) -> Vec<Vec<String>> {
+    (first_hop..=last_hop)
+        .map(|hop| {
+            sets.get(&hop)
+                .map(|addresses| {
+                    addresses
+                        .iter()
+                        .map(|address| address.to_string())
+                        .collect()
+                })
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Sorted origin ASes of the public addresses among the hops
+fn origins(table: &PrefixTable, hops: &[Vec<String>]) -> Vec<u32> {
+    let origins: BTreeSet<u32> = hops
+        .iter()
+        .flatten()
+        .filter_map(|address| address.parse::<IpAddr>().ok())
+        .filter(|address| !is_special_purpose(*address))
+        .filter_map(|address| table.lookup(address))
+        .flatten()
+        .copied()
+        .collect();
+
+    origins.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::measurement::traceroute::testing::{hop, hops, traceroute_json};
+    use serde_json::{json, Value};
+
+    /// 2022-12-01 00:00:00
+    const START: i64 = 1669852800;
+
+    /// A run of the traceroute from a probe, `run` intervals of 15 minutes after `START`
+    fn run(prb_id: i64, run: i64, result: Value) -> TracerouteMeasurement<'static> {
+        let mut value = traceroute_json(prb_id, "193.0.14.129", result);
+        value["timestamp"] = json!(START + 900 * run);
+        value["endtime"] = json!(START + 900 * run + 30);
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn ranges(changes: &[PathChange]) -> Vec<(i64, u32, u32)> {
+        changes
+            .iter()
+            .map(|change| (change.prb_id, change.first_hop, change.last_hop))
+            .collect()
+    }
+
+    fn strings(hops: &[&[&str]]) -> Vec<Vec<String>> {
+        hops.iter()
+            .map(|addresses| addresses.iter().map(|a| a.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn reports_changed_hops() {
+        let measurements = [
+            // given out of order
+            run(
+                1,
+                1,
+                hops(&[
+                    &["10.0.0.1"],
+                    &["192.0.2.1"],
+                    &["192.0.2.9"],
+                    &["193.0.14.129"],
+                ]),
+            ),
+            run(
+                1,
+                0,
+                hops(&[
+                    &["10.0.0.1"],
+                    &["192.0.2.1"],
+                    &["192.0.2.2"],
+                    &["193.0.14.129"],
+                ]),
+            ),
+            run(
+                1,
+                2,
+                hops(&[
+                    &["10.0.0.1"],
+                    &["198.51.100.1"],
+                    &["*"],
+                    &["198.51.100.3"],
+                    &["193.0.14.129"],
+                ]),
+            ),
+            run(2, 0, hops(&[&["10.0.0.1"], &["192.0.2.1"]])),
+        ];
+        let changes = detect_path_changes(&measurements, 16, None);
+        assert_eq!(ranges(&changes), vec![(1, 3, 3), (1, 2, 4)]);
+
+        let change = &changes[0];
+        assert_eq!(change.msm_id, 5001);
+        assert_eq!(unix_seconds(change.before), START);
+        assert_eq!(unix_seconds(change.after), START + 900);
+        assert_eq!(change.old, strings(&[&["192.0.2.2"]]));
+        assert_eq!(change.new, strings(&[&["192.0.2.9"]]));
+        assert_eq!(
+            (change.old_asns.as_ref(), change.is_as_change()),
+            (None, None)
+        );
+
+        // The unresponsive hop does not end the changed part of the path
+        let change = &changes[1];
+        assert_eq!(
+            change.new,
+            strings(&[&["198.51.100.1"], &[], &["198.51.100.3"]])
+        );
+        assert_eq!(
+            change.old,
+            strings(&[&["192.0.2.1"], &["192.0.2.9"], &["193.0.14.129"]])
+        );
+
+        assert!(detect_path_changes(&[], 16, None).is_empty());
+    }
+
+    #[test]
+    fn remembers_load_balanced_alternatives() {
+        let measurements: Vec<_> = ["192.0.2.1", "192.0.2.2", "192.0.2.1", "192.0.2.2"]
+            .iter()
+            .enumerate()
+            .map(|(index, balanced)| {
+                run(
+                    1,
+                    index as i64,
+                    hops(&[&["10.0.0.1"], &[balanced], &["193.0.14.129"]]),
+                )
+            })
+            .collect();
+
+        let changes = detect_path_changes(&measurements, 16, None);
+        assert_eq!(ranges(&changes), vec![(1, 2, 2)]);
+
+        // A history of one run only knows the previous alternative
+        let changes = detect_path_changes(&measurements, 1, None);
+        assert_eq!(ranges(&changes), vec![(1, 2, 2), (1, 2, 2), (1, 2, 2)]);
+        assert_eq!(detect_path_changes(&measurements, 0, None).len(), 3);
+    }
+
+    #[test]
+    fn ignores_hops_without_replies() {
+        let measurements = [
+            run(
+                1,
+                0,
+                json!([
+                    hop(1, &["10.0.0.1"]),
+                    hop(2, &["*", "*", "*"]),
+                    {"error": "sendto failed"},
+                    hop(255, &["193.0.14.129"])
+                ]),
+            ),
+            // Replies where there were none before, an error and a late reply at the end
+            run(
+                1,
+                1,
+                json!([
+                    hop(1, &["10.0.0.1"]),
+                    hop(2, &["192.0.2.1"]),
+                    hop(3, &["192.0.2.3"]),
+                    {"error": "sendto failed"},
+                    {"hop": 255, "result": [
+                        {"from": "193.0.14.129", "late": 2, "size": 76, "ttl": 250}
+                    ]}
+                ]),
+            ),
+            run(
+                1,
+                2,
+                json!([hop(1, &["10.0.0.1"]), hop(255, &["192.0.2.255"])]),
+            ),
+        ];
+
+        let changes = detect_path_changes(&measurements, 16, None);
+        assert_eq!(ranges(&changes), vec![(1, 255, 255)]);
+        assert_eq!(changes[0].old, strings(&[&["193.0.14.129"]]));
+        assert_eq!(changes[0].new, strings(&[&["192.0.2.255"]]));
+    }
+
+    #[test]
+    fn annotates_origin_ases() {
+        let table =
+            PrefixTable::from_reader("80.249.208.0/21 1200\n193.0.0.0/21 3333\n".as_bytes())
+                .unwrap();
+        let measurements = [
+            run(
+                1,
+                0,
+                hops(&[&["10.0.0.1"], &["193.0.0.1"], &["193.0.14.129"]]),
+            ),
+            run(
+                1,
+                1,
+                hops(&[
+                    &["10.0.0.1"],
+                    &["80.249.208.1", "10.1.1.1"],
+                    &["193.0.14.129"],
+                ]),
+            ),
+            run(
+                1,
+                2,
+                hops(&[&["10.0.0.1"], &["80.249.208.2"], &["193.0.14.129"]]),
+            ),
+        ];
+
+        let changes = detect_path_changes(&measurements, 16, Some(&table));
+        assert_eq!(ranges(&changes), vec![(1, 2, 2), (1, 2, 2)]);
+        assert_eq!(changes[0].old_asns, Some(vec![3333]));
+        assert_eq!(changes[0].new_asns, Some(vec![1200]));
+        assert_eq!(changes[0].is_as_change(), Some(true));
+        assert_eq!(changes[1].is_as_change(), Some(false));
+    }
+}
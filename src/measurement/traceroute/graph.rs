@@ -0,0 +1,524 @@
+//! Aggregation of many traceroutes into a single IP or AS level topology graph which can be
+//! exported to Graphviz DOT, GraphML or a JSON adjacency list.
+use crate::measurement::stats::median;
+use crate::measurement::traceroute::asn::{is_special_purpose, AsHop, PrefixTable};
+use crate::measurement::TracerouteMeasurement;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::net::IpAddr;
+
+/// A node of the graph
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Node<'g> {
+    /// address of the interface, or the origin ASes of the addresses in an AS level graph such
+    /// as `AS64496`
+    pub id: &'g str,
+    /// number of traceroutes which passed through the node
+    pub count: usize,
+}
+
+/// A link between the nodes of two successive responsive hops. RTTs are in milliseconds.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Edge<'g> {
+    pub from: &'g str,
+    pub to: &'g str,
+    /// number of traceroutes which passed over the link
+    pub count: usize,
+    /// number of those traceroutes in which one or more unresponsive hops were between the two
+    /// nodes, so the link may not be direct
+    pub indirect: usize,
+    /// median difference between the RTT of `to` and the RTT of `from`
+    pub median_rtt_delta: Option<f64>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct EdgeValues {
+    count: usize,
+    indirect: usize,
+    rtt_deltas: Vec<f64>,
+}
+
+/// The nodes which replied at a single hop of a traceroute along with their median RTT
+struct Step {
+    /// one or more hops without any replies came before this one
+    after_gap: bool,
+    nodes: Vec<(String, Option<f64>)>,
+}
+
+/// Graph of the paths taken by a set of traceroutes. The first node of each path is the public
+/// address of the probe, followed by the nodes of each hop which replied. Unresponsive hops are
+/// skipped and the nodes on either side of them are linked, with the link marked as indirect.
+///
+/// Private and shared addresses are ambiguous across probes, so in an IP level graph they are
+/// qualified with the probe ID (`192.168.1.1@6001`). An AS level graph leaves them out along with
+/// addresses which are not in the prefix table.
+#[derive(Clone, Debug)]
+pub struct TopologyGraph<'t> {
+    table: Option<&'t PrefixTable>,
+    nodes: BTreeMap<String, usize>,
+    edges: BTreeMap<(String, String), EdgeValues>,
+}
+
+impl<'t> TopologyGraph<'t> {
+    /// Create a graph with a node for each interface address
+    pub fn ip() -> Self {
+        TopologyGraph {
+            table: None,
+            nodes: BTreeMap::new(),
+            edges: BTreeMap::new(),
+        }
+    }
+
+    /// Create a graph with a node for each AS, using the prefix table to find the origin of each
+    /// address. Successive hops within the same AS are merged into a single node.
+    pub fn asn(table: &'t PrefixTable) -> Self {
+        TopologyGraph {
+            table: Some(table),
+            nodes: BTreeMap::new(),
+            edges: BTreeMap::new(),
+        }
+    }
+
+    /// Add the path of a traceroute to the graph. Nodes and links are counted at most once for
+    /// each traceroute.
+    pub fn add(&mut self, measurement: &TracerouteMeasurement) {
+        let steps = self.steps(measurement);
+
+        let mut nodes: BTreeSet<&str> = BTreeSet::new();
+        let mut edges: BTreeSet<(&str, &str)> = BTreeSet::new();
+
+        for (prev, next) in steps.iter().zip(steps.iter().skip(1)) {
+            for (from, from_rtt) in &prev.nodes {
+                for (to, to_rtt) in &next.nodes {
+                    if from == to || !edges.insert((from, to)) {
+                        continue;
+                    }
+
+                    let values = self.edges.entry((from.clone(), to.clone())).or_default();
+                    values.count += 1;
+                    if next.after_gap {
+                        values.indirect += 1;
+                    }
+                    if let (Some(from_rtt), Some(to_rtt)) = (from_rtt, to_rtt) {
+                        values.rtt_deltas.push(to_rtt - from_rtt);
+                    }
+                }
+            }
+        }
+
+        for (node, _) in steps.iter().flat_map(|step| &step.nodes) {
+            if nodes.insert(node) {
+                *self.nodes.entry(node.clone()).or_default() += 1;
+            }
+        }
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = Node<'_>> {
+        self.nodes
+            .iter()
+            .map(|(id, count)| Node { id, count: *count })
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = Edge<'_>> {
+        self.edges.iter().map(|((from, to), values)| Edge {
+            from,
+            to,
+            count: values.count,
+            indirect: values.indirect,
+            median_rtt_delta: match values.rtt_deltas.is_empty() {
+                true => None,
+                false => Some(median(&mut values.rtt_deltas.clone())),
+            },
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Export the graph in the Graphviz DOT language. Links are labeled with their count and
+    /// median RTT delta, and indirect links are drawn dashed.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph topology {\n");
+
+        for node in self.nodes() {
+            let _ = writeln!(
+                dot,
+                "    \"{}\" [count={}];",
+                escape_dot(node.id),
+                node.count
+            );
+        }
+
+        for edge in self.edges() {
+            let mut label = edge.count.to_string();
+            if let Some(delta) = edge.median_rtt_delta {
+                let _ = write!(label, " / {:.2} ms", delta);
+            }
+
+            let _ = write!(
+                dot,
+                "    \"{}\" -> \"{}\" [label=\"{}\", weight={}, count={}",
+                escape_dot(edge.from),
+                escape_dot(edge.to),
+                label,
+                edge.count,
+                edge.count
+            );
+            if let Some(delta) = edge.median_rtt_delta {
+                let _ = write!(dot, ", rtt_delta={:.3}", delta);
+            }
+            if edge.indirect == edge.count {
+                dot.push_str(", style=dashed");
+            }
+            dot.push_str("];\n");
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Export the graph as a GraphML document with the counts and RTT deltas as attributes
+    pub fn to_graphml(&self) -> String {
+        let mut xml = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+            "  <key id=\"n_count\" for=\"node\" attr.name=\"count\" attr.type=\"int\"/>\n",
+            "  <key id=\"e_count\" for=\"edge\" attr.name=\"count\" attr.type=\"int\"/>\n",
+            "  <key id=\"e_indirect\" for=\"edge\" attr.name=\"indirect\" attr.type=\"int\"/>\n",
+            "  <key id=\"e_rtt_delta\" for=\"edge\" attr.name=\"median_rtt_delta\" ",
+            "attr.type=\"double\"/>\n",
+            "  <graph id=\"topology\" edgedefault=\"directed\">\n",
+        ));
+
+        for node in self.nodes() {
+            let _ = writeln!(
+                xml,
+                "    <node id=\"{}\"><data key=\"n_count\">{}</data></node>",
+                escape_xml(node.id),
+                node.count
+            );
+        }
+
+        for edge in self.edges() {
+            let _ = write!(
+                xml,
+                "    <edge source=\"{}\" target=\"{}\"><data key=\"e_count\">{}</data>\
+                 <data key=\"e_indirect\">{}</data>",
+                escape_xml(edge.from),
+                escape_xml(edge.to),
+                edge.count,
+                edge.indirect
+            );
+            if let Some(delta) = edge.median_rtt_delta {
+                let _ = write!(xml, "<data key=\"e_rtt_delta\">{}</data>", delta);
+            }
+            xml.push_str("</edge>\n");
+        }
+
+        xml.push_str("  </graph>\n</graphml>\n");
+        xml
+    }
+
+    /// Export the graph as a JSON object with the count of each node under `nodes` and the
+    /// outgoing links of each node under `adjacency`
+    pub fn to_json(&self) -> String {
+        let nodes: BTreeMap<&str, usize> = self.nodes().map(|node| (node.id, node.count)).collect();
+
+        let mut adjacency: BTreeMap<&str, Vec<serde_json::Value>> = BTreeMap::new();
+        for edge in self.edges() {
+            adjacency.entry(edge.from).or_default().push(json!({
+                "to": edge.to,
+                "count": edge.count,
+                "indirect": edge.indirect,
+                "median_rtt_delta": edge.median_rtt_delta,
+            }));
+        }
+
+        json!({ "nodes": nodes, "adjacency": adjacency }).to_string()
+    }
+
+    /// Find the nodes of the probe and of each hop which replied
+    fn steps(&self, measurement: &TracerouteMeasurement) -> Vec<Step> {
+        let mut steps = Vec::new();
+
+        if let Some(node) = self.node(&measurement.from, measurement.prb_id) {
+            steps.push(Step {
+                after_gap: false,
+                nodes: vec![(node, Some(0.0))],
+            });
+        }
+
+        let mut after_gap = false;
+        for hop in &measurement.result {
+            if hop.iter_replies().next().is_none() {
+                after_gap = true;
+                continue;
+            }
+
+            let mut rtts: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+            for reply in hop.iter_replies() {
+                if let Some(node) = self.node(&reply.from, measurement.prb_id) {
+                    let values = rtts.entry(node).or_default();
+                    values.extend(reply.rtt.ok().map(f64::from));
+                }
+            }
+
+            if !rtts.is_empty() {
+                steps.push(Step {
+                    after_gap: std::mem::take(&mut after_gap),
+                    nodes: rtts
+                        .into_iter()
+                        .map(|(node, mut values)| {
+                            let rtt = (!values.is_empty()).then(|| median(&mut values));
+                            (node, rtt)
+                        })
+                        .collect(),
+                });
+            }
+        }
+
+        steps
+    }
+
+    /// The node an address belongs to, if it is part of the graph
+    fn node(&self, address: &str, prb_id: i64) -> Option<String> {
+        let special = address.parse::<IpAddr>().map_or(true, is_special_purpose);
+
+        match self.table {
+            None if special => Some(format!("{}@{}", address, prb_id)),
+            None => Some(address.to_string()),
+            Some(_) if special => None,
+            Some(table) => {
+                let origins = table.lookup(address.parse().ok()?)?;
+                Some(AsHop::Asn(origins.to_vec()).to_string())
+            }
+        }
+    }
+}
+
+impl<'a, 'b: 'a, 't> Extend<&'a TracerouteMeasurement<'b>> for TopologyGraph<'t> {
+    fn extend<T: IntoIterator<Item = &'a TracerouteMeasurement<'b>>>(&mut self, iter: T) {
+        for measurement in iter {
+            self.add(measurement);
+        }
+    }
+}
+
+fn escape_dot(id: &str) -> String {
+    id.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::measurement::traceroute::testing::{hop, hops, traceroute_json};
+    use serde_json::Value;
+
+    const K_ROOT: &str = "193.0.14.129";
+
+    /// Prefixes of the probes, an exchange, RIPE NCC and K-root
+    const TABLE: &str = "\
+85.10.0.0/16 64500
+80.249.208.0/21 1200
+193.0.0.0/21 3333
+193.0.14.0/23 25152
+";
+
+    fn traceroute(prb_id: i64, from: &str, result: Value) -> TracerouteMeasurement<'static> {
+        let mut value = traceroute_json(prb_id, K_ROOT, result);
+        value["from"] = json!(from);
+        serde_json::from_value(value).unwrap()
+    }
+
+    /// Two probes behind home routers reaching K-root through the same exchange. Hop 3 of the
+    /// first probe timed out. RTTs are 1.5 ms per hop.
+    fn measurements() -> Vec<TracerouteMeasurement<'static>> {
+        vec![
+            traceroute(
+                1,
+                "85.10.0.5",
+                hops(&[
+                    &["192.168.1.1"],
+                    &["80.249.208.1"],
+                    &["*", "*", "*"],
+                    &["193.0.0.1"],
+                    &[K_ROOT],
+                ]),
+            ),
+            traceroute(
+                2,
+                "85.10.0.6",
+                hops(&[
+                    &["192.168.1.1"],
+                    &["80.249.208.1"],
+                    &["193.0.0.1"],
+                    &[K_ROOT],
+                ]),
+            ),
+        ]
+    }
+
+    fn edge<'g>(graph: &'g TopologyGraph, from: &str, to: &str) -> Edge<'g> {
+        graph
+            .edges()
+            .find(|edge| edge.from == from && edge.to == to)
+            .unwrap_or_else(|| panic!("no edge from {} to {}", from, to))
+    }
+
+    fn count(graph: &TopologyGraph, id: &str) -> Option<usize> {
+        graph
+            .nodes()
+            .find(|node| node.id == id)
+            .map(|node| node.count)
+    }
+
+    #[test]
+    fn builds_ip_graph() {
+        let mut graph = TopologyGraph::ip();
+        graph.extend(&measurements());
+
+        assert_eq!(graph.nodes().count(), 7);
+        assert_eq!(count(&graph, "80.249.208.1"), Some(2));
+        assert_eq!(count(&graph, "192.168.1.1@1"), Some(1));
+        assert_eq!(count(&graph, "192.168.1.1@2"), Some(1));
+        assert_eq!(count(&graph, "192.168.1.1"), None);
+
+        assert_eq!(
+            edge(&graph, "85.10.0.5", "192.168.1.1@1"),
+            Edge {
+                from: "85.10.0.5",
+                to: "192.168.1.1@1",
+                count: 1,
+                indirect: 0,
+                median_rtt_delta: Some(1.5),
+            }
+        );
+
+        // Only the first probe saw a gap before 193.0.0.1
+        let exchange = edge(&graph, "80.249.208.1", "193.0.0.1");
+        assert_eq!((exchange.count, exchange.indirect), (2, 1));
+        assert_eq!(exchange.median_rtt_delta, Some(2.25));
+        assert_eq!(edge(&graph, "193.0.0.1", K_ROOT).count, 2);
+        assert_eq!(graph.edges().count(), 6);
+    }
+
+    #[test]
+    fn handles_errors_late_replies_and_loops() {
+        let late = traceroute(
+            3,
+            "85.10.0.7",
+            json!([
+                {"error": "sendto failed"},
+                {"hop": 2, "result": [
+                    {"from": "80.249.208.1", "late": 1, "size": 76, "ttl": 250}
+                ]},
+                hop(3, &["*", "*", "*"]),
+                hop(255, &[K_ROOT, K_ROOT, "*"]),
+            ]),
+        );
+        let looping = traceroute(
+            4,
+            "85.10.0.8",
+            hops(&[
+                &["80.249.208.1"],
+                &["80.249.208.1"],
+                &["193.0.0.1"],
+                &["80.249.208.1"],
+                &["193.0.0.1"],
+            ]),
+        );
+
+        let mut graph = TopologyGraph::ip();
+        graph.add(&late);
+        graph.add(&looping);
+
+        let after_error = edge(&graph, "85.10.0.7", "80.249.208.1");
+        assert_eq!((after_error.count, after_error.indirect), (1, 1));
+        assert_eq!(after_error.median_rtt_delta, None);
+        let to_hop_255 = edge(&graph, "80.249.208.1", K_ROOT);
+        assert_eq!(
+            (to_hop_255.indirect, to_hop_255.median_rtt_delta),
+            (1, None)
+        );
+
+        // Each node and link counts once per traceroute and nodes are not linked to themselves
+        assert_eq!(count(&graph, "80.249.208.1"), Some(2));
+        assert_eq!(edge(&graph, "80.249.208.1", "193.0.0.1").count, 1);
+        assert_eq!(edge(&graph, "193.0.0.1", "80.249.208.1").count, 1);
+        assert!(graph.edges().all(|edge| edge.from != edge.to));
+    }
+
+    #[test]
+    fn builds_as_graph() {
+        let table = PrefixTable::from_reader(TABLE.as_bytes()).unwrap();
+        let mut graph = TopologyGraph::asn(&table);
+        graph.extend(&measurements());
+
+        let nodes: Vec<(&str, usize)> = graph.nodes().map(|node| (node.id, node.count)).collect();
+        assert_eq!(
+            nodes,
+            vec![("AS1200", 2), ("AS25152", 2), ("AS3333", 2), ("AS64500", 2)]
+        );
+
+        // The home routers are left out, so the probes link straight to the exchange
+        let access = edge(&graph, "AS64500", "AS1200");
+        assert_eq!((access.count, access.indirect), (2, 0));
+        assert_eq!(access.median_rtt_delta, Some(3.0));
+        assert_eq!(edge(&graph, "AS1200", "AS3333").indirect, 1);
+        assert_eq!(graph.edges().count(), 3);
+    }
+
+    #[test]
+    fn exports_graph() {
+        let table = PrefixTable::from_reader(TABLE.as_bytes()).unwrap();
+        let mut graph = TopologyGraph::asn(&table);
+        graph.add(&measurements()[0]);
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph topology {\n"));
+        assert!(dot.contains("    \"AS3333\" [count=1];\n"));
+        assert!(dot.contains(
+            "    \"AS1200\" -> \"AS3333\" [label=\"1 / 3.00 ms\", weight=1, count=1, \
+             rtt_delta=3.000, style=dashed];\n"
+        ));
+        assert!(dot.contains(
+            "    \"AS64500\" -> \"AS1200\" [label=\"1 / 3.00 ms\", weight=1, count=1, \
+             rtt_delta=3.000];\n"
+        ));
+
+        let xml = graph.to_graphml();
+        assert!(xml.contains("<node id=\"AS25152\"><data key=\"n_count\">1</data></node>"));
+        assert!(xml.contains(
+            "<edge source=\"AS3333\" target=\"AS25152\"><data key=\"e_count\">1</data>\
+             <data key=\"e_indirect\">0</data><data key=\"e_rtt_delta\">1.5</data></edge>"
+        ));
+
+        let exported: Value = serde_json::from_str(&graph.to_json()).unwrap();
+        assert_eq!(exported["nodes"]["AS1200"], json!(1));
+        assert_eq!(
+            exported["adjacency"]["AS1200"],
+            json!([{"to": "AS3333", "count": 1, "indirect": 1, "median_rtt_delta": 3.0}])
+        );
+
+        assert_eq!(escape_dot("a\"b\\c"), "a\\\"b\\\\c");
+        assert_eq!(escape_xml("<a & \"b\">"), "&lt;a &amp; &quot;b&quot;&gt;");
+    }
+
+    #[test]
+    fn exports_empty_graph() {
+        let graph = TopologyGraph::ip();
+        assert!(graph.is_empty());
+        assert_eq!(graph.to_dot(), "digraph topology {\n}\n");
+        assert_eq!(graph.to_json(), r#"{"adjacency":{},"nodes":{}}"#);
+        assert!(graph.to_graphml().ends_with("  </graph>\n</graphml>\n"));
+    }
+}
@@ -0,0 +1,283 @@
+//! RTT statistics for each hop of a traceroute and attribution of increases in latency to the
+//! link between two hops.
+use crate::measurement::stats::median;
+use crate::measurement::traceroute::{RoundTripTime, TraceHop, TraceReply, Traceroute};
+use crate::measurement::Response;
+
+/// RTT statistics over the packets sent for a single hop. RTTs are in milliseconds.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HopStats {
+    /// number of replies which arrived in time
+    pub replies: usize,
+    /// number of replies which arrived after the probe stopped waiting for them. These do not
+    /// have an RTT.
+    pub late: usize,
+    /// number of packets without a reply
+    pub timeouts: usize,
+    /// number of packets which could not be sent
+    pub errors: usize,
+    pub min: Option<f64>,
+    pub median: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl<'a> TraceHop<'a> {
+    /// Summarize the RTTs of the replies for this hop. A hop which failed with an error has no
+    /// packets.
+    pub fn rtt_stats(&self) -> HopStats {
+        let mut stats = HopStats {
+            replies: 0,
+            late: 0,
+            timeouts: 0,
+            errors: 0,
+            min: None,
+            median: None,
+            max: None,
+        };
+
+        let responses = match self {
+            TraceHop::Error { .. } => return stats,
+            TraceHop::Result { result, .. } => result,
+        };
+
+        let mut rtts = Vec::new();
+        for response in responses {
+            match response {
+                Response::Reply(TraceReply {
+                    rtt: RoundTripTime::OnTime(rtt),
+                    ..
+                }) => rtts.push(f64::from(*rtt)),
+                Response::Reply(TraceReply {
+                    rtt: RoundTripTime::Late(_),
+                    ..
+                }) => stats.late += 1,
+                Response::Timeout { .. } => stats.timeouts += 1,
+                Response::Error { .. } | Response::DnsError { .. } => stats.errors += 1,
+            }
+        }
+
+        stats.replies = rtts.len();
+        if !rtts.is_empty() {
+            stats.median = Some(median(&mut rtts));
+            stats.min = rtts.first().copied();
+            stats.max = rtts.last().copied();
+        }
+
+        stats
+    }
+}
+
+/// The change in RTT over the link between two successive hops which replied. RTTs are in
+/// milliseconds.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LatencyJump<'b> {
+    pub from_hop: u32,
+    pub to_hop: u32,
+    /// addresses which replied at `from_hop`
+    pub from: Vec<&'b str>,
+    /// addresses which replied at `to_hop`
+    pub to: Vec<&'b str>,
+    /// median RTT at `from_hop`
+    pub before: f64,
+    /// lowest median RTT of `to_hop` and every hop after it
+    pub after: f64,
+}
+
+impl<'b> LatencyJump<'b> {
+    /// Increase in RTT over the link. This is negative if the RTT decreased.
+    pub fn increase(&self) -> f64 {
+        self.after - self.before
+    }
+}
+
+impl<'a> Traceroute<'a> {
+    /// RTT statistics for each hop along with its hop number
+    pub fn hop_stats(&self) -> Vec<(u32, HopStats)> {
+        self.numbered_hops()
+            .map(|(number, hop)| (number, hop.rtt_stats()))
+            .collect()
+    }
+
+    /// The change in RTT over each link between successive hops with an RTT, in order of hops.
+    /// Hops without an RTT are skipped, so a link may span several hops.
+    ///
+    /// Routers often answer traceroutes slowly compared to forwarding traffic, which shows as a
+    /// spike in RTT at a single hop. To only attribute increases which are carried on to the rest
+    /// of the path, the RTT after a link is the lowest median RTT of all later hops.
+    pub fn latency_jumps(&self) -> Vec<LatencyJump<'_>> {
+        let hops: Vec<(u32, &TraceHop, f64)> = self
+            .numbered_hops()
+            .filter_map(|(number, hop)| Some((number, hop, hop.rtt_stats().median?)))
+            .collect();
+
+        let mut jumps = Vec::new();
+        for index in 1..hops.len() {
+            let (from_hop, from, before) = hops[index - 1];
+            let (to_hop, to, _) = hops[index];
+            let after = hops[index..]
+                .iter()
+                .map(|(_, _, rtt)| *rtt)
+                .fold(f64::INFINITY, f64::min);
+
+            jumps.push(LatencyJump {
+                from_hop,
+                to_hop,
+                from: addresses(from),
+                to: addresses(to),
+                before,
+                after,
+            });
+        }
+
+        jumps
+    }
+
+    /// The link with the largest increase in RTT, see [`Traceroute::latency_jumps`]. Returns
+    /// `None` if the RTT does not increase anywhere along the path.
+    pub fn largest_latency_jump(&self) -> Option<LatencyJump<'_>> {
+        self.latency_jumps()
+            .into_iter()
+            .filter(|jump| jump.increase() > 0.0)
+            .max_by(|a, b| a.increase().total_cmp(&b.increase()))
+    }
+}
+
+/// Sorted unique addresses which replied at a hop
+fn addresses<'b>(hop: &'b TraceHop) -> Vec<&'b str> {
+    let mut addresses: Vec<&str> = hop
+        .iter_replies()
+        .map(|reply| reply.from.as_ref())
+        .collect();
+    addresses.sort_unstable();
+    addresses.dedup();
+    addresses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::measurement::traceroute::testing::{hop, reply, traceroute};
+    use serde_json::{json, Value};
+
+    /// A hop with a reply from each address with the given RTT
+    fn timed_hop(number: u32, replies: &[(&str, f64)]) -> Value {
+        let result: Vec<Value> = replies
+            .iter()
+            .map(|(from, rtt)| reply(from, *rtt))
+            .collect();
+        json!({"hop": number, "result": result})
+    }
+
+    /// A route where 193.0.0.1 answers slowly, followed by an error hop and the destination at
+    /// hop 255
+    fn route() -> Value {
+        json!([
+            timed_hop(1, &[("192.168.1.1", 1.0), ("192.168.1.1", 3.0), ("192.168.1.1", 2.0)]),
+            hop(2, &["*", "*", "*"]),
+            {"hop": 3, "result": [
+                reply("80.249.208.2", 7.0),
+                reply("80.249.208.1", 5.0),
+                {"from": "80.249.208.1", "late": 2, "size": 76, "ttl": 250}
+            ]},
+            timed_hop(4, &[("193.0.0.1", 40.0), ("193.0.0.1", 40.0), ("193.0.0.1", 40.0)]),
+            {"error": "sendto failed"},
+            {"hop": 6, "result": [
+                reply("193.0.14.129", 20.0),
+                reply("193.0.14.129", 22.0),
+                {"error": "Network is unreachable"}
+            ]},
+            timed_hop(255, &[("193.0.14.129", 22.0), ("193.0.14.129", 22.0)]),
+        ])
+    }
+
+    #[test]
+    fn summarizes_hops() {
+        let measurement = traceroute(1, "193.0.14.129", route());
+        let stats = measurement.hop_stats();
+        let numbers: Vec<u32> = stats.iter().map(|(number, _)| *number).collect();
+        assert_eq!(numbers, vec![1, 2, 3, 4, 5, 6, 255]);
+
+        assert_eq!(
+            stats[0].1,
+            HopStats {
+                replies: 3,
+                late: 0,
+                timeouts: 0,
+                errors: 0,
+                min: Some(1.0),
+                median: Some(2.0),
+                max: Some(3.0),
+            }
+        );
+        assert_eq!((stats[1].1.timeouts, stats[1].1.median), (3, None));
+        assert_eq!((stats[2].1.replies, stats[2].1.late), (2, 1));
+        assert_eq!(stats[2].1.median, Some(6.0));
+        assert_eq!((stats[5].1.replies, stats[5].1.errors), (2, 1));
+
+        // A hop which failed with an error sent no packets
+        assert_eq!(
+            stats[4].1,
+            HopStats {
+                replies: 0,
+                late: 0,
+                timeouts: 0,
+                errors: 0,
+                min: None,
+                median: None,
+                max: None,
+            }
+        );
+    }
+
+    #[test]
+    fn attributes_latency_to_links() {
+        let measurement = traceroute(1, "193.0.14.129", route());
+        let jumps = measurement.latency_jumps();
+
+        let links: Vec<(u32, u32, f64, f64)> = jumps
+            .iter()
+            .map(|jump| (jump.from_hop, jump.to_hop, jump.before, jump.after))
+            .collect();
+        assert_eq!(
+            links,
+            vec![
+                (1, 3, 2.0, 6.0),
+                (3, 4, 6.0, 21.0),
+                (4, 6, 40.0, 21.0),
+                (6, 255, 21.0, 22.0),
+            ]
+        );
+        assert_eq!(jumps[0].to, vec!["80.249.208.1", "80.249.208.2"]);
+        assert_eq!(jumps[2].increase(), -19.0);
+
+        // The slow answers of 193.0.0.1 are not carried on to the destination
+        let largest = measurement.largest_latency_jump().unwrap();
+        assert_eq!((largest.from_hop, largest.to_hop), (3, 4));
+        assert_eq!(largest.from, vec!["80.249.208.1", "80.249.208.2"]);
+        assert_eq!(largest.to, vec!["193.0.0.1"]);
+        assert_eq!(largest.increase(), 15.0);
+    }
+
+    #[test]
+    fn finds_no_increase() {
+        let decreasing = traceroute(
+            1,
+            "193.0.14.129",
+            json!([
+                timed_hop(1, &[("192.168.1.1", 9.0)]),
+                hop(2, &["*"]),
+                timed_hop(3, &[("193.0.14.129", 4.0)]),
+            ]),
+        );
+        assert_eq!(decreasing.latency_jumps().len(), 1);
+        assert_eq!(decreasing.largest_latency_jump(), None);
+
+        let unreachable = traceroute(1, "193.0.14.129", json!([hop(1, &["*", "*", "*"])]));
+        assert!(unreachable.latency_jumps().is_empty());
+        assert_eq!(unreachable.largest_latency_jump(), None);
+
+        let empty = traceroute(1, "193.0.14.129", json!([]));
+        assert!(empty.hop_stats().is_empty());
+        assert!(empty.latency_jumps().is_empty());
+    }
+}
@@ -0,0 +1,317 @@
+//! Detection of forwarding loops within a traceroute and of how widely a loop was seen by the
+//! probes measuring the same target.
+use crate::measurement::traceroute::Traceroute;
+use crate::measurement::{Measurement, TracerouteMeasurement};
+use smallvec::SmallVec;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Packets forwarded around the same routers more than once
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoutingLoop<'b> {
+    /// first hop of the loop
+    pub first_hop: u32,
+    /// last hop which followed the loop
+    pub last_hop: u32,
+    /// number of hops from `first_hop` to `last_hop`, counted by their position in the result.
+    /// Hop numbers can skip ahead, such as to hop 255 after a run of hops without replies.
+    pub span: u32,
+    /// one address from each hop of a single turn of the loop, in order. Hops without a reply
+    /// are left out.
+    pub cycle: Vec<&'b str>,
+    /// number of hops in a single turn of the loop, counted like `span`
+    pub period: u32,
+}
+
+impl<'b> RoutingLoop<'b> {
+    /// Number of times the packets went around the loop. An incomplete last turn is not counted.
+    pub fn turns(&self) -> u32 {
+        self.span / self.period
+    }
+
+    /// Sorted addresses in the loop. This is the same wherever the loop was entered.
+    pub fn addresses(&self) -> Vec<&'b str> {
+        let addresses: BTreeSet<&str> = self.cycle.iter().copied().collect();
+        addresses.into_iter().collect()
+    }
+}
+
+impl<'a> Measurement<'a, Traceroute<'a>> {
+    /// Find forwarding loops using the addresses of each hop from [`Self::iter_route`]. A loop
+    /// starts where an address replies again two or more hops later, and the distance between
+    /// the two hops is the length of a turn. The loop continues as long as each hop shares an
+    /// address with the hop a turn before it, or either hop did not reply.
+    ///
+    /// An address replying at two adjacent hops is not counted, since routers which forward
+    /// packets with an expired TTL cause the same pattern.
+    pub fn routing_loops(&self) -> Vec<RoutingLoop<'_>> {
+        let hops: Vec<(u32, SmallVec<[&str; 3]>)> = self.numbered_route().collect();
+        let mut loops = Vec::new();
+        let mut index = 0;
+
+        while index < hops.len() {
+            let period = hops[index]
+                .1
+                .iter()
+                .filter_map(|address| {
+                    hops.iter()
+                        .skip(index + 2)
+                        .position(|(_, later)| later.contains(address))
+                        .map(|position| position + 2)
+                })
+                .min();
+
+            let period = match period {
+                Some(period) => period,
+                None => {
+                    index += 1;
+                    continue;
+                }
+            };
+
+            let mut end = index + period;
+            while let Some((_, next)) = hops.get(end + 1) {
+                let (_, turn_before) = &hops[end + 1 - period];
+                if !next.is_empty()
+                    && !turn_before.is_empty()
+                    && !next.iter().any(|address| turn_before.contains(address))
+                {
+                    break;
+                }
+                end += 1;
+            }
+
+            // Hops without replies at the end of the traceroute do not show the loop went on
+            while hops[end].1.is_empty() {
+                end -= 1;
+            }
+
+            loops.push(RoutingLoop {
+                first_hop: hops[index].0,
+                last_hop: hops[end].0,
+                span: (end - index + 1) as u32,
+                cycle: hops[index..index + period]
+                    .iter()
+                    .filter_map(|(_, addresses)| addresses.first().copied())
+                    .collect(),
+                period: period as u32,
+            });
+
+            index = end + 1;
+        }
+
+        loops
+    }
+}
+
+/// A loop seen by traceroutes towards the same target
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoopPrevalence {
+    /// address of the target, or its name if the address was not reported
+    pub target: String,
+    /// sorted addresses in the loop, see [`RoutingLoop::addresses`]
+    pub addresses: Vec<String>,
+    /// probes which saw the loop, in order of ID
+    pub probes: Vec<i64>,
+    /// number of traceroutes which saw the loop
+    pub traceroutes: usize,
+}
+
+/// Probes which saw a loop and the number of traceroutes it was seen in
+type Sightings = (BTreeSet<i64>, usize);
+
+/// Find the loops in each traceroute and count how many probes saw each of them. Loops are
+/// identified by their target and the addresses in them, so a loop counts as the same whichever
+/// router in it the probes entered it at. The results are sorted by target and then from the
+/// most to the least probes.
+pub fn loop_prevalence<'a: 'b, 'b, I>(measurements: I) -> Vec<LoopPrevalence>
+where
+    I: IntoIterator<Item = &'b TracerouteMeasurement<'a>>,
+{
+    let mut seen: BTreeMap<(&'b str, Vec<&'b str>), Sightings> = BTreeMap::new();
+
+    for measurement in measurements {
+        let target = measurement
+            .dst_addr
+            .as_deref()
+            .unwrap_or(&measurement.dst_name);
+
+        let loops: BTreeSet<Vec<&str>> = measurement
+            .routing_loops()
+            .iter()
+            .map(RoutingLoop::addresses)
+            .collect();
+
+        for addresses in loops {
+            let (probes, traceroutes) = seen.entry((target, addresses)).or_default();
+            probes.insert(measurement.prb_id);
+            *traceroutes += 1;
+        }
+    }
+
+    let mut prevalence: Vec<LoopPrevalence> = seen
+        .into_iter()
+        .map(
+            |((target, addresses), (probes, traceroutes))| LoopPrevalence {
+                target: target.to_string(),
+                addresses: addresses
+                    .iter()
+                    .map(|address| address.to_string())
+                    .collect(),
+                probes: probes.into_iter().collect(),
+                traceroutes,
+            },
+        )
+        .collect();
+
+    prevalence.sort_by(|a, b| {
+        a.target
+            .cmp(&b.target)
+            .then(b.probes.len().cmp(&a.probes.len()))
+    });
+    prevalence
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::measurement::traceroute::testing::{hop, hops, traceroute, traceroute_json};
+    use serde_json::json;
+
+    const K_ROOT: &str = "193.0.14.129";
+
+    #[test]
+    fn finds_loops() {
+        let measurement = traceroute(
+            1,
+            K_ROOT,
+            hops(&[
+                &["80.249.208.1"],
+                &["2.2.2.2"],
+                &["3.3.3.3", "3.3.3.4"],
+                &["2.2.2.2"],
+                &["3.3.3.4"],
+                &["2.2.2.2"],
+                &["193.0.0.1"],
+                &["4.4.4.4"],
+                &["5.5.5.5"],
+                &["4.4.4.4"],
+            ]),
+        );
+
+        assert_eq!(
+            measurement.routing_loops(),
+            vec![
+                RoutingLoop {
+                    first_hop: 2,
+                    last_hop: 6,
+                    span: 5,
+                    cycle: vec!["2.2.2.2", "3.3.3.3"],
+                    period: 2,
+                },
+                RoutingLoop {
+                    first_hop: 8,
+                    last_hop: 10,
+                    span: 3,
+                    cycle: vec!["4.4.4.4", "5.5.5.5"],
+                    period: 2,
+                },
+            ]
+        );
+        assert_eq!(measurement.routing_loops()[0].turns(), 2);
+        assert_eq!(measurement.routing_loops()[1].turns(), 1);
+    }
+
+    #[test]
+    fn counts_turns_by_position() {
+        // The probe gave up after two hops without replies and jumped to hop 255
+        let measurement = traceroute(
+            1,
+            K_ROOT,
+            json!([
+                hop(1, &["80.249.208.1"]),
+                hop(2, &["2.2.2.2"]),
+                hop(3, &["3.3.3.3"]),
+                hop(4, &["2.2.2.2"]),
+                hop(5, &["*", "*", "*"]),
+                {"error": "sendto failed"},
+                hop(255, &["3.3.3.3"]),
+            ]),
+        );
+
+        let loops = measurement.routing_loops();
+        assert_eq!(loops.len(), 1);
+        assert_eq!((loops[0].first_hop, loops[0].last_hop), (2, 255));
+        assert_eq!((loops[0].span, loops[0].period), (6, 2));
+        assert_eq!(loops[0].turns(), 3);
+    }
+
+    #[test]
+    fn trims_unresponsive_hops() {
+        let measurement = traceroute(
+            1,
+            K_ROOT,
+            hops(&[&["2.2.2.2"], &["*"], &["2.2.2.2"], &["*"], &["*"]]),
+        );
+
+        let loops = measurement.routing_loops();
+        assert_eq!(loops.len(), 1);
+        assert_eq!((loops[0].first_hop, loops[0].last_hop), (1, 3));
+        assert_eq!(loops[0].cycle, vec!["2.2.2.2"]);
+        assert_eq!(loops[0].turns(), 1);
+        assert_eq!(loops[0].addresses(), vec!["2.2.2.2"]);
+    }
+
+    #[test]
+    fn ignores_adjacent_repeats() {
+        let measurement = traceroute(
+            1,
+            K_ROOT,
+            hops(&[&["80.249.208.1"], &["193.0.0.1"], &["193.0.0.1"], &[K_ROOT]]),
+        );
+        assert!(measurement.routing_loops().is_empty());
+        assert!(traceroute(1, K_ROOT, json!([])).routing_loops().is_empty());
+    }
+
+    #[test]
+    fn counts_probes_seeing_loops() {
+        let mut unnamed =
+            traceroute_json(5, K_ROOT, hops(&[&["6.6.6.6"], &["7.7.7.7"], &["6.6.6.6"]]));
+        unnamed.as_object_mut().unwrap().remove("dst_addr");
+        unnamed["dst_name"] = json!("k.root-servers.net");
+
+        let measurements = vec![
+            traceroute(1, K_ROOT, hops(&[&["2.2.2.2"], &["3.3.3.3"], &["2.2.2.2"]])),
+            // Entering the loop at another router
+            traceroute(2, K_ROOT, hops(&[&["3.3.3.3"], &["2.2.2.2"], &["3.3.3.3"]])),
+            traceroute(2, K_ROOT, hops(&[&["3.3.3.3"], &["2.2.2.2"], &["3.3.3.3"]])),
+            traceroute(3, K_ROOT, hops(&[&["4.4.4.4"], &["5.5.5.5"], &["4.4.4.4"]])),
+            traceroute(4, K_ROOT, hops(&[&["80.249.208.1"], &[K_ROOT]])),
+            serde_json::from_value(unnamed).unwrap(),
+        ];
+
+        assert_eq!(
+            loop_prevalence(&measurements),
+            vec![
+                LoopPrevalence {
+                    target: K_ROOT.to_string(),
+                    addresses: vec!["2.2.2.2".to_string(), "3.3.3.3".to_string()],
+                    probes: vec![1, 2],
+                    traceroutes: 3,
+                },
+                LoopPrevalence {
+                    target: K_ROOT.to_string(),
+                    addresses: vec!["4.4.4.4".to_string(), "5.5.5.5".to_string()],
+                    probes: vec![3],
+                    traceroutes: 1,
+                },
+                LoopPrevalence {
+                    target: "k.root-servers.net".to_string(),
+                    addresses: vec!["6.6.6.6".to_string(), "7.7.7.7".to_string()],
+                    probes: vec![5],
+                    traceroutes: 1,
+                },
+            ]
+        );
+        assert!(loop_prevalence(&[]).is_empty());
+    }
+}
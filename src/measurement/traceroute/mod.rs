@@ -6,6 +6,17 @@ use serde::{Deserialize, Serialize};
 use smallvec::{smallvec, SmallVec};
 use std::borrow::Cow;
 
+pub mod asn;
+pub mod balancing;
+pub mod change;
+pub mod graph;
+pub mod latency;
+pub mod loops;
+pub mod mpls;
+pub mod termination;
+#[cfg(test)]
+pub(crate) mod testing;
+
 /// https://atlas.ripe.net/docs/apis/result-format/#version-4570
 #[derive(Clone, Serialize, Deserialize, Debug)]
 #[cfg_attr(feature = "strict", serde(deny_unknown_fields))]
@@ -19,6 +30,7 @@ pub struct Traceroute<'a> {
     /// name of the destination (string)
     pub dst_name: Cow<'a, str>,
     /// Unix timestamp for end of measurement (int)
+    #[cfg_attr(feature = "chrono", serde(with = "chrono::serde::ts_seconds"))]
     pub endtime: UnixTimestamp,
     /// variation for the Paris mode of traceroute (int)
     ///
@@ -62,6 +74,20 @@ impl<'a> TraceHop<'a> {
     }
 }
 
+impl<'a> Traceroute<'a> {
+    /// Pair each hop with its hop number. Hops which failed with an error do not report their
+    /// number, so they are numbered one after the previous hop, or 1 if they come first.
+    pub(crate) fn numbered_hops<'b>(&'b self) -> impl Iterator<Item = (u32, &'b TraceHop<'a>)> {
+        self.result.iter().scan(0, |previous: &mut u32, hop| {
+            *previous = match hop {
+                TraceHop::Result { hop: number, .. } => *number,
+                TraceHop::Error { .. } => previous.saturating_add(1),
+            };
+            Some((*previous, hop))
+        })
+    }
+}
+
 impl<'a> Response<'a, TraceReply<'a>> {
     pub fn rtt(&self) -> Option<f32> {
         if let Response::Reply(TraceReply { rtt, .. }) = self {
@@ -165,14 +191,14 @@ fn icmp_default_ttl() -> i64 {
     1
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, Hash, Eq, PartialEq)]
 #[serde(untagged)]
 pub enum ErrorTypes {
     Code(i32),
     Icmp(ICMPError),
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, Hash, Eq, PartialEq)]
 pub enum ICMPError {
     #[serde(rename = "N")]
     NetworkUnreachable,
@@ -187,14 +213,22 @@ pub enum ICMPError {
 }
 
 impl<'a> Measurement<'a, Traceroute<'a>> {
-    pub fn iter_route(&self) -> TracerouteIPIter {
+    pub fn iter_route(&self) -> TracerouteIPIter<'_> {
         TracerouteIPIter {
             trace: self,
             index: 0,
         }
     }
 
-    pub fn iter_route_with_timeouts(&self) -> impl Iterator<Item = SmallVec<[Cow<str>; 3]>> {
+    /// Pair the addresses of each hop from [`Self::iter_route`] with the number of the hop
+    pub(crate) fn numbered_route(&self) -> impl Iterator<Item = (u32, SmallVec<[&str; 3]>)> {
+        // The first item of the route is the probe itself
+        self.numbered_hops()
+            .zip(self.iter_route().skip(1))
+            .map(|((number, _), addresses)| (number, addresses))
+    }
+
+    pub fn iter_route_with_timeouts(&self) -> impl Iterator<Item = SmallVec<[Cow<'_, str>; 3]>> {
         std::iter::once(smallvec!["placeholder".into()])
             .chain(
                 self.iter_route()
@@ -205,7 +239,7 @@ impl<'a> Measurement<'a, Traceroute<'a>> {
             .flat_map(
                 |((_, prev), (count, x))| -> Box<dyn Iterator<Item = SmallVec<[Cow<str>; 3]>>> {
                     if !x.is_empty() {
-                        Box::new(std::iter::repeat(x).take(count))
+                        Box::new(itertools::repeat_n(x, count))
                     } else {
                         Box::new((0..count).map(move |n| {
                             smallvec![format!("Timeout {}: {}", n, prev.join(",")).into()]
@@ -258,3 +292,46 @@ impl<'a> Iterator for TracerouteIPIter<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::general::unix_seconds;
+    use crate::measurement::traceroute::testing::{hop, traceroute};
+    use crate::measurement::TracerouteMeasurement;
+    use serde_json::json;
+
+    #[test]
+    fn reads_endtime_as_unix_seconds() {
+        let measurement: TracerouteMeasurement = serde_json::from_str(
+            r#"{"af":4,"dst_addr":"193.0.14.129","dst_name":"193.0.14.129","endtime":1669852830,
+            "from":"203.0.113.5","fw":5080,"lts":20,"msm_id":5001,"msm_name":"Traceroute",
+            "paris_id":1,"prb_id":6001,"proto":"UDP","result":[{"hop":1,"result":[
+            {"from":"193.0.14.129","rtt":1.5,"size":76,"ttl":64}]}],"size":48,
+            "src_addr":"192.168.1.10","timestamp":1669852800,"type":"traceroute"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(unix_seconds(measurement.timestamp), 1669852800);
+        assert_eq!(unix_seconds(measurement.endtime), 1669852830);
+    }
+
+    #[test]
+    fn numbers_error_hops_after_previous_hop() {
+        let numbers = |result| -> Vec<u32> {
+            traceroute(1, "193.0.14.129", result)
+                .numbered_hops()
+                .map(|(number, _)| number)
+                .collect()
+        };
+        let error = json!({"error": "sendto failed"});
+
+        assert_eq!(
+            numbers(json!([hop(1, &["192.0.2.1"]), hop(255, &["*"]), error])),
+            vec![1, 255, 256]
+        );
+        assert_eq!(
+            numbers(json!([error, error, hop(5, &["192.0.2.1"])])),
+            vec![1, 2, 5]
+        );
+    }
+}
@@ -0,0 +1,342 @@
+//! Detection of MPLS tunnels along the path of a traceroute, both from the label stacks routers
+//! quote in ICMP extensions (RFC 4950) and from the TTL signatures of routers which do not.
+use crate::measurement::traceroute::{MPLSData, TraceHop, TraceReply, Traceroute};
+
+/// How a tunnel revealed itself, following the taxonomy of Donnet et al., "Revealing MPLS Tunnels
+/// Obscured from Traceroute" (2012)
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum TunnelKind {
+    /// the routers in the tunnel reply to traceroute and quote their label stack
+    Explicit,
+    /// the routers in the tunnel reply to traceroute without quoting a label stack. They are
+    /// recognised by the TTL quoted from the expired packet, which is not decremented while the
+    /// packet is in the tunnel and so grows by one at each hop.
+    Implicit,
+    /// the routers in the tunnel do not reply to traceroute and only the egress router quotes a
+    /// label stack. The tunnel is recognised by the label TTL not having expired at the egress.
+    Opaque {
+        /// estimated number of hidden routers, from the difference between the initial label TTL
+        /// of 255 and the label TTL quoted by the egress router
+        hidden: u8,
+    },
+}
+
+/// A hop on the edge of or within a tunnel
+#[derive(Clone, Debug)]
+pub struct TunnelHop<'b> {
+    pub hop: u32,
+    /// address the hop replied from
+    pub address: &'b str,
+    /// label stack quoted by the hop, from the top of the stack to the bottom
+    pub labels: &'b [MPLSData],
+    /// time-to-live of the expired packet quoted by the hop
+    pub quoted_ttl: i64,
+}
+
+/// A single MPLS tunnel
+#[derive(Clone, Debug)]
+pub struct MplsTunnel<'b> {
+    pub kind: TunnelKind,
+    /// last hop before the tunnel, if it replied
+    pub ingress: Option<TunnelHop<'b>>,
+    /// hop where the tunnel ends. For explicit and implicit tunnels this is the first hop after
+    /// the tunnel, if it replied. An opaque tunnel ends at the hop which quoted the labels.
+    pub egress: Option<TunnelHop<'b>>,
+    /// routers inside the tunnel which replied, in order of hop
+    pub hops: Vec<TunnelHop<'b>>,
+}
+
+impl<'b> MplsTunnel<'b> {
+    /// Every label seen within the tunnel, from the top of the stack, in order of hop
+    pub fn labels(&self) -> impl Iterator<Item = i64> + '_ {
+        self.hops
+            .iter()
+            .chain(&self.egress)
+            .flat_map(|hop| hop.labels.iter().map(|entry| entry.label))
+    }
+}
+
+impl<'a> Traceroute<'a> {
+    /// Find the MPLS tunnels along the path. Hops without a reply end a tunnel, since it is not
+    /// known whether they were a part of it.
+    ///
+    /// Tunnels whose routers neither reply nor quote labels at the egress cannot be detected from
+    /// a single traceroute.
+    pub fn mpls_tunnels(&self) -> Vec<MplsTunnel<'_>> {
+        let hops: Vec<Option<Candidate>> = self
+            .numbered_hops()
+            .map(|(number, hop)| self.candidate(number, hop))
+            .collect();
+        let hop = |index: usize| -> Option<TunnelHop> {
+            hops.get(index)?
+                .as_ref()
+                .map(|candidate| candidate.hop.clone())
+        };
+        let labeled = |index: usize| matches!(hop(index), Some(hop) if !hop.labels.is_empty());
+        let signature = |index: usize| hops.get(index)?.as_ref()?.signature;
+
+        let mut tunnels = Vec::new();
+        let mut index = 0;
+
+        while index < hops.len() {
+            let start = index;
+            let ingress = start.checked_sub(1).and_then(hop);
+
+            if labeled(index) {
+                while labeled(index) {
+                    index += 1;
+                }
+
+                let run: Vec<TunnelHop> = (start..index).filter_map(hop).collect();
+                let label_ttl = run[0].labels[0].ttl;
+
+                tunnels.push(if run.len() == 1 && label_ttl > 1 {
+                    MplsTunnel {
+                        kind: TunnelKind::Opaque {
+                            hidden: (255 - label_ttl.clamp(0, 255)) as u8,
+                        },
+                        ingress,
+                        egress: run.into_iter().next(),
+                        hops: Vec::new(),
+                    }
+                } else {
+                    MplsTunnel {
+                        kind: TunnelKind::Explicit,
+                        ingress,
+                        egress: hop(index),
+                        hops: run,
+                    }
+                });
+            } else if matches!(signature(index), Some(ttl) if ttl >= 2) {
+                index += 1;
+                while signature(index).is_some()
+                    && signature(index) == signature(index - 1).map(|ttl| ttl + 1)
+                {
+                    index += 1;
+                }
+
+                tunnels.push(MplsTunnel {
+                    kind: TunnelKind::Implicit,
+                    ingress,
+                    egress: hop(index),
+                    hops: (start..index).filter_map(hop).collect(),
+                });
+            } else {
+                index += 1;
+            }
+        }
+
+        tunnels
+    }
+
+    /// The reply representing a hop, preferring replies which quote a label stack
+    fn candidate<'b>(&'b self, number: u32, hop: &'b TraceHop<'a>) -> Option<Candidate<'b>> {
+        let (reply, labels) = hop
+            .iter_replies()
+            .find_map(|reply| Some((reply, labels(reply)?)))
+            .or_else(|| Some((hop.iter_replies().next()?, &[][..])))?;
+
+        // Replies from the destination and ICMP errors other than time exceeded quote a TTL which
+        // is not affected by tunnels
+        let signature = hop
+            .iter_replies()
+            .find(|reply| {
+                labels.is_empty()
+                    && reply.err.is_none()
+                    && self.dst_addr.as_deref() != Some(reply.from.as_ref())
+            })
+            .map(|reply| reply.ittl);
+
+        Some(Candidate {
+            hop: TunnelHop {
+                hop: number,
+                address: &reply.from,
+                labels,
+                quoted_ttl: reply.ittl,
+            },
+            signature,
+        })
+    }
+}
+
+struct Candidate<'b> {
+    hop: TunnelHop<'b>,
+    /// quoted TTL of a reply without labels which can show an implicit tunnel
+    signature: Option<i64>,
+}
+
+fn labels<'b>(reply: &'b TraceReply) -> Option<&'b [MPLSData]> {
+    reply
+        .icmpext
+        .as_ref()?
+        .obj
+        .iter()
+        .find_map(|obj| obj.mpls.as_deref())
+        .filter(|labels| !labels.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::measurement::traceroute::testing::{hop, reply, traceroute};
+    use serde_json::{json, Value};
+
+    const K_ROOT: &str = "193.0.14.129";
+
+    /// A time exceeded reply quoting a single label with the given label TTL
+    fn labeled(from: &str, label: i64, label_ttl: i64) -> Value {
+        let mut reply = reply(from, 10.0);
+        reply["icmpext"] = json!({
+            "version": 1, "rfc4884": 0,
+            "obj": [{"class": 1, "type": 1, "mpls": [
+                {"exp": 0, "label": label, "s": 1, "ttl": label_ttl}
+            ]}]
+        });
+        reply
+    }
+
+    /// A time exceeded reply quoting the TTL of the expired packet
+    fn quoting(from: &str, ittl: i64) -> Value {
+        let mut reply = reply(from, 10.0);
+        reply["ittl"] = json!(ittl);
+        reply
+    }
+
+    fn numbered(number: u32, result: &[Value]) -> Value {
+        json!({"hop": number, "result": result})
+    }
+
+    fn summary(tunnel: &MplsTunnel) -> (TunnelKind, Option<u32>, Vec<u32>, Option<u32>) {
+        (
+            tunnel.kind,
+            tunnel.ingress.as_ref().map(|hop| hop.hop),
+            tunnel.hops.iter().map(|hop| hop.hop).collect(),
+            tunnel.egress.as_ref().map(|hop| hop.hop),
+        )
+    }
+
+    #[test]
+    fn finds_explicit_tunnel() {
+        let measurement = traceroute(
+            1,
+            K_ROOT,
+            json!([
+                hop(1, &["192.168.1.1"]),
+                hop(2, &["80.249.208.1"]),
+                numbered(
+                    3,
+                    &[reply("193.0.0.1", 10.0), labeled("193.0.0.1", 1001, 1)]
+                ),
+                numbered(4, &[labeled("193.0.0.2", 1002, 1)]),
+                hop(5, &["193.0.0.3"]),
+                hop(6, &[K_ROOT]),
+            ]),
+        );
+
+        let tunnels = measurement.mpls_tunnels();
+        assert_eq!(tunnels.len(), 1);
+        assert_eq!(
+            summary(&tunnels[0]),
+            (TunnelKind::Explicit, Some(2), vec![3, 4], Some(5))
+        );
+        assert_eq!(tunnels[0].hops[0].address, "193.0.0.1");
+        assert_eq!(tunnels[0].labels().collect::<Vec<_>>(), vec![1001, 1002]);
+    }
+
+    #[test]
+    fn finds_opaque_tunnel() {
+        let measurement = traceroute(
+            1,
+            K_ROOT,
+            json!([
+                hop(1, &["80.249.208.1"]),
+                numbered(2, &[labeled("193.0.0.3", 1003, 252)]),
+                hop(3, &[K_ROOT]),
+            ]),
+        );
+
+        let tunnels = measurement.mpls_tunnels();
+        assert_eq!(tunnels.len(), 1);
+        assert_eq!(
+            summary(&tunnels[0]),
+            (TunnelKind::Opaque { hidden: 3 }, Some(1), vec![], Some(2))
+        );
+        assert_eq!(tunnels[0].labels().collect::<Vec<_>>(), vec![1003]);
+    }
+
+    #[test]
+    fn finds_implicit_tunnel() {
+        let measurement = traceroute(
+            1,
+            K_ROOT,
+            json!([
+                hop(1, &["80.249.208.1"]),
+                numbered(2, &[quoting("193.0.0.1", 2)]),
+                numbered(3, &[quoting("193.0.0.2", 3)]),
+                numbered(4, &[quoting("193.0.0.3", 4)]),
+                hop(5, &["193.0.4.1"]),
+                numbered(6, &[quoting("193.0.4.2", 2)]),
+                hop(7, &["*", "*", "*"]),
+                numbered(255, &[quoting(K_ROOT, 2)]),
+            ]),
+        );
+
+        let tunnels = measurement.mpls_tunnels();
+        let summaries: Vec<_> = tunnels.iter().map(summary).collect();
+        assert_eq!(
+            summaries,
+            vec![
+                (TunnelKind::Implicit, Some(1), vec![2, 3, 4], Some(5)),
+                (TunnelKind::Implicit, Some(5), vec![6], None),
+            ]
+        );
+        assert_eq!(tunnels[0].hops[2].quoted_ttl, 4);
+        assert_eq!(tunnels[0].labels().count(), 0);
+    }
+
+    #[test]
+    fn ignores_unrelated_quoted_ttls() {
+        let mut unreachable = quoting("193.0.0.1", 2);
+        unreachable["err"] = json!("N");
+
+        // Neither an ICMP error nor the destination quoting a TTL shows a tunnel
+        let measurement = traceroute(
+            1,
+            K_ROOT,
+            json!([
+                hop(1, &["80.249.208.1"]),
+                numbered(2, &[unreachable]),
+                numbered(3, &[quoting(K_ROOT, 3)]),
+            ]),
+        );
+        assert!(measurement.mpls_tunnels().is_empty());
+    }
+
+    #[test]
+    fn ends_tunnels_at_unresponsive_hops() {
+        let measurement = traceroute(
+            1,
+            K_ROOT,
+            json!([
+                hop(1, &["80.249.208.1"]),
+                numbered(2, &[labeled("193.0.0.1", 1001, 1)]),
+                hop(3, &["*", "*", "*"]),
+                numbered(4, &[labeled("193.0.0.2", 1002, 1)]),
+                {"error": "sendto failed"},
+                hop(6, &[K_ROOT]),
+            ]),
+        );
+
+        let summaries: Vec<_> = measurement.mpls_tunnels().iter().map(summary).collect();
+        assert_eq!(
+            summaries,
+            vec![
+                (TunnelKind::Explicit, Some(1), vec![2], None),
+                (TunnelKind::Explicit, None, vec![4], None),
+            ]
+        );
+
+        let empty = traceroute(1, K_ROOT, json!([]));
+        assert!(empty.mpls_tunnels().is_empty());
+    }
+}
@@ -0,0 +1,330 @@
+//! Classification of how a traceroute ended, from whether it reached its destination to why it
+//! stopped short of it.
+use crate::measurement::traceroute::{ErrorTypes, TraceHop, Traceroute};
+use crate::measurement::Measurement;
+use std::net::IpAddr;
+
+/// The reason a traceroute stopped
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum Termination<'b> {
+    /// the destination replied
+    Reached,
+    /// the last hop which replied sent an ICMP error, such as an unreachable destination or a
+    /// filter prohibiting the traffic
+    IcmpError(ErrorTypes),
+    /// the last hop which replied was part of a forwarding loop, see
+    /// [`Measurement::routing_loops`]
+    Loop {
+        /// address of the last hop which replied
+        address: &'b str,
+        /// first hop of the loop
+        first_hop: u32,
+    },
+    /// the probe failed to send the packets for the last hop
+    HopError(&'b str),
+    /// the traceroute gave up after a run of hops which did not reply
+    GapLimit {
+        /// number of hops without replies at the end of the traceroute
+        unresponsive: usize,
+    },
+    /// the traceroute ran out of hops before reaching the destination
+    Unreached,
+}
+
+/// The last hop which replied to a traceroute
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub struct LastResponsive<'b> {
+    /// number of the hop, which is its distance from the probe
+    pub hop: u32,
+    pub address: &'b str,
+}
+
+/// How a traceroute ended along with the furthest hop it saw
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub struct TerminationReport<'b> {
+    pub termination: Termination<'b>,
+    /// the last hop which replied. When the destination was reached this is the destination.
+    pub last_responsive: Option<LastResponsive<'b>>,
+}
+
+impl<'a> Measurement<'a, Traceroute<'a>> {
+    /// Decide how the traceroute ended. The destination counts as reached when any hop replied
+    /// from `dst_addr`, even if it did so with an ICMP error such as the port unreachable sent
+    /// in reply to UDP traceroutes. Otherwise the reasons are checked in the order of the variants
+    /// of [`Termination`] and the first one which applies is used.
+    pub fn termination(&self) -> TerminationReport<'_> {
+        let destination = self
+            .dst_addr
+            .as_deref()
+            .and_then(|addr| addr.parse::<IpAddr>().ok());
+
+        let mut last_responsive = None;
+        for (number, hop) in self.numbered_hops() {
+            let reached = hop.iter_replies().find(|reply| {
+                destination.is_some() && reply.from.parse::<IpAddr>().ok() == destination
+            });
+
+            if let Some(reply) = reached {
+                return TerminationReport {
+                    termination: Termination::Reached,
+                    last_responsive: Some(LastResponsive {
+                        hop: number,
+                        address: &reply.from,
+                    }),
+                };
+            }
+
+            if let Some(reply) = hop.iter_replies().next() {
+                last_responsive = Some((number, hop, reply));
+            }
+        }
+
+        let termination = match last_responsive {
+            Some((number, hop, reply)) => {
+                if let Some(err) = hop.iter_replies().find_map(|reply| reply.err) {
+                    Termination::IcmpError(err)
+                } else if let Some(routing_loop) = self
+                    .routing_loops()
+                    .into_iter()
+                    .find(|routing_loop| routing_loop.last_hop == number)
+                {
+                    Termination::Loop {
+                        address: &reply.from,
+                        first_hop: routing_loop.first_hop,
+                    }
+                } else {
+                    self.trailing_termination()
+                }
+            }
+            None => self.trailing_termination(),
+        };
+
+        TerminationReport {
+            termination,
+            last_responsive: last_responsive.map(|(hop, _, reply)| LastResponsive {
+                hop,
+                address: &reply.from,
+            }),
+        }
+    }
+}
+
+impl<'a> Traceroute<'a> {
+    /// Look at the hops after the last responsive hop
+    fn trailing_termination(&self) -> Termination<'_> {
+        let mut unresponsive = 0;
+
+        for hop in self.result.iter().rev() {
+            match hop {
+                TraceHop::Error { error } if unresponsive == 0 => {
+                    return Termination::HopError(error)
+                }
+                _ if hop.iter_replies().next().is_none() => unresponsive += 1,
+                _ => break,
+            }
+        }
+
+        match unresponsive {
+            0 => Termination::Unreached,
+            unresponsive => Termination::GapLimit { unresponsive },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::measurement::traceroute::testing::{hop, hops, reply, traceroute};
+    use crate::measurement::traceroute::ICMPError;
+    use serde_json::{json, Value};
+
+    const K_ROOT: &str = "193.0.14.129";
+
+    /// A reply carrying an ICMP error
+    fn error_reply(from: &str, err: &str) -> Value {
+        let mut reply = reply(from, 10.0);
+        reply["err"] = json!(err);
+        reply
+    }
+
+    fn last_responsive(hop: u32, address: &str) -> Option<LastResponsive<'_>> {
+        Some(LastResponsive { hop, address })
+    }
+
+    #[test]
+    fn reaches_destination() {
+        let measurement = traceroute(
+            1,
+            K_ROOT,
+            json!([
+                hop(1, &["192.168.1.1"]),
+                hop(2, &["*", "*", "*"]),
+                {"hop": 3, "result": [error_reply(K_ROOT, "p"), {"x": "*"}]},
+                hop(4, &["*", "*", "*"]),
+            ]),
+        );
+
+        // The port unreachable of a UDP traceroute comes from the destination
+        let report = measurement.termination();
+        assert_eq!(report.termination, Termination::Reached);
+        assert_eq!(report.last_responsive, last_responsive(3, K_ROOT));
+    }
+
+    #[test]
+    fn stops_at_icmp_error() {
+        let measurement = traceroute(
+            1,
+            K_ROOT,
+            json!([
+                hop(1, &["80.249.208.1"]),
+                {"hop": 2, "result": [reply("193.0.0.1", 3.0), error_reply("193.0.0.1", "N")]},
+                hop(3, &["*", "*", "*"]),
+            ]),
+        );
+
+        let report = measurement.termination();
+        assert_eq!(
+            report.termination,
+            Termination::IcmpError(ErrorTypes::Icmp(ICMPError::NetworkUnreachable))
+        );
+        assert_eq!(report.last_responsive, last_responsive(2, "193.0.0.1"));
+    }
+
+    #[test]
+    fn stops_in_loop() {
+        let measurement = traceroute(
+            1,
+            K_ROOT,
+            hops(&[
+                &["192.168.1.1"],
+                &["80.249.208.1"],
+                &["2.2.2.2"],
+                &["3.3.3.3"],
+                &["2.2.2.2"],
+                &["3.3.3.3"],
+                &["*", "*", "*"],
+            ]),
+        );
+
+        // The loop started where 2.2.2.2 first replied, not where 3.3.3.3 did
+        let report = measurement.termination();
+        assert_eq!(
+            report.termination,
+            Termination::Loop {
+                address: "3.3.3.3",
+                first_hop: 3,
+            }
+        );
+        assert_eq!(report.last_responsive, last_responsive(6, "3.3.3.3"));
+    }
+
+    #[test]
+    fn stops_in_loop_at_hop_255() {
+        let measurement = traceroute(
+            1,
+            K_ROOT,
+            json!([
+                hop(1, &["80.249.208.1"]),
+                hop(2, &["2.2.2.2"]),
+                hop(3, &["3.3.3.3"]),
+                hop(4, &["2.2.2.2"]),
+                {"error": "sendto failed"},
+                hop(255, &["2.2.2.2"]),
+            ]),
+        );
+
+        let report = measurement.termination();
+        assert_eq!(
+            report.termination,
+            Termination::Loop {
+                address: "2.2.2.2",
+                first_hop: 2,
+            }
+        );
+        assert_eq!(report.last_responsive, last_responsive(255, "2.2.2.2"));
+    }
+
+    #[test]
+    fn ignores_repeats_outside_loops() {
+        // Replying at adjacent hops is not a loop, nor is a loop which ended before the last hop
+        let repeated = traceroute(
+            1,
+            K_ROOT,
+            hops(&[&["80.249.208.1"], &["193.0.0.1"], &["193.0.0.1"]]),
+        );
+        assert_eq!(repeated.termination().termination, Termination::Unreached);
+
+        let escaped = traceroute(
+            1,
+            K_ROOT,
+            hops(&[&["2.2.2.2"], &["3.3.3.3"], &["2.2.2.2"], &["193.0.0.1"]]),
+        );
+        assert_eq!(escaped.termination().termination, Termination::Unreached);
+    }
+
+    #[test]
+    fn stops_at_hop_error() {
+        let measurement = traceroute(
+            1,
+            K_ROOT,
+            json!([
+                hop(1, &["80.249.208.1"]),
+                hop(2, &["*", "*", "*"]),
+                {"error": "sendto failed: Network is unreachable"},
+            ]),
+        );
+
+        let report = measurement.termination();
+        assert_eq!(
+            report.termination,
+            Termination::HopError("sendto failed: Network is unreachable")
+        );
+        assert_eq!(report.last_responsive, last_responsive(1, "80.249.208.1"));
+    }
+
+    #[test]
+    fn gives_up_after_gap() {
+        let measurement = traceroute(
+            1,
+            K_ROOT,
+            json!([
+                hop(1, &["80.249.208.1"]),
+                hop(2, &["193.0.0.1"]),
+                hop(3, &["*", "*", "*"]),
+                {"error": "sendto failed"},
+                hop(5, &["*", "*", "*"]),
+                hop(6, &["*", "*", "*"]),
+                hop(7, &["*", "*", "*"]),
+                hop(255, &["*", "*", "*"]),
+            ]),
+        );
+
+        let report = measurement.termination();
+        assert_eq!(
+            report.termination,
+            Termination::GapLimit { unresponsive: 6 }
+        );
+        assert_eq!(report.last_responsive, last_responsive(2, "193.0.0.1"));
+
+        let silent = traceroute(1, K_ROOT, hops(&[&["*"], &["*"]]));
+        let report = silent.termination();
+        assert_eq!(
+            report.termination,
+            Termination::GapLimit { unresponsive: 2 }
+        );
+        assert_eq!(report.last_responsive, None);
+    }
+
+    #[test]
+    fn runs_out_of_hops() {
+        let measurement = traceroute(1, K_ROOT, hops(&[&["80.249.208.1"], &["193.0.0.1"]]));
+        let report = measurement.termination();
+        assert_eq!(report.termination, Termination::Unreached);
+        assert_eq!(report.last_responsive, last_responsive(2, "193.0.0.1"));
+
+        let empty = traceroute(1, K_ROOT, json!([]));
+        let report = empty.termination();
+        assert_eq!(report.termination, Termination::Unreached);
+        assert_eq!(report.last_responsive, None);
+    }
+}
@@ -0,0 +1,50 @@
+//! Builders for traceroute results shared by the tests of the traceroute analysis.
+use crate::measurement::TracerouteMeasurement;
+use serde_json::{json, Value};
+
+/// A traceroute from a probe with the given hops, see [`hops`]
+pub(crate) fn traceroute(
+    prb_id: i64,
+    dst_addr: &str,
+    result: Value,
+) -> TracerouteMeasurement<'static> {
+    serde_json::from_value(traceroute_json(prb_id, dst_addr, result)).unwrap()
+}
+
+/// The result of a UDP traceroute as reported by the probe, for tests which change its fields
+pub(crate) fn traceroute_json(prb_id: i64, dst_addr: &str, result: Value) -> Value {
+    json!({
+        "af": 4, "dst_addr": dst_addr, "dst_name": dst_addr, "endtime": 1669852830,
+        "from": "203.0.113.5", "fw": 5080, "lts": 20, "msm_id": 5001, "msm_name": "Traceroute",
+        "paris_id": 1, "prb_id": prb_id, "proto": "UDP", "result": result, "size": 48,
+        "src_addr": "192.168.1.10", "timestamp": 1669852800, "type": "traceroute"
+    })
+}
+
+/// Hops numbered from 1, each sending one packet per address. An address of `*` stands for a
+/// packet which timed out.
+pub(crate) fn hops(route: &[&[&str]]) -> Value {
+    route
+        .iter()
+        .enumerate()
+        .map(|(index, addresses)| hop(index as u32 + 1, addresses))
+        .collect()
+}
+
+/// A single hop with a reply from each address, or a timeout for `*`
+pub(crate) fn hop(number: u32, addresses: &[&str]) -> Value {
+    let result: Vec<Value> = addresses
+        .iter()
+        .map(|address| match *address {
+            "*" => json!({"x": "*"}),
+            address => reply(address, number as f64 * 1.5),
+        })
+        .collect();
+
+    json!({"hop": number, "result": result})
+}
+
+/// A time exceeded reply
+pub(crate) fn reply(from: &str, rtt: f64) -> Value {
+    json!({"from": from, "rtt": rtt, "size": 76, "ttl": 250})
+}